@@ -1,7 +1,7 @@
 use hex_color::HexColor;
 use ass_parser::{AssFile, AssFileOptions};
 use ass_parser::{ScriptInfo, V4Format, Events, Dialogue};
-use rand;
+use ass_parser::colour::{ColourGenerator, PaletteOptions};
 
 fn main() {
     let hexcolor = AssFileOptions::get_ass_color(HexColor::YELLOW);
@@ -9,12 +9,17 @@ fn main() {
     let mut ass_file = AssFile::new();
     let mut event = Events::default();
 
+    // the same seed gives the same colours every time, readable against the black outline
+    let mut options = PaletteOptions::default();
+    options.set_min_contrast(&[HexColor::BLACK], 4.5);
+    let mut colours = ColourGenerator::from_seed(42, options);
+
     for srt_seg in srt_file.iter() {
         let start = &srt_seg.start;
         let end = &srt_seg.end;
         let text = &srt_seg.text;
 
-        let random_color:HexColor = rand::random();
+        let random_color = colours.next_colour();
 
         let dialogue = Dialogue::default()
             .set_start(&start)
@@ -0,0 +1,296 @@
+//! `ass-tool` converts, inspects and edits subtitle files from the command line.
+//!
+//! Every command reads a file or stdin and writes a file or stdout, so commands can be chained
+//! in a shell pipeline:
+//!
+//! ```shell
+//! ass-tool convert RapGod.srt | ass-tool shift -1.5s | ass-tool strip-tags -o RapGod.vtt
+//! ```
+
+use std::io::{Read, Write};
+use std::process::ExitCode;
+
+use ass_parser::{AssFile, SubtitleFormat, V4Format};
+use ass_parser::lint::{LintOptions, LintRule, Severity};
+
+const USAGE: &str = "\
+Usage: ass-tool <COMMAND> [OPTIONS] [INPUT]
+
+Commands:
+  convert                            Convert between subtitle formats
+  shift <OFFSET>                     Move every dialogue by OFFSET (1.5s, -250ms, -0:00:02.00)
+  info                               Print the resolution, styles, event count and duration
+  lint                               Check the subtitles for problems
+  strip-tags                         Remove the style override codes from every dialogue
+  restyle <STYLE> <FIELD=VALUE>...   Change fields of a style, e.g. Fontsize=24
+
+Options:
+  -o, --output <FILE>   Write to FILE instead of stdout
+  -f, --from <FORMAT>   Format of the input (ass, srt, vtt, sbv, lrc, smi)
+  -t, --to <FORMAT>     Format of the output
+      --disable <RULE>  Don't run a lint rule, e.g. line-too-long (lint only)
+      --max-cps <N>     Most characters per second allowed (lint only, default 21)
+      --max-line-length <N>
+                        Most characters in a line allowed (lint only, default 42)
+  -h, --help            Print this message
+
+INPUT is read from stdin when it is missing or `-`. Formats are taken from the file extensions
+and default to ass. The output of convert is ass, every other command keeps the input format.";
+
+struct Options {
+    command: String,
+    arguments: Vec<String>,
+    input: Option<String>,
+    output: Option<String>,
+    from: Option<SubtitleFormat>,
+    to: Option<SubtitleFormat>,
+    lint: LintOptions,
+}
+
+fn main() -> ExitCode {
+    match run(std::env::args().skip(1).collect()) {
+        Ok(code) => code,
+        Err(message) => {
+            eprintln!("ass-tool: {}", message);
+            ExitCode::FAILURE
+        },
+    }
+}
+
+fn run(args: Vec<String>) -> Result<ExitCode, String> {
+    if args.is_empty() || args.iter().any(|arg| arg == "-h" || arg == "--help") {
+        println!("{}", USAGE);
+        return Ok(ExitCode::SUCCESS);
+    }
+    let options = parse_args(args)?;
+    let (mut ass_file, input_format) = read_input(&options)?;
+
+    let default_output = if options.command == "convert" { SubtitleFormat::Ass } else { input_format };
+    let output_format = options.to
+        .or_else(|| options.output.as_deref().and_then(SubtitleFormat::from_extension))
+        .unwrap_or(default_output);
+
+    match options.command.as_str() {
+        "convert" => {},
+        "shift" => {
+            let offset = options.arguments.first().ok_or("shift needs an OFFSET")?;
+            let offset = parse_offset(offset).ok_or(format!("invalid offset {:?}", offset))?;
+            ass_file.components.events.shift(offset);
+        },
+        "info" => {
+            print_info(&ass_file);
+            return Ok(ExitCode::SUCCESS);
+        },
+        "lint" => return Ok(lint(&ass_file, &options.lint)),
+        "strip-tags" => {
+            ass_file.components.events.strip_tags();
+        },
+        "restyle" => restyle(&mut ass_file, &options.arguments)?,
+        _ => unreachable!(),
+    }
+
+    write_output(&ass_file, output_format, options.output.as_deref())?;
+    Ok(ExitCode::SUCCESS)
+}
+
+fn parse_args(args: Vec<String>) -> Result<Options, String> {
+    let mut args = args.into_iter();
+    let command = args.next().ok_or("missing command")?;
+    if !matches!(command.as_str(), "convert" | "shift" | "info" | "lint" | "strip-tags" | "restyle") {
+        return Err(format!("unknown command {:?}, see ass-tool --help", command));
+    }
+    let mut positional = Vec::new();
+    let mut output = None;
+    let mut from = None;
+    let mut to = None;
+    let mut lint = LintOptions::default();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-o" | "--output" => output = Some(args.next().ok_or("--output needs a FILE")?),
+            "-f" | "--from" | "-t" | "--to" => {
+                let name = args.next().ok_or(format!("{} needs a FORMAT", arg))?;
+                let format = SubtitleFormat::from_name(&name).ok_or(format!("unknown format {:?}", name))?;
+                if arg == "-f" || arg == "--from" {
+                    from = Some(format);
+                } else {
+                    to = Some(format);
+                }
+            },
+            "--disable" => {
+                let id = args.next().ok_or("--disable needs a RULE")?;
+                lint.disable(LintRule::from_id(&id).ok_or(format!("unknown lint rule {:?}", id))?);
+            },
+            "--max-cps" => {
+                let value = args.next().ok_or("--max-cps needs a number")?;
+                lint.set_max_cps(value.parse().map_err(|_| format!("invalid --max-cps {:?}", value))?);
+            },
+            "--max-line-length" => {
+                let value = args.next().ok_or("--max-line-length needs a number")?;
+                lint.set_max_line_length(value.parse().map_err(|_| format!("invalid --max-line-length {:?}", value))?);
+            },
+            _ => positional.push(arg),
+        }
+    }
+
+    // shift and restyle take their own arguments before INPUT: shift takes exactly one OFFSET and
+    // every restyle argument after STYLE is a FIELD=VALUE pair.
+    let input = match command.as_str() {
+        "shift" if positional.len() > 1 => positional.pop(),
+        "restyle" if positional.len() > 1 && positional.last().is_some_and(|last| !last.contains('=')) => positional.pop(),
+        "shift" | "restyle" => None,
+        _ => positional.pop(),
+    };
+    let expected = match command.as_str() {
+        "shift" => 1,
+        "restyle" => usize::MAX,
+        _ => 0,
+    };
+    if positional.len() > expected {
+        return Err(format!("unexpected argument {:?}, see ass-tool --help", positional[expected]));
+    }
+
+    Ok(Options {
+        command,
+        arguments: positional,
+        input: input.filter(|input| input != "-"),
+        output,
+        from,
+        to,
+        lint,
+    })
+}
+
+fn read_input(options: &Options) -> Result<(AssFile, SubtitleFormat), String> {
+    let input = options.input.as_deref();
+    if let Some(filename) = input.filter(|filename| filename.to_ascii_lowercase().ends_with(".stl")) {
+        let ass_file = AssFile::from_stl(filename).map_err(|e| format!("{}: {}", filename, e))?;
+        return Ok((ass_file, SubtitleFormat::Ass));
+    }
+
+    let format = options.from
+        .or_else(|| input.and_then(SubtitleFormat::from_extension))
+        .unwrap_or(SubtitleFormat::Ass);
+    let mut contents = String::new();
+    match input {
+        Some(filename) => {
+            contents = std::fs::read_to_string(filename).map_err(|e| format!("{}: {}", filename, e))?;
+        },
+        None => {
+            std::io::stdin().read_to_string(&mut contents).map_err(|e| format!("stdin: {}", e))?;
+        },
+    }
+
+    let ass_file = AssFile::from_contents(&contents, format).map_err(|e| e.to_string())?;
+    Ok((ass_file, format))
+}
+
+fn write_output(ass_file: &AssFile, format: SubtitleFormat, output: Option<&str>) -> Result<(), String> {
+    let contents = ass_file.to_contents(format);
+    match output {
+        Some(filename) => std::fs::write(filename, contents).map_err(|e| format!("{}: {}", filename, e)),
+        None => std::io::stdout().write_all(contents.as_bytes()).map_err(|e| format!("stdout: {}", e)),
+    }
+}
+
+/// Parse an offset such as `1.5s`, `-250ms`, `1.5` (seconds) or `-0:00:02.00`.
+fn parse_offset(offset: &str) -> Option<i64> {
+    let (sign, offset) = match offset.strip_prefix('-') {
+        Some(rest) => (-1.0, rest),
+        None => (1.0, offset.trim_start_matches('+')),
+    };
+    let seconds = if let Some(ms) = offset.strip_suffix("ms") {
+        ms.parse::<f64>().ok()? / 1000.0
+    } else if offset.contains(':') {
+        offset.split(':').try_fold(0.0, |total, part| part.parse::<f64>().ok().map(|part| total * 60.0 + part))?
+    } else {
+        offset.trim_end_matches('s').parse::<f64>().ok()?
+    };
+
+    Some((sign * seconds * 1000.0).round() as i64)
+}
+
+fn format_duration(ms: i64) -> String {
+    let centis = (ms.max(0) + 5) / 10;
+    format!("{}:{:02}:{:02}.{:02}", centis / 360_000, (centis / 6000) % 60, (centis / 100) % 60, centis % 100)
+}
+
+fn print_info(ass_file: &AssFile) {
+    let script = &ass_file.components.script;
+    let events = &ass_file.components.events;
+
+    let resolution_x = script.get_playresx().unwrap_or("?");
+    let resolution_y = script.get_playresy().unwrap_or("?");
+    println!("Resolution: {}x{}", resolution_x, resolution_y);
+    println!("Events: {}", events.len());
+
+    let start = events.iter().filter_map(|dialogue| dialogue.get_start_ms()).min();
+    let end = events.iter().filter_map(|dialogue| dialogue.get_end_ms()).max();
+    if let (Some(start), Some(end)) = (start, end) {
+        println!("Duration: {} ({} - {})", format_duration(end - start), format_duration(start), format_duration(end));
+    }
+
+    println!("Styles:");
+    for style in ass_file.components.v4.iter() {
+        let name = style.get_name().unwrap_or("");
+        let used = events.iter().filter(|dialogue| dialogue.get_style() == Some(name)).count();
+        println!("  {} ({} {}) used by {} events", name, style.get_fontname().unwrap_or("?"), style.get_fontsize().unwrap_or("?"), used);
+    }
+}
+
+fn lint(ass_file: &AssFile, options: &LintOptions) -> ExitCode {
+    let diagnostics = ass_file.lint(options);
+    for diagnostic in &diagnostics {
+        println!("{}", diagnostic);
+    }
+
+    if diagnostics.iter().any(|diagnostic| diagnostic.severity == Severity::Error) { ExitCode::FAILURE } else { ExitCode::SUCCESS }
+}
+
+fn restyle(ass_file: &mut AssFile, arguments: &[String]) -> Result<(), String> {
+    let (name, fields) = arguments.split_first().ok_or("restyle needs a STYLE")?;
+    let style = ass_file.components.v4.get_style_mut(name).ok_or(format!("style {:?} is not defined", name))?;
+    let mut new_name = None;
+
+    for field in fields {
+        let (key, value) = field.split_once('=').ok_or(format!("expected FIELD=VALUE, got {:?}", field))?;
+        if key.eq_ignore_ascii_case("name") {
+            new_name = Some(value);
+            continue;
+        }
+        set_style_field(style, key, value).ok_or(format!("unknown style field {:?}", key))?;
+    }
+
+    if let Some(new_name) = new_name {
+        ass_file.rename_style(name, new_name).map_err(|e| format!("can't rename {:?}: {}", name, e))?;
+    }
+    Ok(())
+}
+
+fn set_style_field(style: &mut V4Format, key: &str, value: &str) -> Option<()> {
+    match key.to_ascii_lowercase().as_str() {
+        "fontname" => style.set_fontname(value),
+        "fontsize" => style.set_fontsize(value),
+        "primarycolour" => style.set_primarycolour(value),
+        "secondarycolour" => style.set_secondarycolour(value),
+        "outlinecolour" => style.set_outlinecolour(value),
+        "backcolour" => style.set_backcolour(value),
+        "bold" => style.set_bold(value),
+        "italic" => style.set_italic(value),
+        "underline" => style.set_underline(value),
+        "strikeout" => style.set_strikeout(value),
+        "scalex" => style.set_scalex(value),
+        "scaley" => style.set_scaley(value),
+        "spacing" => style.set_spacing(value),
+        "angle" => style.set_angle(value),
+        "borderstyle" => style.set_borderstyle(value),
+        "outline" => style.set_outline(value),
+        "shadow" => style.set_shadow(value),
+        "alignment" => style.set_alignment(value),
+        "marginl" => style.set_marginl(value),
+        "marginr" => style.set_marginr(value),
+        "marginv" => style.set_marginv(value),
+        _ => return None,
+    };
+    Some(())
+}
@@ -0,0 +1,542 @@
+//! # Colour
+//!
+//! Colour the text of a dialogue one character or word at a time: gradients between colour
+//! stops, rainbows, repeating palettes and random palettes from a seed or a random number
+//! generator, with constraints to keep them readable. The `\c` tags are put
+//! before the visible characters, so existing override blocks, `\N` line breaks and `\p`
+//! drawings are kept as they are.
+//!
+//! ```rust
+//! use ass_parser::Dialogue;
+//! use ass_parser::colour::{ColourSpace, ColourUnit};
+//! use hex_color::HexColor;
+//!
+//! let dialogue = Dialogue::default()
+//!     .set_text("{\\i1}Rap")
+//!     .set_gradient(&[HexColor::RED, HexColor::BLUE], ColourUnit::Character, ColourSpace::Rgb);
+//! assert_eq!(Some("{\\i1\\c&H0000FF&}R{\\c&H800080&}a{\\c&HFF0000&}p"), dialogue.get_text());
+//!
+//! // fade the whole line from white to yellow during its first second
+//! let dialogue = Dialogue::default()
+//!     .set_text("God")
+//!     .set_colour_transition(&[HexColor::WHITE, HexColor::YELLOW], 0, 1000);
+//! assert_eq!(Some("{\\c&HFFFFFF&\\t(0,1000,\\c&H00FFFF&)}God"), dialogue.get_text());
+//! ```
+
+use hex_color::HexColor;
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+
+use crate::Dialogue;
+use crate::text::{drawing_mode, insert_leading_tags, segments, Segment};
+
+/// What gets its own colour.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColourUnit {
+    /// Every visible character. Spaces are not coloured.
+    #[default]
+    Character,
+    /// Every word, split on spaces and line breaks.
+    Word,
+}
+
+/// How colours are blended in a gradient.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColourSpace {
+    /// Blend the red, green and blue values.
+    #[default]
+    Rgb,
+    /// Blend in the perceptual Oklab space, which keeps the brightness even and avoids the
+    /// grey middle of RGB gradients between opposite colours.
+    Oklab,
+}
+
+/// `count` colours going through the stops in order, with the first and last colours at the
+/// first and last stops.
+pub fn gradient(stops: &[HexColor], count: usize, space: ColourSpace) -> Vec<HexColor> {
+    let Some(first) = stops.first() else {
+        return Vec::new();
+    };
+    if stops.len() == 1 || count == 1 {
+        return vec![*first; count];
+    }
+
+    (0..count)
+        .map(|i| {
+            let position = i as f64 / (count - 1) as f64 * (stops.len() - 1) as f64;
+            let stop = (position.floor() as usize).min(stops.len() - 2);
+            mix(stops[stop], stops[stop + 1], position - stop as f64, space)
+        })
+        .collect()
+}
+
+/// `count` colours going once around the hue circle, starting at red.
+pub fn rainbow(count: usize) -> Vec<HexColor> {
+    (0..count)
+        .map(|i| from_hsl(i as f64 * 360.0 / count as f64, 1.0, 0.5))
+        .collect()
+}
+
+/// `count` random colours which are always the same for the same seed. Use `ColourGenerator`
+/// for colours with constraints.
+pub fn random_palette(seed: u64, count: usize) -> Vec<HexColor> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    (0..count).map(|_| rng.gen()).collect()
+}
+
+/// The smallest Oklab distance between colours which are not counted as a repeat.
+const MIN_COLOUR_DIFFERENCE: f64 = 0.1;
+
+/// How many random colours `ColourGenerator` tries before giving up on the constraints.
+const MAX_ATTEMPTS: usize = 200;
+
+/// Constraints on the colours made by `ColourGenerator`.
+/// By default any saturation and lightness is allowed and no colour is the same as the one
+/// before it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PaletteOptions {
+    saturation: (f64, f64),
+    lightness: (f64, f64),
+    contrast: Option<(Vec<HexColor>, f64)>,
+    avoid_repeats: bool,
+}
+
+impl Default for PaletteOptions {
+    fn default() -> Self {
+        PaletteOptions {
+            saturation: (0.0, 1.0),
+            lightness: (0.0, 1.0),
+            contrast: None,
+            avoid_repeats: true,
+        }
+    }
+}
+
+impl PaletteOptions {
+    /// The HSL saturation of the colours, between 0 and 1.
+    pub fn set_saturation(&mut self, min: f64, max: f64) -> &mut Self {
+        self.saturation = (min.clamp(0.0, 1.0), max.clamp(min.clamp(0.0, 1.0), 1.0));
+        self
+    }
+
+    /// The HSL lightness of the colours, between 0 and 1.
+    pub fn set_lightness(&mut self, min: f64, max: f64) -> &mut Self {
+        self.lightness = (min.clamp(0.0, 1.0), max.clamp(min.clamp(0.0, 1.0), 1.0));
+        self
+    }
+
+    /// The smallest WCAG contrast ratio between the colours and each of the background colours,
+    /// such as the outline and back colours of the style. 4.5 is the WCAG level for normal text.
+    pub fn set_min_contrast(&mut self, against: &[HexColor], ratio: f64) -> &mut Self {
+        self.contrast = Some((against.to_vec(), ratio));
+        self
+    }
+
+    /// Whether colours which look nearly the same as the one before them are skipped.
+    pub fn set_avoid_repeats(&mut self, avoid_repeats: bool) -> &mut Self {
+        self.avoid_repeats = avoid_repeats;
+        self
+    }
+
+    /// How far a colour is from meeting the contrast constraint, 0 if it does.
+    fn contrast_shortfall(&self, colour: HexColor) -> f64 {
+        let Some((against, ratio)) = &self.contrast else {
+            return 0.0;
+        };
+        against.iter()
+            .map(|background| (ratio - contrast_ratio(colour, *background)).max(0.0))
+            .fold(0.0, f64::max)
+    }
+}
+
+/// Random colours which follow the `PaletteOptions`. The colours only depend on the random
+/// number generator, so a generator made with `ColourGenerator::from_seed` makes the same colours
+/// on every run.
+///
+/// If no colour meeting the constraints is found after a number of tries, the colour which comes
+/// closest to the contrast is used.
+///
+/// # Example
+/// ```rust
+/// use ass_parser::Dialogue;
+/// use ass_parser::colour::{ColourGenerator, PaletteOptions};
+/// use hex_color::HexColor;
+///
+/// let mut options = PaletteOptions::default();
+/// options.set_lightness(0.5, 0.8).set_min_contrast(&[HexColor::BLACK], 7.0);
+/// let mut colours = ColourGenerator::from_seed(2024, options.clone());
+///
+/// let dialogue = Dialogue::default().set_text("Rap God").set_colour(colours.next_colour());
+/// assert_eq!(ColourGenerator::from_seed(2024, options).next_colour(), colours.previous().unwrap());
+/// ```
+#[derive(Debug, Clone)]
+pub struct ColourGenerator<R: Rng> {
+    rng: R,
+    options: PaletteOptions,
+    previous: Option<HexColor>,
+}
+
+impl ColourGenerator<StdRng> {
+    /// A generator which makes the same colours for the same seed.
+    pub fn from_seed(seed: u64, options: PaletteOptions) -> Self {
+        ColourGenerator::new(StdRng::seed_from_u64(seed), options)
+    }
+}
+
+impl<R: Rng> ColourGenerator<R> {
+    /// A generator which takes its randomness from `rng`.
+    pub fn new(rng: R, options: PaletteOptions) -> Self {
+        ColourGenerator { rng, options, previous: None }
+    }
+
+    /// The last colour made.
+    pub fn previous(&self) -> Option<HexColor> {
+        self.previous
+    }
+
+    /// Make the next random colour.
+    pub fn next_colour(&mut self) -> HexColor {
+        let options = &self.options;
+        let mut best: Option<(f64, HexColor)> = None;
+
+        for _ in 0..MAX_ATTEMPTS {
+            let hue = self.rng.gen_range(0.0..360.0);
+            let saturation = self.rng.gen_range(options.saturation.0..=options.saturation.1);
+            let lightness = self.rng.gen_range(options.lightness.0..=options.lightness.1);
+            let colour = from_hsl(hue, saturation, lightness);
+
+            let repeat = options.avoid_repeats && self.previous.is_some_and(|previous| difference(previous, colour) < MIN_COLOUR_DIFFERENCE);
+            if repeat {
+                continue;
+            }
+            let shortfall = options.contrast_shortfall(colour);
+            if best.is_none_or(|(best, _)| shortfall < best) {
+                best = Some((shortfall, colour));
+            }
+            if shortfall == 0.0 {
+                break;
+            }
+        }
+
+        let colour = best.map(|(_, colour)| colour).unwrap_or(HexColor::WHITE);
+        self.previous = Some(colour);
+        colour
+    }
+
+    /// Make `count` random colours.
+    pub fn palette(&mut self, count: usize) -> Vec<HexColor> {
+        (0..count).map(|_| self.next_colour()).collect()
+    }
+}
+
+impl<R: Rng> Iterator for ColourGenerator<R> {
+    type Item = HexColor;
+
+    fn next(&mut self) -> Option<HexColor> {
+        Some(self.next_colour())
+    }
+}
+
+/// The WCAG relative luminance of a colour, between 0 for black and 1 for white.
+pub fn relative_luminance(colour: HexColor) -> f64 {
+    0.2126 * to_linear(colour.r) + 0.7152 * to_linear(colour.g) + 0.0722 * to_linear(colour.b)
+}
+
+/// The WCAG contrast ratio between two colours, from 1 for the same colour to 21 for black on
+/// white.
+pub fn contrast_ratio(a: HexColor, b: HexColor) -> f64 {
+    let (a, b) = (relative_luminance(a), relative_luminance(b));
+    (a.max(b) + 0.05) / (a.min(b) + 0.05)
+}
+
+/// How different two colours look, as their distance in Oklab.
+fn difference(a: HexColor, b: HexColor) -> f64 {
+    let (a, b) = (to_oklab(a), to_oklab(b));
+    a.iter().zip(b).map(|(a, b)| (a - b).powi(2)).sum::<f64>().sqrt()
+}
+
+impl Dialogue {
+    /// Colour the text with a gradient through the colour stops.
+    pub fn set_gradient(self, stops: &[HexColor], unit: ColourUnit, space: ColourSpace) -> Self {
+        let count = count_units(self.get_text().unwrap_or(""), unit);
+        let colours = gradient(stops, count, space);
+        self.set_unit_colours(&colours, unit)
+    }
+
+    /// Colour the text with the colours of the rainbow.
+    pub fn set_rainbow(self, unit: ColourUnit) -> Self {
+        let count = count_units(self.get_text().unwrap_or(""), unit);
+        let colours = rainbow(count);
+        self.set_unit_colours(&colours, unit)
+    }
+
+    /// Colour the text with the colours of the palette, starting again from the first colour
+    /// when they run out. Use `random_palette` for random colours which are the same every time.
+    pub fn set_palette(self, palette: &[HexColor], unit: ColourUnit) -> Self {
+        let count = count_units(self.get_text().unwrap_or(""), unit);
+        let colours: Vec<HexColor> = palette.iter().cycle().take(count).copied().collect();
+        self.set_unit_colours(&colours, unit)
+    }
+
+    /// Animate the colour of the text through the colour stops with `\t` tags, between
+    /// `start_ms` and `end_ms` from the start of the dialogue. The text starts with the first
+    /// colour and each change takes the same time.
+    pub fn set_colour_transition(self, stops: &[HexColor], start_ms: i64, end_ms: i64) -> Self {
+        let Some(first) = stops.first() else {
+            return self;
+        };
+
+        let mut tags = format!(r"\c{}", ass_colour(*first));
+        let changes = stops.len() as i64 - 1;
+        for (i, stop) in stops.iter().enumerate().skip(1) {
+            let i = i as i64;
+            let from = start_ms + (end_ms - start_ms) * (i - 1) / changes;
+            let to = start_ms + (end_ms - start_ms) * i / changes;
+            tags.push_str(&format!(r"\t({},{},\c{})", from, to, ass_colour(*stop)));
+        }
+
+        let text = insert_leading_tags(self.get_text().unwrap_or(""), &tags);
+        self.set_text(&text)
+    }
+
+    fn set_unit_colours(self, colours: &[HexColor], unit: ColourUnit) -> Self {
+        let text = before_units(self.get_text().unwrap_or(""), unit, |i| {
+            colours.get(i).map(|colour| format!(r"\c{}", ass_colour(*colour))).unwrap_or_default()
+        });
+        self.set_text(&text)
+    }
+}
+
+/// A colour as it is written in override tags, e.g. `&H00FFFF&` for yellow.
+pub(crate) fn ass_colour(colour: HexColor) -> String {
+    format!("&H{:02X}{:02X}{:02X}&", colour.b, colour.g, colour.r)
+}
+
+/// A colour as it is written in styles, e.g. `&H0000FFFF` for yellow. ASS alpha counts up from
+/// opaque, so it is the opposite of the alpha of `HexColor`.
+pub(crate) fn style_colour(colour: HexColor) -> String {
+    format!("&H{:02X}{:02X}{:02X}{:02X}", 255 - colour.a, colour.b, colour.g, colour.r)
+}
+
+/// Read a colour of a style or an override tag, such as `&H0000FFFF`, `&H00FFFF&` or the decimal
+/// form of older scripts.
+pub(crate) fn parse_ass_colour(value: &str) -> Option<HexColor> {
+    let value = value.trim().trim_end_matches('&');
+    let value = match value.strip_prefix("&H").or_else(|| value.strip_prefix("&h")) {
+        Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+        None => value.parse::<u32>().ok()?,
+    };
+    let [alpha, b, g, r] = value.to_be_bytes();
+    Some(HexColor::rgba(r, g, b, 255 - alpha))
+}
+
+fn count_units(text: &str, unit: ColourUnit) -> usize {
+    let mut count = 0;
+    before_units(text, unit, |_| {
+        count += 1;
+        String::new()
+    });
+    count
+}
+
+/// Put the tags given by `tags` for each unit of visible text before it, in the override block
+/// right before it if there is one.
+fn before_units(text: &str, unit: ColourUnit, mut tags: impl FnMut(usize) -> String) -> String {
+    let mut result = String::new();
+    let mut count = 0;
+    let mut drawing = false;
+    let mut in_word = false;
+    let mut after_block = false;
+
+    for segment in segments(text) {
+        let text = match segment {
+            Segment::Tags(block) => {
+                drawing = drawing_mode(block, drawing);
+                result.push_str(&format!("{{{}}}", block));
+                after_block = true;
+                continue;
+            },
+            Segment::Text(text) if drawing => {
+                result.push_str(text);
+                after_block = false;
+                continue;
+            },
+            Segment::Text(text) => text,
+        };
+
+        let mut chars = text.chars().peekable();
+        while let Some(c) = chars.next() {
+            if c == '\\' && matches!(chars.peek(), Some('N' | 'n' | 'h')) {
+                result.push(c);
+                result.push(chars.next().unwrap());
+                in_word = false;
+                after_block = false;
+                continue;
+            }
+            if c.is_whitespace() {
+                result.push(c);
+                in_word = false;
+                after_block = false;
+                continue;
+            }
+
+            if unit == ColourUnit::Character || !in_word {
+                let unit_tags = tags(count);
+                count += 1;
+                if !unit_tags.is_empty() {
+                    match after_block {
+                        true => {
+                            result.pop();
+                            result.push_str(&format!("{}}}", unit_tags));
+                        },
+                        false => result.push_str(&format!("{{{}}}", unit_tags)),
+                    }
+                }
+            }
+            result.push(c);
+            in_word = true;
+            after_block = false;
+        }
+    }
+
+    result
+}
+
+fn mix(from: HexColor, to: HexColor, t: f64, space: ColourSpace) -> HexColor {
+    let lerp = |a: f64, b: f64| a + (b - a) * t;
+    match space {
+        ColourSpace::Rgb => {
+            let channel = |a: u8, b: u8| lerp(a as f64, b as f64).round() as u8;
+            HexColor::rgb(channel(from.r, to.r), channel(from.g, to.g), channel(from.b, to.b))
+        },
+        ColourSpace::Oklab => {
+            let (from, to) = (to_oklab(from), to_oklab(to));
+            from_oklab([lerp(from[0], to[0]), lerp(from[1], to[1]), lerp(from[2], to[2])])
+        },
+    }
+}
+
+/// The linear light value of an sRGB channel, between 0 and 1.
+pub(crate) fn to_linear(channel: u8) -> f64 {
+    let channel = channel as f64 / 255.0;
+    match channel <= 0.04045 {
+        true => channel / 12.92,
+        false => ((channel + 0.055) / 1.055).powf(2.4),
+    }
+}
+
+fn from_linear(value: f64) -> u8 {
+    let value = value.clamp(0.0, 1.0);
+    let channel = match value <= 0.0031308 {
+        true => value * 12.92,
+        false => 1.055 * value.powf(1.0 / 2.4) - 0.055,
+    };
+    (channel * 255.0).round() as u8
+}
+
+fn to_oklab(colour: HexColor) -> [f64; 3] {
+    let (r, g, b) = (to_linear(colour.r), to_linear(colour.g), to_linear(colour.b));
+    let l = (0.4122214708 * r + 0.5363325363 * g + 0.0514459929 * b).cbrt();
+    let m = (0.2119034982 * r + 0.6806995451 * g + 0.1073969566 * b).cbrt();
+    let s = (0.0883024619 * r + 0.2817188376 * g + 0.6299787005 * b).cbrt();
+    [
+        0.2104542553 * l + 0.7936177850 * m - 0.0040720468 * s,
+        1.9779984951 * l - 2.4285922050 * m + 0.4505937099 * s,
+        0.0259040371 * l + 0.7827717662 * m - 0.8086757660 * s,
+    ]
+}
+
+fn from_oklab([lightness, a, b]: [f64; 3]) -> HexColor {
+    let l = (lightness + 0.3963377774 * a + 0.2158037573 * b).powi(3);
+    let m = (lightness - 0.1055613458 * a - 0.0638541728 * b).powi(3);
+    let s = (lightness - 0.0894841775 * a - 1.2914855480 * b).powi(3);
+    HexColor::rgb(
+        from_linear(4.0767416621 * l - 3.3077115913 * m + 0.2309699292 * s),
+        from_linear(-1.2684380046 * l + 2.6097574011 * m - 0.3413193965 * s),
+        from_linear(-0.0041960863 * l - 0.7034186147 * m + 1.7076147010 * s),
+    )
+}
+
+/// A colour from its hue in degrees, and saturation and lightness between 0 and 1.
+pub(crate) fn from_hsl(hue: f64, saturation: f64, lightness: f64) -> HexColor {
+    let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+    let hue = hue.rem_euclid(360.0) / 60.0;
+    let x = chroma * (1.0 - (hue % 2.0 - 1.0).abs());
+    let (r, g, b) = match hue as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let m = lightness - chroma / 2.0;
+    let channel = |value: f64| ((value + m) * 255.0).round().clamp(0.0, 255.0) as u8;
+    HexColor::rgb(channel(r), channel(g), channel(b))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gradient() {
+        assert_eq!(vec![HexColor::RED, HexColor::rgb(128, 0, 128), HexColor::BLUE], gradient(&[HexColor::RED, HexColor::BLUE], 3, ColourSpace::Rgb));
+        assert_eq!(vec![HexColor::RED, HexColor::GREEN, HexColor::BLUE], gradient(&[HexColor::RED, HexColor::GREEN, HexColor::BLUE], 3, ColourSpace::Oklab));
+        assert_eq!(vec![HexColor::WHITE; 2], gradient(&[HexColor::WHITE], 2, ColourSpace::Rgb));
+        assert_eq!(vec![HexColor::RED, HexColor::rgb(0, 255, 255)], rainbow(2));
+        assert_eq!(random_palette(7, 4), random_palette(7, 4));
+        assert_ne!(random_palette(7, 4), random_palette(8, 4));
+    }
+
+    #[test]
+    fn test_colour_generator() {
+        let mut options = PaletteOptions::default();
+        options.set_saturation(0.6, 1.0).set_lightness(0.3, 0.7).set_min_contrast(&[HexColor::BLACK, HexColor::rgb(0, 0, 64)], 5.0);
+
+        let palette = ColourGenerator::from_seed(1, options.clone()).palette(20);
+        assert_eq!(palette, ColourGenerator::from_seed(1, options.clone()).take(20).collect::<Vec<_>>());
+        assert_ne!(palette, ColourGenerator::from_seed(2, options).palette(20));
+        for colours in palette.windows(2) {
+            assert!(contrast_ratio(colours[1], HexColor::BLACK) >= 5.0);
+            assert!(difference(colours[0], colours[1]) >= MIN_COLOUR_DIFFERENCE);
+        }
+
+        // impossible constraints give the closest colour
+        let mut options = PaletteOptions::default();
+        options.set_lightness(0.0, 0.1).set_min_contrast(&[HexColor::BLACK], 21.0);
+        let colour = ColourGenerator::new(StdRng::seed_from_u64(3), options).next_colour();
+        assert!(relative_luminance(colour) < 0.1);
+    }
+
+    #[test]
+    fn test_ass_colours() {
+        assert_eq!(Some(HexColor::YELLOW), parse_ass_colour("&H0000FFFF"));
+        assert_eq!(Some(HexColor::rgba(255, 0, 0, 127)), parse_ass_colour("&H800000FF"));
+        assert_eq!(Some(HexColor::BLUE), parse_ass_colour("&HFF0000&"));
+        assert_eq!(Some(HexColor::RED), parse_ass_colour("255"));
+        assert_eq!(None, parse_ass_colour("yellow"));
+        assert_eq!("&H800000FF", style_colour(HexColor::rgba(255, 0, 0, 127)));
+    }
+
+    #[test]
+    fn test_contrast_ratio() {
+        assert_eq!(21.0, contrast_ratio(HexColor::BLACK, HexColor::WHITE));
+        assert_eq!(1.0, contrast_ratio(HexColor::RED, HexColor::RED));
+        assert_eq!("1.07", format!("{:.2}", contrast_ratio(HexColor::YELLOW, HexColor::WHITE)));
+    }
+
+    #[test]
+    fn test_colour_units() {
+        let text = r"{\i1}Rap God\N{\p1}m 0 0 l 1 1{\p0}I";
+        let dialogue = Dialogue::default().set_text(text).set_palette(&[HexColor::RED, HexColor::BLUE], ColourUnit::Word);
+        assert_eq!(Some(r"{\i1\c&H0000FF&}Rap {\c&HFF0000&}God\N{\p1}m 0 0 l 1 1{\p0\c&H0000FF&}I"), dialogue.get_text());
+
+        let dialogue = Dialogue::default().set_text("Hi you").set_rainbow(ColourUnit::Character);
+        assert_eq!(Some(r"{\c&H0000FF&}H{\c&H00FFCC&}i {\c&H66FF00&}y{\c&HFF6600&}o{\c&HFF00CC&}u"), dialogue.get_text());
+
+        let dialogue = Dialogue::default().set_text("{\\an8}Look").set_colour_transition(&[HexColor::RED, HexColor::GREEN, HexColor::BLUE], 0, 1000);
+        assert_eq!(Some(r"{\an8\c&H0000FF&\t(0,500,\c&H00FF00&)\t(500,1000,\c&HFF0000&)}Look"), dialogue.get_text());
+    }
+}
@@ -0,0 +1,353 @@
+//! # Contrast
+//!
+//! Check that text stands out from its outline and shadow, using the WCAG contrast ratio between
+//! the primary colour and the outline and back colours. The checks follow the `\c`, `\1c`,
+//! `\3c`, `\4c`, `\bord`, `\shad` and `\r` tags of each dialogue, and the outline is only checked
+//! when it is drawn, like the shadow.
+//!
+//! ```rust
+//! use ass_parser::{AssFile, Dialogue, Events};
+//! use ass_parser::contrast::ContrastTarget;
+//!
+//! let mut ass_file = AssFile::new();
+//! ass_file.components.events.set_events(Events::default())
+//!     // yellow text with a white outline
+//!     .add_dialogue(Dialogue::default().set_text("{\\c&H00FFFF&\\3c&HFFFFFF&}Rap God"));
+//!
+//! let issues = ass_file.check_contrast(4.5);
+//! assert_eq!(ContrastTarget::Outline, issues[0].target);
+//! assert!(issues[0].ratio < 1.1);
+//!
+//! ass_file.fix_contrast(4.5);
+//! assert!(ass_file.check_contrast(4.5).is_empty());
+//! ```
+
+use hex_color::HexColor;
+
+use crate::{AssFile, Dialogue, V4Format, V4Styles};
+use crate::colour::{ass_colour, contrast_ratio, parse_ass_colour, style_colour};
+use crate::text::{drawing_mode, segments, split_tags, tag_name, Segment};
+
+/// What the text colour is compared with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContrastTarget {
+    /// The outline colour, or the box colour with `BorderStyle` 3.
+    Outline,
+    /// The back colour of the shadow.
+    Back,
+}
+
+/// The contrast ratios of a style.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StyleContrast {
+    /// Name of the style.
+    pub style: String,
+    /// Contrast ratio between the primary and outline colours.
+    pub outline_ratio: f64,
+    /// Contrast ratio between the primary and back colours.
+    pub back_ratio: f64,
+}
+
+/// A dialogue with text which doesn't stand out enough.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContrastIssue {
+    /// Index of the dialogue.
+    pub event: usize,
+    /// What the text is compared with.
+    pub target: ContrastTarget,
+    /// The lowest contrast ratio in the dialogue.
+    pub ratio: f64,
+    /// The colour of the text with the lowest contrast.
+    pub colour: HexColor,
+    /// The outline or back colour behind it.
+    pub against: HexColor,
+}
+
+/// The colours of the text at a point in a dialogue.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Colours {
+    pub(crate) primary: HexColor,
+    pub(crate) outline: HexColor,
+    pub(crate) back: HexColor,
+    pub(crate) border: f64,
+    pub(crate) shadow: f64,
+    pub(crate) opaque_box: bool,
+}
+
+impl Colours {
+    pub(crate) fn new(style: &V4Format) -> Colours {
+        let colour = |value: Option<&str>, default: HexColor| value.and_then(parse_ass_colour).unwrap_or(default);
+        let number = |value: Option<&str>| value.and_then(|value| value.trim().parse::<f64>().ok()).unwrap_or(0.0);
+        Colours {
+            primary: colour(style.get_primarycolour(), HexColor::WHITE),
+            outline: colour(style.get_outlinecolour(), HexColor::BLACK),
+            back: colour(style.get_backcolour(), HexColor::BLACK),
+            border: number(style.get_outline()),
+            shadow: number(style.get_shadow()),
+            opaque_box: style.get_borderstyle().map(str::trim) == Some("3"),
+        }
+    }
+
+    /// The colours of the style with the name, or of the default style if there is none.
+    fn of_style(styles: &V4Styles, name: &str) -> Colours {
+        Colours::new(&styles.get_style(name).cloned().unwrap_or_default())
+    }
+
+    /// Follow the tags of an override block. `\r` goes back to the style of `line_style`.
+    /// Colour tags keep the alpha, which is changed by the alpha tags.
+    pub(crate) fn apply(&mut self, block: &str, styles: &V4Styles, line_style: &str) {
+        for tag in split_tags(block) {
+            let Some(tag) = tag.strip_prefix('\\') else {
+                continue;
+            };
+            let is_colour = |value: &str| value.starts_with('&') || value.starts_with(|c: char| c.is_ascii_digit());
+
+            let colour = |value: &str, old: HexColor| parse_ass_colour(value).map_or(old, |colour| colour.with_a(old.a));
+            // an alpha such as `&H80&` reads like a colour with only a red part
+            let alpha = |value: &str, old: HexColor| parse_ass_colour(value).map_or(old, |alpha| old.with_a(255 - alpha.r));
+
+            if let Some(value) = tag.strip_prefix("1c").or_else(|| tag.strip_prefix('c').filter(|value| is_colour(value))) {
+                self.primary = colour(value, self.primary);
+            } else if let Some(value) = tag.strip_prefix("3c") {
+                self.outline = colour(value, self.outline);
+            } else if let Some(value) = tag.strip_prefix("4c") {
+                self.back = colour(value, self.back);
+            } else if let Some(value) = tag.strip_prefix("alpha") {
+                (self.primary, self.outline, self.back) = (alpha(value, self.primary), alpha(value, self.outline), alpha(value, self.back));
+            } else if let Some(value) = tag.strip_prefix("1a") {
+                self.primary = alpha(value, self.primary);
+            } else if let Some(value) = tag.strip_prefix("3a") {
+                self.outline = alpha(value, self.outline);
+            } else if let Some(value) = tag.strip_prefix("4a") {
+                self.back = alpha(value, self.back);
+            } else if let Some(value) = tag.strip_prefix("bord") {
+                self.border = value.trim().parse::<f64>().unwrap_or(self.border);
+            } else if let Some(value) = tag.strip_prefix("shad") {
+                self.shadow = value.trim().parse::<f64>().unwrap_or(self.shadow);
+            } else if let Some(style) = tag.strip_prefix('r') {
+                let style = match style.trim().is_empty() {
+                    true => line_style,
+                    false => style.trim(),
+                };
+                *self = Colours::of_style(styles, style);
+            }
+        }
+    }
+
+    /// The contrast ratios with the outline and shadow which are drawn.
+    fn ratios(&self) -> Vec<(ContrastTarget, f64, HexColor)> {
+        let mut ratios = Vec::new();
+        if self.border > 0.0 || self.opaque_box {
+            ratios.push((ContrastTarget::Outline, contrast_ratio(self.primary, self.outline), self.outline));
+        }
+        if self.shadow > 0.0 {
+            ratios.push((ContrastTarget::Back, contrast_ratio(self.primary, self.back), self.back));
+        }
+        ratios
+    }
+
+    /// The black or white outline to use when the outline is drawn with a contrast ratio below
+    /// `min_ratio`, or `None` if it is fine or already the most readable one. The alpha is kept.
+    fn readable_outline(&self, min_ratio: f64) -> Option<HexColor> {
+        let low = self.ratios().iter().any(|(target, ratio, _)| *target == ContrastTarget::Outline && *ratio < min_ratio);
+        let outline = readable_outline(self.primary).with_a(self.outline.a);
+        (low && outline != self.outline).then_some(outline)
+    }
+}
+
+impl AssFile {
+    /// The contrast ratios of each style.
+    pub fn style_contrasts(&self) -> Vec<StyleContrast> {
+        self.components.v4.iter()
+            .map(|style| {
+                let colours = Colours::new(style);
+                StyleContrast {
+                    style: style.get_name().unwrap_or("").to_string(),
+                    outline_ratio: contrast_ratio(colours.primary, colours.outline),
+                    back_ratio: contrast_ratio(colours.primary, colours.back),
+                }
+            })
+            .collect()
+    }
+
+    /// Find the dialogues with text whose contrast ratio with its outline or shadow is below
+    /// `min_ratio`. 4.5 is the WCAG level for normal text. Comments are not checked.
+    pub fn check_contrast(&self, min_ratio: f64) -> Vec<ContrastIssue> {
+        let styles = &self.components.v4;
+        let mut issues = Vec::new();
+
+        for (event, dialogue) in self.components.events.iter().enumerate() {
+            if dialogue.is_comment() {
+                continue;
+            }
+
+            let mut worst: Vec<ContrastIssue> = Vec::new();
+            visit_text(dialogue, styles, |colours| {
+                for (target, ratio, against) in colours.ratios() {
+                    let issue = ContrastIssue { event, target, ratio, colour: colours.primary, against };
+                    match worst.iter_mut().find(|issue| issue.target == target) {
+                        Some(worst) if worst.ratio <= ratio => {},
+                        Some(worst) => *worst = issue,
+                        None => worst.push(issue),
+                    }
+                }
+                None
+            });
+            issues.extend(worst.into_iter().filter(|issue| issue.ratio < min_ratio));
+        }
+
+        issues
+    }
+
+    /// Change outline colours which are drawn and have a contrast ratio below `min_ratio` with the
+    /// text to black or white, whichever stands out more. Styles are changed first, then `\3c`
+    /// tags are added to the dialogues whose override tags still give a low contrast. The alpha of
+    /// the outline is kept. Returns the number of styles and dialogues changed.
+    ///
+    /// Black or white outlines reach a contrast ratio of at least 4.58 with any colour, so higher
+    /// ratios can't always be met. Outlines which are already black or white are left as they
+    /// are, so fixing twice changes nothing the second time.
+    pub fn fix_contrast(&mut self, min_ratio: f64) -> usize {
+        let mut changed = 0;
+
+        for style in self.components.v4.styles.iter_mut() {
+            if let Some(outline) = Colours::new(style).readable_outline(min_ratio) {
+                style.set_outlinecolour(&style_colour(outline));
+                changed += 1;
+            }
+        }
+
+        let styles = &self.components.v4;
+        for dialogue in self.components.events.dialogues.dialogues.iter_mut() {
+            if dialogue.is_comment() {
+                continue;
+            }
+            let text = visit_text(dialogue, styles, |colours| colours.readable_outline(min_ratio));
+            if let Some(text) = text {
+                dialogue.event.text = Some(text);
+                changed += 1;
+            }
+        }
+
+        changed
+    }
+}
+
+/// Black or white, whichever has the higher contrast with the colour.
+fn readable_outline(colour: HexColor) -> HexColor {
+    match contrast_ratio(colour, HexColor::BLACK) >= contrast_ratio(colour, HexColor::WHITE) {
+        true => HexColor::BLACK,
+        false => HexColor::WHITE,
+    }
+}
+
+/// Call `check` with the colours of each run of visible text in the dialogue. If it returns an
+/// outline colour, a `\3c` tag is put before the text, replacing the `\3c` tags of the override
+/// block just before it, and the changed text is returned.
+fn visit_text(dialogue: &Dialogue, styles: &V4Styles, mut check: impl FnMut(&Colours) -> Option<HexColor>) -> Option<String> {
+    let line_style = dialogue.get_style().unwrap_or("");
+    let mut colours = Colours::of_style(styles, line_style);
+    let mut drawing = false;
+    let mut text = String::new();
+    let mut changed = false;
+    // where the override block right before the current text starts in `text`, and its tags
+    let mut last_block: Option<(usize, &str)> = None;
+
+    for segment in segments(dialogue.get_text().unwrap_or("")) {
+        match segment {
+            Segment::Tags(block) => {
+                colours.apply(block, styles, line_style);
+                drawing = drawing_mode(block, drawing);
+                last_block = Some((text.len(), block));
+                text.push_str(&format!("{{{}}}", block));
+            },
+            Segment::Text(visible) => {
+                let blank = visible.replace("\\N", "").replace("\\n", "").replace("\\h", "").trim().is_empty();
+                if !drawing && !blank {
+                    if let Some(outline) = check(&colours) {
+                        let tag = format!(r"\3c{}", ass_colour(outline));
+                        match last_block {
+                            Some((start, block)) => {
+                                text.truncate(start);
+                                let kept: String = split_tags(block).into_iter()
+                                    .filter(|existing| existing.strip_prefix('\\').and_then(tag_name) != Some("3c"))
+                                    .collect();
+                                text.push_str(&format!("{{{}{}}}", kept, tag));
+                            },
+                            None => text.push_str(&format!("{{{}}}", tag)),
+                        }
+                        colours.outline = outline;
+                        changed = true;
+                    }
+                }
+                text.push_str(visible);
+                last_block = None;
+            },
+        }
+    }
+
+    changed.then_some(text)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::ass_file_with;
+
+    #[test]
+    fn test_check_contrast() {
+        let ass_file = ass_file_with(&[
+            "Look",
+            r"{\c&H00FFFF&}Rap {\3c&HFFFFFF&}God",
+            r"{\bord0\c&H000000&}I was",
+            r"{\3c&HFFFFFF&\p1}m 0 0 l 10 10{\p0\r}gonna",
+        ]);
+
+        let contrasts = ass_file.style_contrasts();
+        assert_eq!(("Default", 21.0, 21.0), (contrasts[0].style.as_str(), contrasts[0].outline_ratio, contrasts[0].back_ratio));
+
+        let issues = ass_file.check_contrast(4.5);
+        assert_eq!(1, issues.len());
+        assert_eq!((1, ContrastTarget::Outline), (issues[0].event, issues[0].target));
+        assert_eq!((HexColor::YELLOW, HexColor::WHITE), (issues[0].colour, issues[0].against));
+
+        let ass_file = ass_file_with(&[r"{\shad2\4c&H00FFFF&}Look"]);
+        assert_eq!(ContrastTarget::Back, ass_file.check_contrast(4.5)[0].target);
+    }
+
+    #[test]
+    fn test_fix_contrast() {
+        let mut ass_file = ass_file_with(&["Look", r"{\c&H00FFFF&}Rap {\3c&HFFFFFF&}God", r"{\c&H000000&}I was"]);
+        ass_file.components.v4.set_outlinecolour("&H80FFFFFF");
+
+        assert_eq!(3, ass_file.fix_contrast(4.5));
+        assert_eq!(Some("&H80000000"), ass_file.components.v4.get_outlinecolour());
+        let texts: Vec<&str> = ass_file.components.events.iter().map(|dialogue| dialogue.get_text().unwrap()).collect();
+        assert_eq!(vec!["Look", r"{\c&H00FFFF&}Rap {\3c&H000000&}God", r"{\c&H000000&\3c&HFFFFFF&}I was"], texts);
+        assert!(ass_file.check_contrast(4.5).is_empty());
+        assert_eq!(0, ass_file.fix_contrast(4.5));
+    }
+
+    #[test]
+    fn test_fix_contrast_unreachable() {
+        // grey has a ratio of about 5.3 with black, which is as good as it gets
+        let mut ass_file = ass_file_with(&[r"{\c&H808080&}Look", "Rap God"]);
+        ass_file.components.v4.set_primarycolour("&H00808080");
+        let before = ass_file.clone();
+
+        assert_eq!(2, ass_file.check_contrast(7.0).len());
+        assert_eq!(0, ass_file.fix_contrast(7.0));
+        assert_eq!(before, ass_file);
+    }
+
+    #[test]
+    fn test_fix_contrast_without_outline() {
+        let mut ass_file = ass_file_with(&["Look"]);
+        ass_file.components.v4.set_outlinecolour("&H00FFFFFF").set_outline("0");
+
+        assert!(ass_file.check_contrast(4.5).is_empty());
+        assert_eq!(0, ass_file.fix_contrast(4.5));
+        assert_eq!(Some("&H00FFFFFF"), ass_file.components.v4.get_outlinecolour());
+    }
+}
@@ -0,0 +1,606 @@
+//! # Drawing
+//!
+//! Read, change and write the vector drawings of `\p` drawing mode and of vector `\clip` and
+//! `\iclip` tags, such as `m 0 0 l 100 0 100 100 b 50 150 0 150 0 100`.
+//!
+//! | Command | Meaning |
+//! |---|---|
+//! | `m x y` | Close the current shape and move to a point. |
+//! | `n x y` | Move to a point without closing the current shape. |
+//! | `l x y ...` | Straight lines through the points. |
+//! | `b x1 y1 x2 y2 x3 y3 ...` | Cubic Bézier curves. |
+//! | `s x1 y1 x2 y2 x3 y3 ...` | A cubic B-spline through three or more control points. |
+//! | `p x y ...` | More control points for the B-spline. |
+//! | `c` | Close the B-spline. |
+//!
+//! ```rust
+//! use ass_parser::Dialogue;
+//! use ass_parser::drawing::{BoundingBox, Drawing, Point};
+//!
+//! let mut star = Drawing::from_svg_path("M 10 0 L 13 7 L 20 7 L 14 12 L 16 20 L 10 15 L 4 20 L 6 12 L 0 7 L 7 7 Z").unwrap();
+//! star.scale(2.0, 2.0).rotate(90.0, Point::new(20.0, 20.0)).translate(100.0, 50.0);
+//! let bounds = star.bounding_box().unwrap();
+//! assert_eq!(BoundingBox { left: 100.0, top: 50.0, right: 140.0, bottom: 90.0 }, bounds);
+//! assert!(star.to_string().starts_with("m 140 70 l 126 76 126 90"));
+//!
+//! let dialogue = Dialogue::default()
+//!     .set_text(&format!("{{\\p1}}{}", star))
+//!     .set_vector_clip(&star);
+//! assert_eq!(Some(format!("{{\\p1\\clip({})}}{}", star, star).as_str()), dialogue.get_text());
+//! ```
+
+use std::fmt;
+use std::ops::Range;
+
+use crate::text::format_number;
+
+/// How many straight lines a curve is split into by `Drawing::flatten`.
+pub const DEFAULT_CURVE_SEGMENTS: usize = 16;
+
+/// A point of a drawing.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Point {
+    /// Horizontal position, growing to the right.
+    pub x: f64,
+    /// Vertical position, growing downwards.
+    pub y: f64,
+}
+
+impl Point {
+    /// A point at `x`, `y`.
+    pub fn new(x: f64, y: f64) -> Point {
+        Point { x, y }
+    }
+
+    fn lerp(self, other: Point, t: f64) -> Point {
+        Point::new(self.x + (other.x - self.x) * t, self.y + (other.y - self.y) * t)
+    }
+}
+
+/// A command of a drawing.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    /// `m`: close the current shape and start a new one at the point.
+    Move(Point),
+    /// `n`: move to the point without closing the current shape.
+    MoveNoClose(Point),
+    /// `l`: straight lines through the points.
+    Line(Vec<Point>),
+    /// `b`: cubic Bézier curves, three points for each curve.
+    Bezier(Vec<Point>),
+    /// `s`: a cubic B-spline through the control points.
+    Spline(Vec<Point>),
+    /// `p`: more control points for the last B-spline.
+    ExtendSpline(Vec<Point>),
+    /// `c`: close the last B-spline.
+    CloseSpline,
+}
+
+impl Command {
+    fn letter(&self) -> char {
+        match self {
+            Command::Move(_) => 'm',
+            Command::MoveNoClose(_) => 'n',
+            Command::Line(_) => 'l',
+            Command::Bezier(_) => 'b',
+            Command::Spline(_) => 's',
+            Command::ExtendSpline(_) => 'p',
+            Command::CloseSpline => 'c',
+        }
+    }
+
+    fn points(&self) -> &[Point] {
+        match self {
+            Command::Move(point) | Command::MoveNoClose(point) => std::slice::from_ref(point),
+            Command::Line(points) | Command::Bezier(points) | Command::Spline(points) | Command::ExtendSpline(points) => points,
+            Command::CloseSpline => &[],
+        }
+    }
+
+    fn points_mut(&mut self) -> &mut [Point] {
+        match self {
+            Command::Move(point) | Command::MoveNoClose(point) => std::slice::from_mut(point),
+            Command::Line(points) | Command::Bezier(points) | Command::Spline(points) | Command::ExtendSpline(points) => points,
+            Command::CloseSpline => &mut [],
+        }
+    }
+}
+
+/// The smallest rectangle around a drawing or text.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct BoundingBox {
+    /// The smallest x.
+    pub left: f64,
+    /// The smallest y.
+    pub top: f64,
+    /// The largest x.
+    pub right: f64,
+    /// The largest y.
+    pub bottom: f64,
+}
+
+impl BoundingBox {
+    /// The width of the box.
+    pub fn width(&self) -> f64 {
+        self.right - self.left
+    }
+
+    /// The height of the box.
+    pub fn height(&self) -> f64 {
+        self.bottom - self.top
+    }
+}
+
+/// A vector drawing made of commands.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Drawing {
+    /// The commands in order.
+    pub commands: Vec<Command>,
+}
+
+impl Drawing {
+    /// Read drawing commands. Unknown letters are skipped, and so are coordinates without a pair
+    /// or curves without enough points, in the same way renderers skip them.
+    pub fn parse(commands: &str) -> Drawing {
+        let mut drawing = Drawing::default();
+        let mut letter: Option<char> = None;
+        let mut numbers: Vec<f64> = Vec::new();
+
+        for (_, token) in tokenize(commands) {
+            match token {
+                Token::Number(number) => numbers.push(number),
+                Token::Letter(next) => {
+                    if let Some(letter) = letter {
+                        drawing.push(letter, &numbers);
+                    }
+                    letter = Some(next);
+                    numbers.clear();
+                },
+            }
+        }
+        if let Some(letter) = letter {
+            drawing.push(letter, &numbers);
+        }
+
+        drawing
+    }
+
+    fn push(&mut self, letter: char, numbers: &[f64]) {
+        let points: Vec<Point> = numbers.chunks_exact(2).map(|pair| Point::new(pair[0], pair[1])).collect();
+        let command = match letter {
+            'm' => points.last().copied().map(Command::Move),
+            'n' => points.last().copied().map(Command::MoveNoClose),
+            'l' => Some(Command::Line(points)).filter(|_| numbers.len() >= 2),
+            'b' => {
+                let curves = points.len() / 3 * 3;
+                Some(Command::Bezier(points[..curves].to_vec())).filter(|_| curves > 0)
+            },
+            's' => Some(Command::Spline(points)).filter(|points| points.points().len() >= 3),
+            'p' => Some(Command::ExtendSpline(points)).filter(|points| !points.points().is_empty()),
+            'c' => Some(Command::CloseSpline),
+            _ => None,
+        };
+        self.commands.extend(command);
+    }
+
+    /// Every point of the drawing, including the control points of curves.
+    pub fn points(&self) -> impl Iterator<Item = &Point> {
+        self.commands.iter().flat_map(|command| command.points())
+    }
+
+    fn map_points(&mut self, f: impl Fn(Point) -> Point) -> &mut Self {
+        for command in self.commands.iter_mut() {
+            for point in command.points_mut() {
+                *point = f(*point);
+            }
+        }
+        self
+    }
+
+    /// Move the drawing.
+    pub fn translate(&mut self, x: f64, y: f64) -> &mut Self {
+        self.map_points(|point| Point::new(point.x + x, point.y + y))
+    }
+
+    /// Scale the drawing from the origin.
+    pub fn scale(&mut self, x: f64, y: f64) -> &mut Self {
+        self.map_points(|point| Point::new(point.x * x, point.y * y))
+    }
+
+    /// Rotate the drawing around the point, clockwise on the screen as y grows downwards.
+    pub fn rotate(&mut self, degrees: f64, around: Point) -> &mut Self {
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        self.map_points(|point| {
+            let (x, y) = (point.x - around.x, point.y - around.y);
+            Point::new(around.x + x * cos - y * sin, around.y + x * sin + y * cos)
+        })
+    }
+
+    /// The box around the drawing, with curves flattened first. Returns `None` for an empty
+    /// drawing.
+    pub fn bounding_box(&self) -> Option<BoundingBox> {
+        let flat = self.flatten(DEFAULT_CURVE_SEGMENTS);
+        let mut points = flat.points();
+        let first = points.next()?;
+        let start = BoundingBox { left: first.x, top: first.y, right: first.x, bottom: first.y };
+        Some(points.fold(start, |bounds, point| BoundingBox {
+            left: bounds.left.min(point.x),
+            top: bounds.top.min(point.y),
+            right: bounds.right.max(point.x),
+            bottom: bounds.bottom.max(point.y),
+        }))
+    }
+
+    /// The shapes of the drawing as polylines, with each curve split into `segments` straight
+    /// lines. Shapes are closed when they are drawn, so the last point doesn't repeat the first.
+    pub fn polylines(&self, segments: usize) -> Vec<Vec<Point>> {
+        let mut shapes: Vec<Vec<Point>> = Vec::new();
+        let mut current = Point::default();
+        // control points of the B-spline being drawn
+        let mut spline: Vec<Point> = Vec::new();
+        let segments = segments.max(1);
+
+        for command in &self.commands {
+            let shape = match shapes.last_mut() {
+                Some(shape) if !matches!(command, Command::Move(_)) => shape,
+                _ => {
+                    shapes.push(Vec::new());
+                    shapes.last_mut().unwrap()
+                },
+            };
+            match command {
+                Command::Move(point) | Command::MoveNoClose(point) => {
+                    current = *point;
+                    shape.push(current);
+                    spline.clear();
+                },
+                Command::Line(points) => {
+                    shape.extend(points);
+                    current = *points.last().unwrap_or(&current);
+                    spline.clear();
+                },
+                Command::Bezier(points) => {
+                    for curve in points.chunks_exact(3) {
+                        shape.extend(bezier(current, curve[0], curve[1], curve[2], segments));
+                        current = curve[2];
+                    }
+                    spline.clear();
+                },
+                Command::Spline(points) | Command::ExtendSpline(points) => {
+                    if spline.is_empty() {
+                        spline.push(current);
+                    }
+                    let start = spline.len();
+                    spline.extend(points);
+                    for i in start.max(3)..spline.len() {
+                        shape.extend(b_spline(&spline[i - 3..=i], segments));
+                    }
+                    current = *shape.last().unwrap_or(&current);
+                },
+                Command::CloseSpline => {
+                    if spline.len() >= 3 {
+                        let count = spline.len();
+                        for i in count..count + 3 {
+                            let points: Vec<Point> = (i - 3..=i).map(|j| spline[j % count]).collect();
+                            shape.extend(b_spline(&points, segments));
+                        }
+                        current = *shape.last().unwrap_or(&current);
+                    }
+                    spline.clear();
+                },
+            }
+        }
+
+        shapes.retain(|shape| !shape.is_empty());
+        shapes
+    }
+
+    /// The same drawing with curves and B-splines replaced by straight lines, each curve split
+    /// into `segments` lines.
+    pub fn flatten(&self, segments: usize) -> Drawing {
+        let commands = self.polylines(segments).into_iter()
+            .flat_map(|shape| {
+                let mut commands = vec![Command::Move(shape[0])];
+                if shape.len() > 1 {
+                    commands.push(Command::Line(shape[1..].to_vec()));
+                }
+                commands
+            })
+            .collect();
+        Drawing { commands }
+    }
+
+    /// Read simple SVG path data with the `M`, `L`, `H`, `V`, `C`, `S`, `Q`, `T` and `Z`
+    /// commands, in absolute or relative form. Quadratic curves become cubic ones.
+    pub fn from_svg_path(path: &str) -> Result<Drawing, SvgPathError> {
+        SvgParser::default().parse(path)
+    }
+}
+
+impl fmt::Display for Drawing {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut first = true;
+        for command in &self.commands {
+            if !first {
+                write!(f, " ")?;
+            }
+            first = false;
+            write!(f, "{}", command.letter())?;
+            for point in command.points() {
+                write!(f, " {} {}", format_number(point.x), format_number(point.y))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Points along a cubic Bézier curve, without its start.
+fn bezier(p0: Point, p1: Point, p2: Point, p3: Point, segments: usize) -> Vec<Point> {
+    (1..=segments)
+        .map(|i| {
+            let t = i as f64 / segments as f64;
+            let (a, b, c) = (p0.lerp(p1, t), p1.lerp(p2, t), p2.lerp(p3, t));
+            let (d, e) = (a.lerp(b, t), b.lerp(c, t));
+            d.lerp(e, t)
+        })
+        .collect()
+}
+
+/// Points along the segment of a uniform cubic B-spline between the middle two of four control
+/// points, without its start.
+fn b_spline(points: &[Point], segments: usize) -> Vec<Point> {
+    let [p0, p1, p2, p3] = [points[0], points[1], points[2], points[3]];
+    let mix = |a: f64, b: f64, c: f64, d: f64| Point::new(
+        (a * p0.x + b * p1.x + c * p2.x + d * p3.x) / 6.0,
+        (a * p0.y + b * p1.y + c * p2.y + d * p3.y) / 6.0,
+    );
+    // the same segment as a Bézier curve
+    let start = mix(1.0, 4.0, 1.0, 0.0);
+    bezier(start, mix(0.0, 4.0, 2.0, 0.0), mix(0.0, 2.0, 4.0, 0.0), mix(0.0, 1.0, 4.0, 1.0), segments)
+}
+
+pub(crate) enum Token {
+    Letter(char),
+    Number(f64),
+}
+
+/// Split drawing commands into letters and numbers. Letters and numbers don't need spaces
+/// between them, and commas separate numbers like spaces. Each token comes with where it is in
+/// `commands`.
+pub(crate) fn tokenize(commands: &str) -> Vec<(Range<usize>, Token)> {
+    let mut tokens = Vec::new();
+    let mut chars = commands.char_indices().peekable();
+
+    while let Some((start, c)) = chars.next() {
+        if c.is_ascii_digit() || matches!(c, '-' | '+' | '.') {
+            let mut end = start + c.len_utf8();
+            let mut seen_dot = c == '.';
+            let mut seen_exponent = false;
+            while let Some(&(i, next)) = chars.peek() {
+                let accept = match next {
+                    '0'..='9' => true,
+                    '.' if !seen_dot && !seen_exponent => {
+                        seen_dot = true;
+                        true
+                    },
+                    'e' | 'E' if !seen_exponent && commands[i + 1..].starts_with(|c: char| c.is_ascii_digit() || c == '-' || c == '+') => {
+                        seen_exponent = true;
+                        true
+                    },
+                    '-' | '+' => commands[..i].ends_with(['e', 'E']) && seen_exponent,
+                    _ => false,
+                };
+                if !accept {
+                    break;
+                }
+                end = i + next.len_utf8();
+                chars.next();
+            }
+            if let Ok(number) = commands[start..end].parse::<f64>() {
+                tokens.push((start..end, Token::Number(number)));
+            }
+        } else if c.is_ascii_alphabetic() {
+            tokens.push((start..start + 1, Token::Letter(c)));
+        }
+    }
+
+    tokens
+}
+
+/// SVG path data which can't be converted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SvgPathError {
+    /// A command which isn't supported, such as `A` for arcs.
+    UnsupportedCommand(char),
+    /// A command without the numbers it needs.
+    MissingNumbers(char),
+}
+
+impl fmt::Display for SvgPathError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SvgPathError::UnsupportedCommand(command) => write!(f, "The SVG path command {} is not supported.", command),
+            SvgPathError::MissingNumbers(command) => write!(f, "The SVG path command {} is missing numbers.", command),
+        }
+    }
+}
+
+impl std::error::Error for SvgPathError {}
+
+#[derive(Default)]
+struct SvgParser {
+    drawing: Drawing,
+    current: Point,
+    start: Point,
+    /// The second control point of the last cubic curve, for `S`.
+    last_cubic: Option<Point>,
+    /// The control point of the last quadratic curve, for `T`.
+    last_quadratic: Option<Point>,
+}
+
+impl SvgParser {
+    fn parse(mut self, path: &str) -> Result<Drawing, SvgPathError> {
+        let mut command: Option<char> = None;
+        let mut numbers: Vec<f64> = Vec::new();
+
+        for (_, token) in tokenize(path) {
+            match token {
+                Token::Number(number) => numbers.push(number),
+                Token::Letter(next) => {
+                    if let Some(command) = command {
+                        self.command(command, &numbers)?;
+                    }
+                    command = Some(next);
+                    numbers.clear();
+                },
+            }
+        }
+        if let Some(command) = command {
+            self.command(command, &numbers)?;
+        }
+
+        Ok(self.drawing)
+    }
+
+    fn command(&mut self, command: char, numbers: &[f64]) -> Result<(), SvgPathError> {
+        let arity = match command.to_ascii_uppercase() {
+            'Z' => 0,
+            'H' | 'V' => 1,
+            'M' | 'L' | 'T' => 2,
+            'S' | 'Q' => 4,
+            'C' => 6,
+            _ => return Err(SvgPathError::UnsupportedCommand(command)),
+        };
+        if arity == 0 {
+            self.current = self.start;
+            self.last_cubic = None;
+            self.last_quadratic = None;
+            return Ok(());
+        }
+        if numbers.is_empty() || !numbers.len().is_multiple_of(arity) {
+            return Err(SvgPathError::MissingNumbers(command));
+        }
+
+        let relative = command.is_ascii_lowercase();
+        for (i, values) in numbers.chunks_exact(arity).enumerate() {
+            let origin = if relative { self.current } else { Point::default() };
+            let point = |index: usize| Point::new(origin.x + values[index], origin.y + values[index + 1]);
+
+            let (mut cubic, mut quadratic) = (None, None);
+            match command.to_ascii_uppercase() {
+                // pairs after the first one of a move are lines
+                'M' if i == 0 => {
+                    self.current = point(0);
+                    self.start = self.current;
+                    self.drawing.commands.push(Command::Move(self.current));
+                },
+                'M' | 'L' => self.line_to(point(0)),
+                'H' => self.line_to(Point::new(if relative { self.current.x + values[0] } else { values[0] }, self.current.y)),
+                'V' => self.line_to(Point::new(self.current.x, if relative { self.current.y + values[0] } else { values[0] })),
+                'C' => {
+                    self.curve_to(point(0), point(2), point(4));
+                    cubic = Some(point(2));
+                },
+                'S' => {
+                    let first = self.reflect(self.last_cubic);
+                    self.curve_to(first, point(0), point(2));
+                    cubic = Some(point(0));
+                },
+                'Q' => {
+                    self.quadratic_to(point(0), point(2));
+                    quadratic = Some(point(0));
+                },
+                _ => {
+                    let control = self.reflect(self.last_quadratic);
+                    self.quadratic_to(control, point(0));
+                    quadratic = Some(control);
+                },
+            }
+            self.last_cubic = cubic;
+            self.last_quadratic = quadratic;
+        }
+
+        Ok(())
+    }
+
+    fn reflect(&self, control: Option<Point>) -> Point {
+        match control {
+            Some(control) => Point::new(2.0 * self.current.x - control.x, 2.0 * self.current.y - control.y),
+            None => self.current,
+        }
+    }
+
+    fn line_to(&mut self, point: Point) {
+        match self.drawing.commands.last_mut() {
+            Some(Command::Line(points)) => points.push(point),
+            _ => self.drawing.commands.push(Command::Line(vec![point])),
+        }
+        self.current = point;
+    }
+
+    fn curve_to(&mut self, first: Point, second: Point, end: Point) {
+        match self.drawing.commands.last_mut() {
+            Some(Command::Bezier(points)) => points.extend([first, second, end]),
+            _ => self.drawing.commands.push(Command::Bezier(vec![first, second, end])),
+        }
+        self.current = end;
+    }
+
+    fn quadratic_to(&mut self, control: Point, end: Point) {
+        let start = self.current;
+        let first = start.lerp(control, 2.0 / 3.0);
+        let second = end.lerp(control, 2.0 / 3.0);
+        self.curve_to(first, second, end);
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_drawing() {
+        let drawing = Drawing::parse("m 0 0 l 100 0 100 100 7 b 50,150 0 150 0 100 n10 10 s 1 1 2 2 3 3 p 4 4 c x");
+
+        assert_eq!(vec![
+            Command::Move(Point::new(0.0, 0.0)),
+            Command::Line(vec![Point::new(100.0, 0.0), Point::new(100.0, 100.0)]),
+            Command::Bezier(vec![Point::new(50.0, 150.0), Point::new(0.0, 150.0), Point::new(0.0, 100.0)]),
+            Command::MoveNoClose(Point::new(10.0, 10.0)),
+            Command::Spline(vec![Point::new(1.0, 1.0), Point::new(2.0, 2.0), Point::new(3.0, 3.0)]),
+            Command::ExtendSpline(vec![Point::new(4.0, 4.0)]),
+            Command::CloseSpline,
+        ], drawing.commands);
+        assert_eq!("m 0 0 l 100 0 100 100 b 50 150 0 150 0 100 n 10 10 s 1 1 2 2 3 3 p 4 4 c", drawing.to_string());
+        assert_eq!("m -1.5 2 l 300 0", Drawing::parse("m-1.5 2l3e2 0").to_string());
+    }
+
+    #[test]
+    fn test_geometry() {
+        let mut drawing = Drawing::parse("m 0 0 l 10 0 10 20");
+        drawing.translate(5.0, 5.0).scale(2.0, 1.0);
+        assert_eq!("m 10 5 l 30 5 30 25", drawing.to_string());
+
+        drawing.rotate(90.0, Point::new(10.0, 5.0));
+        assert_eq!("m 10 5 l 10 25 -10 25", drawing.to_string());
+        let bounds = drawing.bounding_box().unwrap();
+        assert_eq!((-10.0, 5.0, 20.0, 20.0), (bounds.left.round(), bounds.top, bounds.width().round(), bounds.height()));
+        assert_eq!(None, Drawing::default().bounding_box());
+
+        // the curve bulges past its end points, but not as far as its control points
+        let curve = Drawing::parse("m 0 0 b 0 10 10 10 10 0");
+        assert_eq!("m 0 0 l 5 7.5 10 0", curve.flatten(2).to_string());
+        let bounds = curve.bounding_box().unwrap();
+        assert_eq!((0.0, 10.0, 7.5), (bounds.left, bounds.right, bounds.bottom));
+    }
+
+    #[test]
+    fn test_svg_path() {
+        let drawing = Drawing::from_svg_path("M10 10 h 20 v20 H10 Z m 5,5 c 0 5 5 5 5 0 s 5 -5 5 0 Q 30 30 40 15").unwrap();
+        assert_eq!("m 10 10 l 30 10 30 30 10 30 m 15 15 b 15 20 20 20 20 15 20 10 25 10 25 15 28.333 25 33.333 25 40 15", drawing.to_string());
+
+        assert_eq!(Err(SvgPathError::UnsupportedCommand('A')), Drawing::from_svg_path("M 0 0 A 5 5 0 0 1 10 10"));
+        assert_eq!(Err(SvgPathError::MissingNumbers('L')), Drawing::from_svg_path("M 0 0 L 5"));
+    }
+}
@@ -0,0 +1,211 @@
+//! # Find
+//!
+//! Find and replace text in dialogues without touching their override blocks, so replacing `c`
+//! doesn't break a `{\c&H00FF00&}` tag. Only the visible text is searched: override blocks,
+//! `\N`, `\n` and `\h` escapes and `\p` drawings are skipped, and a match can't cross them.
+//!
+//! ```rust
+//! use ass_parser::{Dialogue, Events};
+//! use ass_parser::find::FindOptions;
+//! use ass_parser::query::Query;
+//!
+//! let mut events = Events::default();
+//! events.add_dialogue(Dialogue::default().set_text("{\\c&H00FF00&}Colour me cold"));
+//!
+//! let mut options = FindOptions::default();
+//! options.set_case_insensitive(true).set_whole_word(true);
+//! let counts = events.replace("colour", "color", &options, &Query::new()).unwrap();
+//! assert_eq!(1, counts[0].count);
+//! assert_eq!(Some("{\\c&H00FF00&}color me cold"), events.iter().last().unwrap().get_text());
+//! ```
+
+use regex::{NoExpand, Regex, RegexBuilder};
+
+use crate::Events;
+use crate::query::Query;
+use crate::text::{drawing_mode, segments, Segment};
+
+/// How `Events::find` and `Events::replace` match the pattern.
+/// By default the pattern is plain text matched with the same case anywhere in a word.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct FindOptions {
+    regex: bool,
+    case_insensitive: bool,
+    whole_word: bool,
+}
+
+impl FindOptions {
+    /// Treat the pattern as a regular expression. The replacement can then use capture groups
+    /// such as `$1` or `${name}`.
+    pub fn set_regex(&mut self, regex: bool) -> &mut Self {
+        self.regex = regex;
+        self
+    }
+
+    /// Ignore the case of letters.
+    pub fn set_case_insensitive(&mut self, case_insensitive: bool) -> &mut Self {
+        self.case_insensitive = case_insensitive;
+        self
+    }
+
+    /// Only match whole words.
+    pub fn set_whole_word(&mut self, whole_word: bool) -> &mut Self {
+        self.whole_word = whole_word;
+        self
+    }
+
+    fn build(&self, pattern: &str) -> Result<Regex, regex::Error> {
+        let pattern = match self.regex {
+            true => pattern.to_string(),
+            false => regex::escape(pattern),
+        };
+        let pattern = match self.whole_word {
+            true => format!(r"\b(?:{})\b", pattern),
+            false => pattern,
+        };
+        RegexBuilder::new(&pattern)
+            .case_insensitive(self.case_insensitive)
+            .build()
+    }
+}
+
+/// The number of matches in a dialogue.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MatchCount {
+    /// Index of the dialogue.
+    pub event: usize,
+    /// Number of matches in the dialogue.
+    pub count: usize,
+}
+
+impl Events {
+    /// Count the matches of `pattern` in the visible text of the dialogues selected by `query`.
+    /// Only dialogues with at least one match are returned. Fails if the pattern is not a valid
+    /// regular expression.
+    pub fn find(&self, pattern: &str, options: &FindOptions, query: &Query) -> Result<Vec<MatchCount>, regex::Error> {
+        let regex = options.build(pattern)?;
+
+        Ok(self.iter().enumerate()
+            .filter(|(_, dialogue)| query.matches(dialogue))
+            .filter_map(|(event, dialogue)| {
+                let mut count = 0;
+                map_visible_text(dialogue.get_text().unwrap_or(""), |text| {
+                    count += regex.find_iter(text).count();
+                    text.to_string()
+                });
+                (count > 0).then_some(MatchCount { event, count })
+            })
+            .collect())
+    }
+
+    /// Replace the matches of `pattern` in the visible text of the dialogues selected by `query`.
+    /// Returns the number of replacements in each changed dialogue. Fails if the pattern is not a
+    /// valid regular expression.
+    pub fn replace(&mut self, pattern: &str, replacement: &str, options: &FindOptions, query: &Query) -> Result<Vec<MatchCount>, regex::Error> {
+        let regex = options.build(pattern)?;
+        let mut counts = Vec::new();
+
+        for (event, dialogue) in self.dialogues.dialogues.iter_mut().enumerate() {
+            if !query.matches(dialogue) {
+                continue;
+            }
+            let Some(text) = &dialogue.event.text else {
+                continue;
+            };
+
+            let mut count = 0;
+            let replaced = map_visible_text(text, |text| {
+                count += regex.find_iter(text).count();
+                match options.regex {
+                    true => regex.replace_all(text, replacement).into_owned(),
+                    false => regex.replace_all(text, NoExpand(replacement)).into_owned(),
+                }
+            });
+            if count > 0 {
+                dialogue.event.text = Some(replaced);
+                counts.push(MatchCount { event, count });
+            }
+        }
+
+        Ok(counts)
+    }
+}
+
+/// Rebuild a dialogue text with `f` applied to each run of visible text between override
+/// blocks, `\N`, `\n` and `\h` escapes. Drawings after a `\p` tag are kept as they are.
+fn map_visible_text(text: &str, mut f: impl FnMut(&str) -> String) -> String {
+    let mut mapped = String::new();
+    let mut drawing = false;
+
+    for segment in segments(text) {
+        match segment {
+            Segment::Tags(block) => {
+                drawing = drawing_mode(block, drawing);
+                mapped.push('{');
+                mapped.push_str(block);
+                mapped.push('}');
+            },
+            Segment::Text(text) if drawing => mapped.push_str(text),
+            Segment::Text(text) => {
+                let bytes = text.as_bytes();
+                let mut start = 0;
+                let mut i = 0;
+                while i + 1 < bytes.len() {
+                    if bytes[i] == b'\\' && matches!(bytes[i + 1], b'N' | b'n' | b'h') {
+                        mapped.push_str(&f(&text[start..i]));
+                        mapped.push_str(&text[i..i + 2]);
+                        i += 2;
+                        start = i;
+                    } else {
+                        i += 1;
+                    }
+                }
+                mapped.push_str(&f(&text[start..]));
+            },
+        }
+    }
+
+    mapped
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Dialogue;
+
+    fn events() -> Events {
+        Events::from_dialogues(vec![
+            Dialogue::default().set_start("0:00:01.00").set_end("0:00:02.00").set_text(r"{\c&H00FF00&}Cold cash\Nnice{\p1}m 0 0 c{\p0}cc"),
+            Dialogue::default().set_start("0:00:03.00").set_end("0:00:04.00").set_style("Signs").set_text("Picnic"),
+        ])
+    }
+
+    #[test]
+    fn test_find() {
+        let events = events();
+        let mut options = FindOptions::default();
+
+        assert_eq!(vec![MatchCount { event: 0, count: 4 }, MatchCount { event: 1, count: 2 }], events.find("c", &options, &Query::new()).unwrap());
+        assert_eq!(vec![MatchCount { event: 0, count: 1 }, MatchCount { event: 1, count: 1 }], events.find("n", &options, &Query::new()).unwrap());
+        assert_eq!(vec![MatchCount { event: 1, count: 2 }], events.find("c", &options, &Query::new().style("Signs")).unwrap());
+
+        options.set_case_insensitive(true).set_whole_word(true);
+        assert_eq!(vec![MatchCount { event: 0, count: 1 }], events.find("cold", &options, &Query::new()).unwrap());
+        assert!(events.find("(", options.set_regex(true), &Query::new()).is_err());
+    }
+
+    #[test]
+    fn test_replace() {
+        let mut events = events();
+        let mut options = FindOptions::default();
+
+        events.replace("c", "k", &options, &Query::new().time_range(0, 2500)).unwrap();
+        assert_eq!(Some(r"{\c&H00FF00&}Cold kash\Nnike{\p1}m 0 0 c{\p0}kk"), events.iter().next().unwrap().get_text());
+
+        options.set_regex(true);
+        let counts = events.replace(r"(\w)ic(\w)", "$2$1", &options, &Query::new()).unwrap();
+        assert_eq!(vec![MatchCount { event: 1, count: 1 }], counts);
+        assert_eq!(Some("nPic"), events.iter().last().unwrap().get_text());
+    }
+}
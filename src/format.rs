@@ -0,0 +1,51 @@
+use std::path::Path;
+
+/// The subtitle formats an `AssFile` can be read from and written to as text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubtitleFormat {
+    /// Advanced SubStation Alpha (`.ass`). SubStation Alpha `.ssa` files, which have
+    /// `[V4 Styles]` instead of `[V4+ Styles]`, are not read.
+    Ass,
+    /// SubRip (`.srt`).
+    Srt,
+    /// WebVTT (`.vtt`).
+    Vtt,
+    /// YouTube captions (`.sbv`).
+    Sbv,
+    /// Synced lyrics (`.lrc`).
+    Lrc,
+    /// SAMI (`.smi`, `.sami`). Every language class is read with a style named after the class.
+    Sami,
+}
+
+impl SubtitleFormat {
+    /// Get the format from a name such as `"srt"` or `"vtt"`. The name is not case sensitive and
+    /// is the same as the file extension of the format.
+    pub fn from_name(name: &str) -> Option<SubtitleFormat> {
+        match name.to_ascii_lowercase().as_str() {
+            "ass" => Some(SubtitleFormat::Ass),
+            "srt" => Some(SubtitleFormat::Srt),
+            "vtt" => Some(SubtitleFormat::Vtt),
+            "sbv" => Some(SubtitleFormat::Sbv),
+            "lrc" => Some(SubtitleFormat::Lrc),
+            "smi" | "sami" => Some(SubtitleFormat::Sami),
+            _ => None,
+        }
+    }
+
+    /// Get the format from the extension of a file name.
+    ///
+    /// # Example
+    /// ```rust
+    /// use ass_parser::SubtitleFormat;
+    ///
+    /// assert_eq!(Some(SubtitleFormat::Srt), SubtitleFormat::from_extension("RapGod.srt"));
+    /// assert_eq!(None, SubtitleFormat::from_extension("RapGod.ssa"));
+    /// ```
+    pub fn from_extension(filename: &str) -> Option<SubtitleFormat> {
+        Path::new(filename)
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .and_then(SubtitleFormat::from_name)
+    }
+}
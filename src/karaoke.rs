@@ -0,0 +1,295 @@
+//! # Karaoke
+//!
+//! Read and write the `\k`, `\K`, `\kf` and `\ko` karaoke tags of a dialogue as a list of
+//! syllables. Each syllable has a duration in centiseconds, like the tags, and its absolute
+//! start and end times follow from the start of the dialogue.
+//!
+//! ```rust
+//! use ass_parser::Dialogue;
+//! use ass_parser::karaoke::Karaoke;
+//!
+//! let dialogue = Dialogue::default()
+//!     .set_start("0:00:01.00")
+//!     .set_end("0:00:02.40")
+//!     .set_text("{\\k50}Look, {\\k50}I {\\k40}was");
+//!
+//! let mut karaoke = dialogue.get_karaoke().unwrap();
+//! assert_eq!(vec![(1000, 1500), (1500, 2000), (2000, 2400)], karaoke.times());
+//!
+//! karaoke.syllables[2].text = "was gonna".to_string();
+//! karaoke.syllables[2].set_duration_ms(900);
+//! let dialogue = dialogue.set_karaoke(&karaoke);
+//! assert_eq!(Some("{\\k50}Look, {\\k50}I {\\k90}was gonna"), dialogue.get_text());
+//!
+//! // a plain cue, such as one from an SRT file, with the time shared evenly between its words
+//! let karaoke = Karaoke::split_evenly("Look, I was", 1000, 2500);
+//! assert_eq!("{\\k50}Look, {\\k50}I {\\k50}was", karaoke.to_text());
+//! ```
+
+use crate::Dialogue;
+use crate::text::{segments, split_tags, strip_tags, Segment};
+
+/// The karaoke tags.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum KaraokeTag {
+    /// `\k`: the syllable is highlighted at once when it starts.
+    #[default]
+    K,
+    /// `\K`: the highlight sweeps from left to right over the syllable.
+    UpperK,
+    /// `\kf`: the same as `\K`.
+    Kf,
+    /// `\ko`: like `\k`, but the outline is hidden until the syllable starts.
+    Ko,
+}
+
+impl KaraokeTag {
+    /// The name of the tag, without the backslash.
+    pub fn name(&self) -> &'static str {
+        match self {
+            KaraokeTag::K => "k",
+            KaraokeTag::UpperK => "K",
+            KaraokeTag::Kf => "kf",
+            KaraokeTag::Ko => "ko",
+        }
+    }
+
+    /// Read a karaoke tag such as `\kf45`, returning the tag and its duration in centiseconds.
+    fn parse(tag: &str) -> Option<(KaraokeTag, i64)> {
+        let tag = tag.strip_prefix('\\')?;
+        let (kind, duration) = if let Some(duration) = tag.strip_prefix("kf") {
+            (KaraokeTag::Kf, duration)
+        } else if let Some(duration) = tag.strip_prefix("ko") {
+            (KaraokeTag::Ko, duration)
+        } else if let Some(duration) = tag.strip_prefix('K') {
+            (KaraokeTag::UpperK, duration)
+        } else {
+            (KaraokeTag::K, tag.strip_prefix('k')?)
+        };
+        let duration = duration.trim().parse::<f64>().ok()?;
+        Some((kind, duration.round() as i64))
+    }
+}
+
+/// A syllable of a karaoke line.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Syllable {
+    /// The karaoke tag of the syllable.
+    pub tag: KaraokeTag,
+    /// How long the syllable lasts in centiseconds.
+    pub duration: i64,
+    /// Other override tags in the block of the karaoke tag, such as `\1c&HFF0000&`.
+    pub tags: String,
+    /// The text of the syllable up to the next karaoke tag. It can contain override blocks.
+    pub text: String,
+}
+
+impl Syllable {
+    /// How long the syllable lasts in milliseconds.
+    pub fn duration_ms(&self) -> i64 {
+        self.duration * 10
+    }
+
+    /// Set how long the syllable lasts, rounded to centiseconds.
+    pub fn set_duration_ms(&mut self, duration_ms: i64) -> &mut Self {
+        self.duration = (duration_ms.max(0) + 5) / 10;
+        self
+    }
+
+    /// The text of the syllable without override blocks.
+    pub fn plain_text(&self) -> String {
+        strip_tags(&self.text)
+    }
+}
+
+/// The syllables of a karaoke line.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Karaoke {
+    /// When the first syllable starts in milliseconds, usually the start of the dialogue.
+    pub start_ms: i64,
+    /// Text and override blocks before the first karaoke tag.
+    pub prefix: String,
+    /// The syllables in order.
+    pub syllables: Vec<Syllable>,
+}
+
+impl Karaoke {
+    /// Read the karaoke tags of a dialogue text. Returns `None` if it has no karaoke tags.
+    pub fn parse(text: &str, start_ms: i64) -> Option<Karaoke> {
+        let mut karaoke = Karaoke { start_ms, ..Karaoke::default() };
+
+        for segment in segments(text) {
+            let block = match segment {
+                Segment::Tags(block) => block,
+                Segment::Text(text) => {
+                    karaoke.text_mut().push_str(text);
+                    continue;
+                },
+            };
+
+            let tags = split_tags(block);
+            if !tags.iter().any(|tag| KaraokeTag::parse(tag).is_some()) {
+                karaoke.text_mut().push_str(&format!("{{{}}}", block));
+                continue;
+            }
+
+            // tags before the first karaoke tag of the block go with it
+            let mut pending = String::new();
+            let mut found = false;
+            for tag in tags {
+                match KaraokeTag::parse(tag) {
+                    Some((tag, duration)) => {
+                        karaoke.syllables.push(Syllable { tag, duration, tags: std::mem::take(&mut pending), text: String::new() });
+                        found = true;
+                    },
+                    None if found => karaoke.syllables.last_mut().unwrap().tags.push_str(tag),
+                    None => pending.push_str(tag),
+                }
+            }
+        }
+
+        (!karaoke.syllables.is_empty()).then_some(karaoke)
+    }
+
+    /// Make a karaoke line from words and their durations in milliseconds. The words are used
+    /// as they are, so they should include the spaces between them.
+    pub fn from_words<S: AsRef<str>>(start_ms: i64, words: &[(S, i64)]) -> Karaoke {
+        // round the time each syllable starts, so rounding errors don't add up
+        let mut elapsed = 0;
+        let mut syllables = Vec::new();
+        for (word, duration_ms) in words {
+            let start = (elapsed + 5) / 10;
+            elapsed += duration_ms.max(&0);
+            syllables.push(Syllable {
+                duration: (elapsed + 5) / 10 - start,
+                text: word.as_ref().to_string(),
+                ..Syllable::default()
+            });
+        }
+
+        Karaoke { start_ms, prefix: String::new(), syllables }
+    }
+
+    /// Make a karaoke line from a plain text, with the time between `start_ms` and `end_ms`
+    /// shared evenly between its words. Words are split after spaces and `\N` line breaks.
+    pub fn split_evenly(text: &str, start_ms: i64, end_ms: i64) -> Karaoke {
+        let mut words: Vec<String> = Vec::new();
+        let mut word = String::new();
+        let mut chars = text.chars().peekable();
+        while let Some(c) = chars.next() {
+            word.push(c);
+            let line_break = c == '\\' && matches!(chars.peek(), Some('N' | 'n'));
+            if line_break {
+                word.push(chars.next().unwrap());
+            }
+            let end_of_word = (line_break || c.is_whitespace()) && !chars.peek().is_some_and(|c| c.is_whitespace());
+            if end_of_word {
+                words.push(std::mem::take(&mut word));
+            }
+        }
+        if !word.is_empty() {
+            words.push(word);
+        }
+
+        let duration = (end_ms - start_ms).max(0);
+        let count = words.len().max(1) as i64;
+        let words: Vec<(String, i64)> = words.into_iter()
+            .enumerate()
+            .map(|(i, word)| {
+                let i = i as i64;
+                (word, duration * (i + 1) / count - duration * i / count)
+            })
+            .collect();
+        Karaoke::from_words(start_ms, &words)
+    }
+
+    /// Use the same karaoke tag for every syllable.
+    pub fn set_tag(&mut self, tag: KaraokeTag) -> &mut Self {
+        for syllable in self.syllables.iter_mut() {
+            syllable.tag = tag;
+        }
+        self
+    }
+
+    /// The absolute start and end times of each syllable in milliseconds.
+    pub fn times(&self) -> Vec<(i64, i64)> {
+        let mut time = self.start_ms;
+        self.syllables.iter()
+            .map(|syllable| {
+                let start = time;
+                time += syllable.duration_ms();
+                (start, time)
+            })
+            .collect()
+    }
+
+    /// How long all the syllables last in milliseconds.
+    pub fn duration_ms(&self) -> i64 {
+        self.syllables.iter().map(Syllable::duration_ms).sum()
+    }
+
+    /// Write the karaoke line back as a dialogue text.
+    pub fn to_text(&self) -> String {
+        let mut text = self.prefix.clone();
+        for syllable in &self.syllables {
+            text.push_str(&format!("{{\\{}{}{}}}{}", syllable.tag.name(), syllable.duration, syllable.tags, syllable.text));
+        }
+        text
+    }
+
+    fn text_mut(&mut self) -> &mut String {
+        match self.syllables.last_mut() {
+            Some(syllable) => &mut syllable.text,
+            None => &mut self.prefix,
+        }
+    }
+}
+
+impl Dialogue {
+    /// The karaoke syllables of the dialogue, timed from its start. Returns `None` if the text
+    /// has no karaoke tags.
+    pub fn get_karaoke(&self) -> Option<Karaoke> {
+        Karaoke::parse(self.get_text()?, self.get_start_ms().unwrap_or(0))
+    }
+
+    /// Replace the text of the dialogue with the karaoke line.
+    pub fn set_karaoke(self, karaoke: &Karaoke) -> Self {
+        self.set_text(&karaoke.to_text())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_karaoke() {
+        let text = r"{\an8}Rap {\1c&HFF0000&\kf45\3c&H00FF00&}God{\i1} {\K30}I {\ko20\k15}was";
+        let karaoke = Karaoke::parse(text, 2000).unwrap();
+
+        assert_eq!(r"{\an8}Rap ", karaoke.prefix);
+        assert_eq!(4, karaoke.syllables.len());
+        assert_eq!(Syllable { tag: KaraokeTag::Kf, duration: 45, tags: r"\1c&HFF0000&\3c&H00FF00&".to_string(), text: r"God{\i1} ".to_string() }, karaoke.syllables[0]);
+        assert_eq!(KaraokeTag::UpperK, karaoke.syllables[1].tag);
+        assert_eq!("", karaoke.syllables[2].text);
+        assert_eq!("God ", karaoke.syllables[0].plain_text());
+        assert_eq!(vec![(2000, 2450), (2450, 2750), (2750, 2950), (2950, 3100)], karaoke.times());
+        assert_eq!(1100, karaoke.duration_ms());
+        assert_eq!(r"{\an8}Rap {\kf45\1c&HFF0000&\3c&H00FF00&}God{\i1} {\K30}I {\ko20}{\k15}was", karaoke.to_text());
+
+        assert_eq!(None, Karaoke::parse(r"{\i1}Look", 0));
+    }
+
+    #[test]
+    fn test_build_karaoke() {
+        let mut karaoke = Karaoke::from_words(0, &[("Look, ", 333), ("I ", 333), ("was", 334)]);
+        assert_eq!(r"{\k33}Look, {\k34}I {\k33}was", karaoke.to_text());
+
+        karaoke.set_tag(KaraokeTag::Kf);
+        assert_eq!(r"{\kf33}Look, {\kf34}I {\kf33}was", karaoke.to_text());
+
+        let karaoke = Karaoke::split_evenly(r"Look,  I was\Ngonna", 0, 2000);
+        assert_eq!(r"{\k50}Look,  {\k50}I {\k50}was\N{\k50}gonna", karaoke.to_text());
+    }
+}
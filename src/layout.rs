@@ -0,0 +1,749 @@
+//! # Layout
+//!
+//! Work out how big each dialogue is on the screen without a renderer: the width of every glyph
+//! is read from TrueType or OpenType fonts, lines are wrapped following `WrapStyle` (or `\q`),
+//! and the lines are placed with the alignment, margins and `\pos` of the dialogue. Sizes are in
+//! script pixels (`PlayResX` by `PlayResY`).
+//!
+//! When a font is missing, the first loaded font is used instead, and without any fonts the
+//! widths are estimated like `template::estimate_width`. Kerning and shaping are not applied,
+//! and drawings are not measured.
+//!
+//! ```rust
+//! use ass_parser::{AssFile, Dialogue, Events};
+//! use ass_parser::layout::Fonts;
+//!
+//! let mut ass_file = AssFile::new();
+//! ass_file.components.script.set_playresx("384").set_playresy("288");
+//! ass_file.components.events.set_events(Events::default())
+//!     .add_dialogue(Dialogue::default().set_text("I'm beginning to feel like a Rap God, Rap God. All my people from the front to the back nod"));
+//!
+//! // load a directory of fonts with `Fonts::from_dir("fonts")?`
+//! let fonts = Fonts::new();
+//! let layout = ass_file.layout(&fonts).pop().unwrap();
+//! assert!(!layout.overflows);
+//! assert_eq!(3, layout.lines.len());
+//! assert!(layout.lines[0].wrapped);
+//! ```
+
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+use ttf_parser::{Face, GlyphId, name_id};
+
+use crate::{AssFile, Dialogue, ScriptInfo, V4Format, V4Styles};
+use crate::contrast::Colours;
+use crate::drawing::BoundingBox;
+use crate::template::estimate_char_width;
+use crate::text::{drawing_mode, parse_number, segments, split_tags, tag_arguments, tag_name, Segment};
+
+/// A TrueType or OpenType font.
+#[derive(Debug, Clone)]
+pub struct Font {
+    data: Arc<Vec<u8>>,
+    index: u32,
+    names: Vec<String>,
+    bold: bool,
+    italic: bool,
+}
+
+impl Font {
+    /// Load the first font of a `.ttf`, `.otf` or `.ttc` file.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Font, io::Error> {
+        Font::from_bytes(fs::read(path)?)
+    }
+
+    /// Read the first font of the contents of a font file.
+    pub fn from_bytes(data: Vec<u8>) -> Result<Font, io::Error> {
+        Font::from_data(Arc::new(data), 0)
+    }
+
+    fn from_data(data: Arc<Vec<u8>>, index: u32) -> Result<Font, io::Error> {
+        let face = Face::parse(&data, index).map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error.to_string()))?;
+        let mut names: Vec<String> = Vec::new();
+        for id in [name_id::FAMILY, name_id::TYPOGRAPHIC_FAMILY, name_id::FULL_NAME] {
+            let name = face.names().into_iter().filter(|name| name.name_id == id).find_map(|name| name.to_string());
+            if let Some(name) = name.filter(|name| !names.contains(name)) {
+                names.push(name);
+            }
+        }
+        if names.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "The font has no name."));
+        }
+        let (bold, italic) = (face.is_bold(), face.is_italic());
+        Ok(Font { data, index, names, bold, italic })
+    }
+
+    /// The family name of the font, followed by its typographic family and full names.
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    /// Check if the font is bold.
+    pub fn is_bold(&self) -> bool {
+        self.bold
+    }
+
+    /// Check if the font is italic.
+    pub fn is_italic(&self) -> bool {
+        self.italic
+    }
+
+    pub(crate) fn face(&self) -> Face<'_> {
+        // the font was parsed when it was loaded
+        Face::parse(&self.data, self.index).unwrap()
+    }
+
+    /// The width of a character at a font size, before `\fscx` and `\fsp`.
+    pub fn advance(&self, c: char, size: f64) -> f64 {
+        let face = self.face();
+        advance(&face, c, size)
+    }
+}
+
+/// How many pixels one font unit takes at a font size. Renderers fit the Windows ascender and
+/// descender of the font in the font size, like VSFilter.
+pub(crate) fn font_scale(face: &Face, size: f64) -> f64 {
+    let height = match face.tables().os2 {
+        Some(os2) if os2.windows_ascender() > os2.windows_descender() => os2.windows_ascender() as f64 - os2.windows_descender() as f64,
+        _ => face.ascender() as f64 - face.descender() as f64,
+    };
+    match height > 0.0 {
+        true => size / height,
+        false => size / face.units_per_em() as f64,
+    }
+}
+
+fn advance(face: &Face, c: char, size: f64) -> f64 {
+    let glyph = face.glyph_index(c).unwrap_or(GlyphId(0));
+    face.glyph_hor_advance(glyph).unwrap_or(0) as f64 * font_scale(face, size)
+}
+
+/// The fonts used to measure text.
+#[derive(Debug, Clone, Default)]
+pub struct Fonts {
+    fonts: Vec<Font>,
+}
+
+impl Fonts {
+    /// No fonts.
+    pub fn new() -> Fonts {
+        Fonts::default()
+    }
+
+    /// Load every `.ttf`, `.otf` and `.ttc` font in a directory. Files which aren't fonts are
+    /// skipped.
+    pub fn from_dir<P: AsRef<Path>>(path: P) -> Result<Fonts, io::Error> {
+        let mut paths: Vec<_> = fs::read_dir(path)?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| {
+                let extension = path.extension().and_then(|extension| extension.to_str()).unwrap_or("");
+                ["ttf", "otf", "ttc"].iter().any(|known| extension.eq_ignore_ascii_case(known))
+            })
+            .collect();
+        paths.sort();
+
+        let mut fonts = Fonts::new();
+        for path in paths {
+            let data = Arc::new(fs::read(path)?);
+            let count = ttf_parser::fonts_in_collection(&data).unwrap_or(1);
+            for index in 0..count {
+                if let Ok(font) = Font::from_data(data.clone(), index) {
+                    fonts.add(font);
+                }
+            }
+        }
+        Ok(fonts)
+    }
+
+    /// Add a font.
+    pub fn add(&mut self, font: Font) -> &mut Self {
+        self.fonts.push(font);
+        self
+    }
+
+    /// Number of fonts.
+    pub fn len(&self) -> usize {
+        self.fonts.len()
+    }
+
+    /// Check if there are no fonts.
+    pub fn is_empty(&self) -> bool {
+        self.fonts.is_empty()
+    }
+
+    /// The font with the family or full name, preferring the one with the same weight and
+    /// slant. Falls back to the first font when no name matches.
+    pub fn find(&self, name: &str, bold: bool, italic: bool) -> Option<&Font> {
+        let name = name.trim().trim_start_matches('@');
+        self.fonts.iter()
+            .filter(|font| font.names.iter().any(|font_name| font_name.eq_ignore_ascii_case(name)))
+            // the family name (the first name) matching is worth more than the weight and slant
+            .max_by_key(|font| font.names[0].eq_ignore_ascii_case(name) as u8 * 4 + (font.bold == bold) as u8 + (font.italic == italic) as u8)
+            .or(self.fonts.first())
+    }
+
+    /// The width of a text without override tags in a style. It can be passed to
+    /// `AssFile::apply_templates_with`.
+    pub fn measure(&self, style: &V4Format, text: &str) -> f64 {
+        let run = Run::new(style);
+        let face = self.face(&run);
+        text.chars().map(|c| width(face.as_ref(), &run, c)).sum()
+    }
+
+    /// The parsed font of a run, to look up once for all of its characters.
+    pub(crate) fn face(&self, run: &Run) -> Option<Face<'_>> {
+        self.find(&run.font, run.bold, run.italic).map(Font::face)
+    }
+}
+
+/// The width of a character in a run, estimated like `template::estimate_width` without a font.
+fn width(face: Option<&Face>, run: &Run, c: char) -> f64 {
+    let c = if c == '\u{a0}' { ' ' } else { c };
+    match face {
+        Some(face) => advance(face, c, run.size) * run.scale_x / 100.0 + run.spacing,
+        None => estimate_char_width(c, run.size, run.scale_x, run.spacing),
+    }
+}
+
+/// How lines which don't fit between the margins are wrapped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WrapStyle {
+    /// `0`: lines are broken into even lines, the top one wider.
+    #[default]
+    Smart,
+    /// `1`: words which don't fit go to the next line.
+    EndOfLine,
+    /// `2`: no wrapping, and `\n` breaks lines like `\N`.
+    NoWrap,
+    /// `3`: like `Smart`, with the bottom line wider.
+    SmartBottom,
+}
+
+impl WrapStyle {
+    /// The wrap style of a `WrapStyle` field or `\q` tag.
+    pub fn from_number(number: u8) -> Option<WrapStyle> {
+        match number {
+            0 => Some(WrapStyle::Smart),
+            1 => Some(WrapStyle::EndOfLine),
+            2 => Some(WrapStyle::NoWrap),
+            3 => Some(WrapStyle::SmartBottom),
+            _ => None,
+        }
+    }
+
+    /// The number of the wrap style.
+    pub fn number(&self) -> u8 {
+        *self as u8
+    }
+}
+
+/// A line of a dialogue after wrapping.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LayoutLine {
+    /// The visible text of the line.
+    pub text: String,
+    /// Position of the left edge.
+    pub left: f64,
+    /// Position of the top edge.
+    pub top: f64,
+    /// Width of the text.
+    pub width: f64,
+    /// Height of the line, the largest font size in it.
+    pub height: f64,
+    /// Whether the line ends at an automatic line break rather than `\N` or the end.
+    pub wrapped: bool,
+    /// The characters of the line with their fonts and colours, for drawing it.
+    pub(crate) glyphs: Vec<Glyph>,
+}
+
+/// The size and position of a dialogue on the screen.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EventLayout {
+    /// Index of the dialogue.
+    pub event: usize,
+    /// The lines of the dialogue from top to bottom.
+    pub lines: Vec<LayoutLine>,
+    /// The box around all the lines.
+    pub bounding_box: BoundingBox,
+    /// Whether the box goes past the edges of the screen.
+    pub overflows: bool,
+}
+
+impl AssFile {
+    /// The layout of every dialogue, with the widths measured from the fonts. Comments are
+    /// skipped.
+    pub fn layout(&self, fonts: &Fonts) -> Vec<EventLayout> {
+        let script = &self.components.script;
+        self.components.events.iter().enumerate()
+            .filter(|(_, dialogue)| !dialogue.is_comment())
+            .map(|(event, dialogue)| {
+                let mut layout = layout_dialogue(dialogue, &self.components.v4, fonts, script.wrap_style(), script.play_res());
+                layout.event = event;
+                layout
+            })
+            .collect()
+    }
+}
+
+impl ScriptInfo {
+    /// The `WrapStyle` of the script, `Smart` when it is missing.
+    pub(crate) fn wrap_style(&self) -> WrapStyle {
+        self.get_field("WrapStyle")
+            .and_then(|value| value.trim().parse::<u8>().ok())
+            .and_then(WrapStyle::from_number)
+            .unwrap_or_default()
+    }
+}
+
+/// The font settings and colours of a run of text.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Run {
+    pub(crate) font: String,
+    pub(crate) size: f64,
+    pub(crate) scale_x: f64,
+    pub(crate) scale_y: f64,
+    pub(crate) spacing: f64,
+    pub(crate) bold: bool,
+    pub(crate) italic: bool,
+    pub(crate) colours: Colours,
+}
+
+impl Run {
+    fn new(style: &V4Format) -> Run {
+        let flag = |value: Option<&str>| value.is_some_and(|value| !matches!(value.trim(), "" | "0"));
+        Run {
+            font: style.get_fontname().unwrap_or("Arial").to_string(),
+            size: parse_number(style.get_fontsize(), 20.0),
+            scale_x: parse_number(style.get_scalex(), 100.0),
+            scale_y: parse_number(style.get_scaley(), 100.0),
+            spacing: parse_number(style.get_spacing(), 0.0),
+            bold: flag(style.get_bold()),
+            italic: flag(style.get_italic()),
+            colours: Colours::new(style),
+        }
+    }
+
+    pub(crate) fn height(&self) -> f64 {
+        self.size * self.scale_y / 100.0
+    }
+
+    /// Follow a tag of an override block. `\r` goes back to the style of `line_style`.
+    fn apply(&mut self, tag: &str, styles: &V4Styles, line_style: &str) {
+        let Some(name) = tag_name(tag) else {
+            return;
+        };
+        let value = tag.trim()[name.len()..].trim();
+        let parsed = value.parse::<f64>().ok();
+        match name {
+            "fn" => self.font = value.to_string(),
+            "fs" => self.size = parsed.filter(|size| *size > 0.0).unwrap_or(self.size),
+            "fscx" => self.scale_x = parsed.unwrap_or(self.scale_x),
+            "fscy" => self.scale_y = parsed.unwrap_or(self.scale_y),
+            "fsp" => self.spacing = parsed.unwrap_or(self.spacing),
+            "b" => self.bold = parsed.is_some_and(|weight| weight == 1.0 || weight >= 700.0),
+            "i" => self.italic = parsed.is_some_and(|italic| italic != 0.0),
+            "r" => {
+                let style = if value.is_empty() { line_style } else { value };
+                *self = Run::new(&styles.get_style(style).cloned().unwrap_or_default());
+            },
+            _ => self.colours.apply(&format!("\\{}", tag), styles, line_style),
+        }
+    }
+}
+
+/// A character placed on a line. The glyphs of a run share its settings.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Glyph {
+    pub(crate) c: char,
+    pub(crate) width: f64,
+    pub(crate) run: Arc<Run>,
+}
+
+/// A word of a paragraph, with the spaces before it.
+#[derive(Debug, Clone, Default)]
+struct Word {
+    text: String,
+    width: f64,
+    height: f64,
+    glyphs: Vec<Glyph>,
+    space: String,
+    space_width: f64,
+    space_glyphs: Vec<Glyph>,
+}
+
+/// The text between hard line breaks.
+#[derive(Debug, Clone, Default)]
+struct Paragraph {
+    words: Vec<Word>,
+    /// The height of the line when it has no words.
+    height: f64,
+}
+
+pub(crate) fn layout_dialogue(dialogue: &Dialogue, styles: &V4Styles, fonts: &Fonts, wrap_style: WrapStyle, (play_res_x, play_res_y): (f64, f64)) -> EventLayout {
+    let line_style = dialogue.get_style().unwrap_or("");
+    let style = styles.get_style(line_style).cloned().unwrap_or_default();
+    let mut run = Run::new(&style);
+    let mut alignment = parse_number(style.get_alignment(), 2.0) as u8;
+    let mut wrap_style = wrap_style;
+    let mut position: Option<(f64, f64)> = None;
+    let mut drawing = false;
+
+    let text = dialogue.get_text().unwrap_or("");
+    // \q changes the whole line, so it is read before the text is split
+    for segment in segments(text) {
+        if let Segment::Tags(block) = segment {
+            for tag in split_tags(block) {
+                let Some(tag) = tag.strip_prefix('\\') else {
+                    continue;
+                };
+                let value = |name: &str| tag.trim()[name.len()..].trim().parse::<u8>().ok();
+                match tag_name(tag) {
+                    Some("q") => wrap_style = value("q").and_then(WrapStyle::from_number).unwrap_or(wrap_style),
+                    Some("an") => alignment = value("an").filter(|an| (1..=9).contains(an)).unwrap_or(alignment),
+                    Some("a") => alignment = value("a").and_then(legacy_alignment).unwrap_or(alignment),
+                    Some("pos") if position.is_none() => {
+                        let arguments = tag_arguments(tag).unwrap_or_default();
+                        if let [x, y] = arguments.as_slice() {
+                            position = x.parse::<f64>().ok().zip(y.parse::<f64>().ok());
+                        }
+                    },
+                    _ => {},
+                }
+            }
+        }
+    }
+
+    let mut paragraphs = vec![Paragraph::default()];
+    let mut in_space = false;
+    let mut shared_run = Arc::new(run.clone());
+    let mut face = fonts.face(&run);
+    for segment in segments(text) {
+        match segment {
+            Segment::Tags(block) => {
+                for tag in split_tags(block) {
+                    if let Some(tag) = tag.strip_prefix('\\') {
+                        run.apply(tag, styles, line_style);
+                    }
+                }
+                if run != *shared_run {
+                    shared_run = Arc::new(run.clone());
+                    face = fonts.face(&run);
+                }
+                drawing = drawing_mode(block, drawing);
+            },
+            Segment::Text(_) if drawing => {},
+            Segment::Text(visible) => {
+                let mut chars = visible.chars().peekable();
+                while let Some(mut c) = chars.next() {
+                    if c == '\\' {
+                        match chars.peek() {
+                            Some('N') => {
+                                chars.next();
+                                paragraphs.last_mut().unwrap().height = run.height();
+                                paragraphs.push(Paragraph::default());
+                                in_space = false;
+                                continue;
+                            },
+                            Some('n') if wrap_style == WrapStyle::NoWrap => {
+                                chars.next();
+                                paragraphs.last_mut().unwrap().height = run.height();
+                                paragraphs.push(Paragraph::default());
+                                in_space = false;
+                                continue;
+                            },
+                            Some('n') => {
+                                chars.next();
+                                c = ' ';
+                            },
+                            Some('h') => {
+                                chars.next();
+                                c = '\u{a0}';
+                            },
+                            _ => {},
+                        }
+                    }
+
+                    let width = width(face.as_ref(), &run, c);
+                    let paragraph = paragraphs.last_mut().unwrap();
+                    if c == ' ' {
+                        if !paragraph.words.is_empty() {
+                            if !in_space {
+                                paragraph.words.push(Word::default());
+                            }
+                            let word = paragraph.words.last_mut().unwrap();
+                            word.space.push(c);
+                            word.space_width += width;
+                            word.space_glyphs.push(Glyph { c, width, run: shared_run.clone() });
+                            in_space = true;
+                        }
+                        continue;
+                    }
+                    if paragraph.words.is_empty() {
+                        paragraph.words.push(Word::default());
+                    }
+                    let word = paragraph.words.last_mut().unwrap();
+                    let c = if c == '\u{a0}' { ' ' } else { c };
+                    word.text.push(c);
+                    word.width += width;
+                    word.glyphs.push(Glyph { c, width, run: shared_run.clone() });
+                    word.height = word.height.max(run.height());
+                    in_space = false;
+                }
+            },
+        }
+    }
+    paragraphs.last_mut().unwrap().height = run.height();
+
+    let (margin_l, margin_r, margin_v) = dialogue.margins(&style);
+    let max_width = play_res_x - margin_l - margin_r;
+
+    let mut lines = Vec::new();
+    for paragraph in paragraphs {
+        // spaces at the end of a paragraph are not drawn
+        let words: Vec<Word> = paragraph.words.into_iter().filter(|word| !word.text.is_empty()).collect();
+        if words.is_empty() {
+            lines.push(LayoutLine { text: String::new(), left: 0.0, top: 0.0, width: 0.0, height: paragraph.height, wrapped: false, glyphs: Vec::new() });
+            continue;
+        }
+        let starts = wrap(&words, wrap_style, max_width);
+        for (i, start) in starts.iter().enumerate() {
+            let end = starts.get(i + 1).copied().unwrap_or(words.len());
+            let mut line = LayoutLine { text: String::new(), left: 0.0, top: 0.0, width: 0.0, height: 0.0, wrapped: end < words.len(), glyphs: Vec::new() };
+            for (j, word) in words[*start..end].iter().enumerate() {
+                if j > 0 {
+                    line.text.push_str(&word.space);
+                    line.width += word.space_width;
+                    line.glyphs.extend(word.space_glyphs.iter().cloned());
+                }
+                line.text.push_str(&word.text);
+                line.width += word.width;
+                line.glyphs.extend(word.glyphs.iter().cloned());
+                line.height = line.height.max(word.height);
+            }
+            lines.push(line);
+        }
+    }
+
+    let width = lines.iter().map(|line| line.width).fold(0.0, f64::max);
+    let height: f64 = lines.iter().map(|line| line.height).sum();
+    let column = match alignment % 3 {
+        1 => 0.0,
+        0 => 1.0,
+        _ => 0.5,
+    };
+    let row = match alignment {
+        7..=9 => 0.0,
+        4..=6 => 0.5,
+        _ => 1.0,
+    };
+    let (left, top) = match position {
+        Some((x, y)) => (x - width * column, y - height * row),
+        None => {
+            let left = match alignment % 3 {
+                1 => margin_l,
+                0 => play_res_x - margin_r - width,
+                _ => margin_l + (max_width - width) / 2.0,
+            };
+            let top = match alignment {
+                7..=9 => margin_v,
+                4..=6 => (play_res_y - height) / 2.0,
+                _ => play_res_y - margin_v - height,
+            };
+            (left, top)
+        },
+    };
+
+    let mut line_top = top;
+    for line in lines.iter_mut() {
+        line.left = left + (width - line.width) * column;
+        line.top = line_top;
+        line_top += line.height;
+    }
+
+    let bounding_box = BoundingBox { left, top, right: left + width, bottom: top + height };
+    let overflows = bounding_box.left < 0.0 || bounding_box.top < 0.0 || bounding_box.right > play_res_x || bounding_box.bottom > play_res_y;
+    EventLayout { event: 0, lines, bounding_box, overflows }
+}
+
+/// The `\an` alignment of a legacy `\a` alignment.
+fn legacy_alignment(a: u8) -> Option<u8> {
+    match a {
+        1..=3 => Some(a),
+        5..=7 => Some(a + 2),
+        9..=11 => Some(a - 5),
+        _ => None,
+    }
+}
+
+/// The index of the first word of each line.
+fn wrap(words: &[Word], wrap_style: WrapStyle, max_width: f64) -> Vec<usize> {
+    let widths: Vec<f64> = words.iter().map(|word| word.width).collect();
+    let spaces: Vec<f64> = words.iter().skip(1).map(|word| word.space_width).collect();
+
+    match wrap_style {
+        WrapStyle::NoWrap => vec![0],
+        WrapStyle::EndOfLine => greedy(&widths, &spaces, max_width),
+        WrapStyle::Smart => balanced(&widths, &spaces, max_width),
+        WrapStyle::SmartBottom => {
+            // wrapping the words backwards puts the wider lines at the bottom
+            let widths: Vec<f64> = widths.into_iter().rev().collect();
+            let spaces: Vec<f64> = spaces.into_iter().rev().collect();
+            let count = words.len();
+            let mut starts: Vec<usize> = balanced(&widths, &spaces, max_width).into_iter()
+                .map(|start| count - start)
+                .filter(|start| *start < count)
+                .collect();
+            starts.push(0);
+            starts.sort();
+            starts
+        },
+    }
+}
+
+/// Fill each line with as many words as fit. `spaces[i]` is the width between word `i` and the
+/// next one.
+fn greedy(widths: &[f64], spaces: &[f64], max_width: f64) -> Vec<usize> {
+    let mut starts = vec![0];
+    let mut width = widths[0];
+    for i in 1..widths.len() {
+        let next = width + spaces[i - 1] + widths[i];
+        match next > max_width {
+            true => {
+                starts.push(i);
+                width = widths[i];
+            },
+            false => width = next,
+        }
+    }
+    starts
+}
+
+/// Wrap into as few lines as `greedy`, with the narrowest width which still needs no more lines,
+/// so the lines come out about even.
+fn balanced(widths: &[f64], spaces: &[f64], max_width: f64) -> Vec<usize> {
+    let starts = greedy(widths, spaces, max_width);
+    if starts.len() < 2 {
+        return starts;
+    }
+
+    let total: f64 = widths.iter().sum::<f64>() + spaces.iter().sum::<f64>();
+    let widest = widths.iter().copied().fold(0.0, f64::max);
+    let (mut low, mut high) = ((total / starts.len() as f64).max(widest), max_width.max(widest));
+    for _ in 0..40 {
+        let middle = (low + high) / 2.0;
+        match greedy(widths, spaces, middle).len() <= starts.len() {
+            true => high = middle,
+            false => low = middle,
+        }
+    }
+    greedy(widths, spaces, high)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::ass_file_with;
+
+    fn with_wrap_style(wrap_style: &str, texts: &[&str]) -> AssFile {
+        let mut ass_file = ass_file_with(texts);
+        ass_file.components.script.set_field("WrapStyle", wrap_style);
+        ass_file
+    }
+
+    const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures");
+
+    fn fixture_fonts() -> Fonts {
+        Fonts::from_dir(FIXTURES).unwrap()
+    }
+
+    fn texts(layout: &EventLayout) -> Vec<&str> {
+        layout.lines.iter().map(|line| line.text.as_str()).collect()
+    }
+
+    #[test]
+    fn test_wrap_styles() {
+        // without fonts each character is 10 wide, with 180 between the margins
+        let text = r"aa bb cc dd ee ff gg hh ii\Njj";
+        let smart = with_wrap_style("0", &[text]).layout(&Fonts::new());
+        assert_eq!(vec!["aa bb cc dd ee", "ff gg hh ii", "jj"], texts(&smart[0]));
+        assert_eq!((true, false, false), (smart[0].lines[0].wrapped, smart[0].lines[1].wrapped, smart[0].lines[2].wrapped));
+
+        let end_of_line = with_wrap_style("1", &[text]).layout(&Fonts::new());
+        assert_eq!(vec!["aa bb cc dd ee ff", "gg hh ii", "jj"], texts(&end_of_line[0]));
+
+        let bottom = with_wrap_style("3", &[text]).layout(&Fonts::new());
+        assert_eq!(vec!["aa bb cc dd", "ee ff gg hh ii", "jj"], texts(&bottom[0]));
+
+        let no_wrap = with_wrap_style("1", &[&format!(r"{{\q2}}{}\nkk", text)]).layout(&Fonts::new());
+        assert_eq!(vec!["aa bb cc dd ee ff gg hh ii", "jj", "kk"], texts(&no_wrap[0]));
+        assert!(no_wrap[0].overflows);
+    }
+
+    #[test]
+    fn test_bounding_box() {
+        let layouts = with_wrap_style("0", &[
+            "Rap God",
+            r"{\an7\fs40}Rap\NGod",
+            r"{\pos(100,50)\an5}Rap",
+            r"{\p1}m 0 0 l 100 100{\p0}Rap",
+        ]).layout(&Fonts::new());
+
+        assert_eq!(BoundingBox { left: 65.0, top: 70.0, right: 135.0, bottom: 90.0 }, layouts[0].bounding_box);
+        assert_eq!(BoundingBox { left: 10.0, top: 10.0, right: 70.0, bottom: 90.0 }, layouts[1].bounding_box);
+        assert_eq!(BoundingBox { left: 85.0, top: 40.0, right: 115.0, bottom: 60.0 }, layouts[2].bounding_box);
+        assert_eq!(30.0, layouts[3].bounding_box.width());
+        assert_eq!((10.0, 50.0), (layouts[1].lines[1].left, layouts[1].lines[1].top));
+        assert!(layouts.iter().all(|layout| !layout.overflows));
+    }
+
+    #[test]
+    fn test_fonts() {
+        assert!(Font::from_bytes(vec![0; 16]).is_err());
+        assert!(Fonts::new().find("Arial", false, false).is_none());
+        assert_eq!(Some(WrapStyle::SmartBottom), WrapStyle::from_number(3));
+
+        // the Box Sans fixtures have 1000 units per em, and boxes 500 units wide for most letters
+        let regular = Font::from_file(format!("{}/BoxSans-Regular.ttf", FIXTURES)).unwrap();
+        assert_eq!(["Box Sans"], regular.names());
+        assert!(!regular.is_bold() && !regular.is_italic());
+        assert_eq!(0.02, font_scale(&regular.face(), 20.0));
+        assert_eq!((10.0, 15.0, 5.0), (regular.advance('a', 20.0), regular.advance('m', 20.0), regular.advance(' ', 20.0)));
+
+        let fonts = fixture_fonts();
+        assert_eq!(3, fonts.len());
+        let bold = fonts.find("Box Sans", true, false).unwrap();
+        assert_eq!(["Box Sans", "Box Sans Bold"], bold.names());
+        assert_eq!(12.0, bold.advance('a', 20.0));
+        assert!(fonts.find("box sans", false, true).unwrap().is_italic());
+        let regular = fonts.find("Box Sans", false, false).unwrap();
+        assert!(!regular.is_bold() && !regular.is_italic());
+        // a full name match beats the weight
+        assert!(fonts.find("Box Sans Bold", false, false).unwrap().is_bold());
+
+        let mut style = V4Format::default();
+        style.set_fontname("Box Sans").set_fontsize("20");
+        assert_eq!(65.0, fonts.measure(&style, "Rap God"));
+        assert_eq!(70.0, Fonts::new().measure(&style, "Rap God"));
+    }
+
+    #[test]
+    fn test_font_wrapping() {
+        let text = "mmmm mmmm iiii iiii";
+        let mut ass_file = with_wrap_style("1", &[text, &format!(r"{{\b1}}{}", text)]);
+        ass_file.components.v4.set_fontname("Box Sans");
+
+        // 175 wide with the font, but the estimate of 190 doesn't fit in 180
+        let layouts = ass_file.layout(&fixture_fonts());
+        assert_eq!(vec![text], texts(&layouts[0]));
+        assert_eq!(BoundingBox { left: 12.5, top: 70.0, right: 187.5, bottom: 90.0 }, layouts[0].bounding_box);
+        assert_eq!(2, ass_file.layout(&Fonts::new())[0].lines.len());
+
+        // bold m and i are 17 and 7 wide
+        assert_eq!(vec!["mmmm mmmm iiii", "iiii"], texts(&layouts[1]));
+        assert_eq!(174.0, layouts[1].lines[0].width);
+    }
+}
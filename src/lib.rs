@@ -44,6 +44,7 @@
 /// Here we create an .ass file with default values and When you open the .ass file you can see the
 /// following content.
 /// ```
+/// [Script Info]
 /// ScriptType: v4.00+
 /// PlayResX: 384
 /// PlayResY: 288
@@ -214,6 +215,7 @@
 /// ## This will generate an ASS file which would be similiar to this
 ///
 /// ```
+///[Script Info]
 ///ScriptType: FFMPEG
 ///PlayResX: 384
 ///PlayResY: 288
@@ -275,11 +277,40 @@
 use hex_color::HexColor;
 use std::{fs, io::Read};
 use std::io::{Seek, Write};
-use std::ops::Deref;
+use std::ops::{Deref, DerefMut};
 use std::fmt;
 use std::iter::Iterator;
 
 mod parser;
+mod timestamp;
+mod text;
+mod sbv;
+mod lrc;
+mod sami;
+mod stl;
+mod vtt;
+mod format;
+mod styles;
+pub mod lint;
+pub mod timing;
+pub mod reading_speed;
+pub mod rescale;
+pub mod merge;
+pub mod split;
+pub mod query;
+pub mod find;
+pub mod karaoke;
+pub mod template;
+pub mod colour;
+pub mod contrast;
+pub mod tags;
+pub mod drawing;
+pub mod layout;
+#[cfg(feature = "render")]
+pub mod render;
+
+pub use format::SubtitleFormat;
+pub use styles::RenameStyleError;
 
 type SrtData = parser::SrtContent;
 
@@ -293,6 +324,7 @@ const V4_HEADER:&str = "[V4+ Styles]";
 const V4_STYLE_HEAD:&str = "Style: ";
 const EVENTS_HEADER:&str = "[Events]";
 const EVENT_HEAD:&str = "Dialogue: ";
+const COMMENT_HEAD:&str = "Comment: ";
 
 
 type Result<T> = std::result::Result<T, IndexNotFound>;
@@ -312,12 +344,41 @@ impl std::fmt::Display for IndexNotFound {
 /// the `.ass` file.
 
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ScriptInfo {
+    #[cfg_attr(feature = "serde", serde(rename = "ScriptType"))]
     scripttype: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "PlayResX"))]
     playresx: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "PlayResY"))]
     playresy: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "ScaledBorderAndShadow"))]
     scaledborderandshadow: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "YCbCr Matrix"))]
     ycbcr_matrix: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "Fields", with = "serde_fields"))]
+    fields: Vec<[String; 2]>,
+}
+
+/// Serialize the extra `Script Info` fields as `[key, value]` pairs without the `": "` that is
+/// kept after each key.
+#[cfg(feature = "serde")]
+mod serde_fields {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(fields: &[[String; 2]], serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let pairs: Vec<[&str; 2]> = fields.iter()
+            .map(|[key, value]| [key.strip_suffix(": ").unwrap_or(key), value.as_str()])
+            .collect();
+        pairs.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Vec<[String; 2]>, D::Error> {
+        let pairs = Vec::<[String; 2]>::deserialize(deserializer)?;
+        Ok(pairs.into_iter()
+            .map(|[key, value]| [key + ": ", value])
+            .collect())
+    }
 }
 
 impl ScriptInfo {
@@ -339,6 +400,9 @@ impl ScriptInfo {
         if let Some(value) = &self.ycbcr_matrix {
             values.push([SCRIPT_YCBCR_MATRIX, value])
         }
+        for [key, value] in &self.fields {
+            values.push([key, value])
+        }
         values
     }
 }
@@ -351,6 +415,7 @@ impl ScriptInfo {
     		playresy: None,
     		scaledborderandshadow: None,
     		ycbcr_matrix: None,
+    		fields: Vec::new(),
         }
     }
 
@@ -368,6 +433,7 @@ impl Default for ScriptInfo {
     		playresy: Some("288".to_string()),
     		scaledborderandshadow: Some("yes".to_string()),
     		ycbcr_matrix: Some("None".to_string()),
+    		fields: Vec::new(),
         }
     }
 }
@@ -418,6 +484,44 @@ impl ScriptInfo {
 		self.ycbcr_matrix = Some(value.to_string());
 		self
 	}
+    /// Set any other `Script Info` field such as `Title` or `Original Script`.
+    /// If the field already exists its value is replaced, otherwise it is written after the
+    /// standard fields.
+    pub fn set_field(&mut self, key: &str, value: &str) -> &mut Self {
+        let key = key.to_string() + ": ";
+        match self.fields.iter_mut().find(|field| field[0] == key) {
+            Some(field) => field[1] = value.to_string(),
+            None => self.fields.push([key, value.to_string()]),
+        }
+        self
+    }
+    /// get the ScriptType of the .ass file.
+    pub fn get_scripttype(&self) -> Option<&str> {
+        self.scripttype.as_deref()
+    }
+    /// get the PlayResX of the .ass file.
+    pub fn get_playresx(&self) -> Option<&str> {
+        self.playresx.as_deref()
+    }
+    /// get the PlayResY of the .ass file.
+    pub fn get_playresy(&self) -> Option<&str> {
+        self.playresy.as_deref()
+    }
+    /// get the ScaledBorderAndShadow of the .ass file.
+    pub fn get_scaledborderandshadow(&self) -> Option<&str> {
+        self.scaledborderandshadow.as_deref()
+    }
+    /// get the YCbCr Matrix of the .ass file.
+    pub fn get_ycbcr_matrix(&self) -> Option<&str> {
+        self.ycbcr_matrix.as_deref()
+    }
+    /// Get the value of a `Script Info` field set with `set_field` or read from a file.
+    pub fn get_field(&self, key: &str) -> Option<&str> {
+        let key = key.to_string() + ": ";
+        self.fields.iter()
+            .find(|field| field[0] == key)
+            .map(|field| field[1].as_str())
+    }
 }
 
 
@@ -428,29 +532,53 @@ impl ScriptInfo {
 /// encoding colors and many other important parts of the the `.ass` file.
 
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct V4Format {
+    #[cfg_attr(feature = "serde", serde(rename = "Name"))]
     name: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "Fontname"))]
     fontname: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "Fontsize"))]
     fontsize: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "PrimaryColour"))]
     primarycolour: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "SecondaryColour"))]
     secondarycolour: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "OutlineColour"))]
     outlinecolour: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "BackColour"))]
     backcolour: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "Bold"))]
     bold: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "Italic"))]
     italic: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "Underline"))]
     underline: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "StrikeOut"))]
     strikeout: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "ScaleX"))]
     scalex: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "ScaleY"))]
     scaley: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "Spacing"))]
     spacing: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "Angle"))]
     angle: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "BorderStyle"))]
     borderstyle: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "Outline"))]
     outline: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "Shadow"))]
     shadow: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "Alignment"))]
     alignment: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "MarginL"))]
     marginl: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "MarginR"))]
     marginr: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "MarginV"))]
     marginv: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "Encoding"))]
     encoding: Option<String>,
 }
 
@@ -799,12 +927,222 @@ impl V4Format {
 	}
 }
 
+impl V4Format {
+    /// get the name of the V4 field.
+    pub fn get_name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+    /// get the fontname of the V4 field.
+    pub fn get_fontname(&self) -> Option<&str> {
+        self.fontname.as_deref()
+    }
+    /// get the fontsize of the V4 field.
+    pub fn get_fontsize(&self) -> Option<&str> {
+        self.fontsize.as_deref()
+    }
+    /// get the primarycolour of the V4 field.
+    pub fn get_primarycolour(&self) -> Option<&str> {
+        self.primarycolour.as_deref()
+    }
+    /// get the secondarycolour of the V4 field.
+    pub fn get_secondarycolour(&self) -> Option<&str> {
+        self.secondarycolour.as_deref()
+    }
+    /// get the outlinecolour of the V4 field.
+    pub fn get_outlinecolour(&self) -> Option<&str> {
+        self.outlinecolour.as_deref()
+    }
+    /// get the backcolour of the V4 field.
+    pub fn get_backcolour(&self) -> Option<&str> {
+        self.backcolour.as_deref()
+    }
+    /// get the bold of the V4 field.
+    pub fn get_bold(&self) -> Option<&str> {
+        self.bold.as_deref()
+    }
+    /// get the italic of the V4 field.
+    pub fn get_italic(&self) -> Option<&str> {
+        self.italic.as_deref()
+    }
+    /// get the underline of the V4 field.
+    pub fn get_underline(&self) -> Option<&str> {
+        self.underline.as_deref()
+    }
+    /// get the strikeout of the V4 field.
+    pub fn get_strikeout(&self) -> Option<&str> {
+        self.strikeout.as_deref()
+    }
+    /// get the scalex of the V4 field.
+    pub fn get_scalex(&self) -> Option<&str> {
+        self.scalex.as_deref()
+    }
+    /// get the scaley of the V4 field.
+    pub fn get_scaley(&self) -> Option<&str> {
+        self.scaley.as_deref()
+    }
+    /// get the spacing of the V4 field.
+    pub fn get_spacing(&self) -> Option<&str> {
+        self.spacing.as_deref()
+    }
+    /// get the angle of the V4 field.
+    pub fn get_angle(&self) -> Option<&str> {
+        self.angle.as_deref()
+    }
+    /// get the borderstyle of the V4 field.
+    pub fn get_borderstyle(&self) -> Option<&str> {
+        self.borderstyle.as_deref()
+    }
+    /// get the outline of the V4 field.
+    pub fn get_outline(&self) -> Option<&str> {
+        self.outline.as_deref()
+    }
+    /// get the shadow of the V4 field.
+    pub fn get_shadow(&self) -> Option<&str> {
+        self.shadow.as_deref()
+    }
+    /// get the alignment of the V4 field.
+    pub fn get_alignment(&self) -> Option<&str> {
+        self.alignment.as_deref()
+    }
+    /// get the marginl of the V4 field.
+    pub fn get_marginl(&self) -> Option<&str> {
+        self.marginl.as_deref()
+    }
+    /// get the marginr of the V4 field.
+    pub fn get_marginr(&self) -> Option<&str> {
+        self.marginr.as_deref()
+    }
+    /// get the marginv of the V4 field.
+    pub fn get_marginv(&self) -> Option<&str> {
+        self.marginv.as_deref()
+    }
+    /// get the encoding of the V4 field.
+    pub fn get_encoding(&self) -> Option<&str> {
+        self.encoding.as_deref()
+    }
+
+    /// Check if two styles are the same apart from their names.
+    pub(crate) fn same_properties(&self, other: &V4Format) -> bool {
+        V4Format { name: None, ..self.clone() } == V4Format { name: None, ..other.clone() }
+    }
+}
+
+
+/// # V4Styles
+///
+/// All the `Style: ` lines of the `[V4+ Styles]` section.
+/// There is always at least one style. `V4Styles` dereferences to the first style so setters such
+/// as `set_primarycolour` can be called on it directly like on a single `V4Format`.
+
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct V4Styles {
+    styles: Vec<V4Format>,
+}
+
+impl V4Styles {
+    fn new() -> V4Styles {
+        V4Styles {
+            styles: vec![V4Format::new()],
+        }
+    }
+
+    /// Replace every style with a single V4 style and return it.
+    pub fn set_v4(&mut self, v4: V4Format) -> &mut V4Format {
+        self.styles = vec![v4];
+        &mut self.styles[0]
+    }
+
+    /// Add a style to the end of the `[V4+ Styles]` section.
+    /// If a style with the same name already exists it is replaced instead.
+    pub fn add_style(&mut self, v4: V4Format) -> &mut V4Styles {
+        match self.styles.iter_mut().find(|style| style.name.is_some() && style.name == v4.name) {
+            Some(style) => *style = v4,
+            None => self.styles.push(v4),
+        }
+        self
+    }
+
+    /// Get the style with the given name.
+    pub fn get_style(&self, name: &str) -> Option<&V4Format> {
+        self.styles.iter().find(|style| style.get_name() == Some(name))
+    }
+
+    /// Get the style with the given name to modify it.
+    pub fn get_style_mut(&mut self, name: &str) -> Option<&mut V4Format> {
+        self.styles.iter_mut().find(|style| style.get_name() == Some(name))
+    }
+
+    /// Remove the style with the given name and return it.
+    /// Returns `None` if there is no such style or if it is the only style left.
+    pub fn remove_style(&mut self, name: &str) -> Option<V4Format> {
+        let index = self.styles.iter().position(|style| style.get_name() == Some(name))?;
+        if self.styles.len() == 1 {
+            return None;
+        }
+        Some(self.styles.remove(index))
+    }
+
+    /// Iterate over each style.
+    pub fn iter(&self) -> std::slice::Iter<'_, V4Format> {
+        self.styles.iter()
+    }
+
+    /// Number of styles.
+    pub fn len(&self) -> usize {
+        self.styles.len()
+    }
+
+    /// Check if there are no styles. Always `false` since there is at least one style.
+    pub fn is_empty(&self) -> bool {
+        self.styles.is_empty()
+    }
+}
+
+/// Deserialize the styles from a list, which must hold at least one style.
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for V4Styles {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<V4Styles, D::Error> {
+        let styles = Vec::<V4Format>::deserialize(deserializer)?;
+        if styles.is_empty() {
+            return Err(serde::de::Error::invalid_length(0, &"at least one style"));
+        }
+        Ok(V4Styles { styles })
+    }
+}
+
+impl Default for V4Styles {
+    /// A single '`Default`' style.
+    fn default() -> V4Styles {
+        V4Styles {
+            styles: vec![V4Format::default()],
+        }
+    }
+}
+
+impl Deref for V4Styles {
+    type Target = V4Format;
+
+    fn deref(&self) -> &Self::Target {
+        &self.styles[0]
+    }
+}
+
+impl DerefMut for V4Styles {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.styles[0]
+    }
+}
+
 
 /// # Events
 /// In `Advanced SubStation Alpha` Events is the core part of the subtitle file.
 /// This contains Dialogues which can be subtitle text. and even Graphics.
 
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
 pub struct Events {
     dialogues: Dialogues,
 }
@@ -936,11 +1274,63 @@ impl Events {
         *self = events;
         self
     }
+
+    pub(crate) fn from_dialogues(dialogues: Vec<Dialogue>) -> Events {
+        Events {
+            dialogues: Dialogues {
+                dialogues,
+            }
+        }
+    }
+
+    /// Iterate over each `Dialogue` in the `Events`.
+    pub fn iter(&self) -> std::slice::Iter<'_, Dialogue> {
+        self.dialogues.dialogues.iter()
+    }
+
+    /// Number of dialogues in the `Events`.
+    pub fn len(&self) -> usize {
+        self.dialogues.dialogues.len()
+    }
+
+    /// Returns `true` if there are no dialogues in the `Events`.
+    pub fn is_empty(&self) -> bool {
+        self.dialogues.dialogues.is_empty()
+    }
+
+    /// Move every dialogue by `offset_ms` milliseconds. A negative offset moves the dialogues
+    /// earlier. Times which would become negative are set to `0:00:00.00`.
+    ///
+    /// # Example
+    /// ```rust
+    /// use ass_parser::{Dialogue, Events};
+    ///
+    /// let mut events = Events::default();
+    /// events.add_dialogue(Dialogue::default().set_start("0:00:01.00").set_end("0:00:02.00"));
+    /// events.shift(1500);
+    /// assert_eq!(Some("0:00:02.50"), events.iter().last().unwrap().get_start());
+    /// ```
+    pub fn shift(&mut self, offset_ms: i64) -> &mut Events {
+        self.shift_matching(|_| true, offset_ms);
+        self
+    }
+
+    /// Remove the style override codes (`{...}` blocks) from the text of every dialogue.
+    pub fn strip_tags(&mut self) -> &mut Events {
+        for dialogue in self.dialogues.dialogues.iter_mut() {
+            if let Some(text) = &dialogue.event.text {
+                dialogue.event.text = Some(text::strip_tags(text));
+            }
+        }
+        self
+    }
 }
 
 /// # Dialogues
 /// This stores each `Dialogue: ` field in an `Advanced SubStation File`
 #[derive(Debug, PartialEq,Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
 struct Dialogues {
     dialogues: Vec<Dialogue>
 }
@@ -948,22 +1338,46 @@ struct Dialogues {
 /// A single `Dialogue` which contain `event` which can be used to modify the state of a
 /// `Dialogue`.
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
 pub struct Dialogue {
     event: EventFormat
 }
 
 #[derive(Debug, PartialEq,Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 struct EventFormat {
+    #[cfg_attr(feature = "serde", serde(rename = "Layer"))]
     layer: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "Start"))]
     start: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "End"))]
     end: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "Style"))]
     style: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "Name"))]
     name: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "MarginL"))]
     marginl: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "MarginR"))]
     marginr: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "MarginV"))]
     marginv: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "Effect"))]
     effect: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "Text"))]
     text: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "Kind", default))]
+    kind: EventKind,
+}
+
+/// Whether an event is a `Dialogue: ` line which is shown, or a `Comment: ` line which is not.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum EventKind {
+    #[default]
+    Dialogue,
+    Comment,
 }
 
 impl Default for EventFormat {
@@ -979,6 +1393,7 @@ impl Default for EventFormat {
             marginv: Some("0".to_string()),
             effect: Some("".to_string()),
             text: None,
+            kind: EventKind::Dialogue,
         }
     }
 }
@@ -997,6 +1412,7 @@ impl Dialogue {
                 marginv: None,
                 effect: None,
                 text: None,
+                kind: EventKind::Dialogue,
             }
         }
     }
@@ -1013,7 +1429,10 @@ impl Default for Dialogue {
 impl Dialogue {
     fn to_string(&self) -> String {
         let mut dialogue_string = String::new();
-        dialogue_string.push_str(EVENT_HEAD);
+        dialogue_string.push_str(match self.event.kind {
+            EventKind::Dialogue => EVENT_HEAD,
+            EventKind::Comment => COMMENT_HEAD,
+        });
         dialogue_string.push_str(&(self.event.layer.as_ref().unwrap_or(&"".to_owned()).to_owned() + ","));
         dialogue_string.push_str(&(self.event.start.as_ref().unwrap_or(&"".to_owned()).to_owned() + ","));
         dialogue_string.push_str(&(self.event.end.as_ref().unwrap_or(&"".to_owned()).to_owned() + ","));
@@ -1097,19 +1516,80 @@ impl Dialogue {
 		self.event.text = Some(value.to_string());
 		self
 	}
+    /// set whether the event is a `Dialogue` which is shown or a `Comment` which is not.
+    pub fn set_kind(mut self, kind: EventKind) -> Self {
+        self.event.kind = kind;
+        self
+    }
 
     /// set the color of the subtitle.
+    /// The `\c` tag goes into the leading override block, replacing the colour already there.
     pub fn set_colour(self, color: HexColor) -> Self {
-        let colour = AssFileOptions::get_ass_color_text(color);
-        match &self.event.text {
-            Some(text) => {
-                let new_text = &(colour + text);
-                self.set_text(new_text)
-            },
-            None => {
-                self.set_text(&colour)
-            }
-        }
+        let colour = format!(r"\c{}", colour::ass_colour(color));
+        let text = text::set_leading_tag(self.get_text().unwrap_or(""), &colour, &["c", "1c"]);
+        self.set_text(&text)
+    }
+}
+
+impl Dialogue {
+    /// get the layer of the Dialogue.
+    pub fn get_layer(&self) -> Option<&str> {
+        self.event.layer.as_deref()
+    }
+    /// get the start time of the Dialogue.
+    pub fn get_start(&self) -> Option<&str> {
+        self.event.start.as_deref()
+    }
+    /// get the end time of the Dialogue.
+    pub fn get_end(&self) -> Option<&str> {
+        self.event.end.as_deref()
+    }
+    /// get the style name of the Dialogue.
+    pub fn get_style(&self) -> Option<&str> {
+        self.event.style.as_deref()
+    }
+    /// get the character name of the Dialogue.
+    pub fn get_name(&self) -> Option<&str> {
+        self.event.name.as_deref()
+    }
+    /// get the marginl of the Dialogue.
+    pub fn get_marginl(&self) -> Option<&str> {
+        self.event.marginl.as_deref()
+    }
+    /// get the marginr of the Dialogue.
+    pub fn get_marginr(&self) -> Option<&str> {
+        self.event.marginr.as_deref()
+    }
+    /// get the marginv of the Dialogue.
+    pub fn get_marginv(&self) -> Option<&str> {
+        self.event.marginv.as_deref()
+    }
+    /// get the effect of the Dialogue.
+    pub fn get_effect(&self) -> Option<&str> {
+        self.event.effect.as_deref()
+    }
+    /// get the text of the Dialogue including any style override codes.
+    pub fn get_text(&self) -> Option<&str> {
+        self.event.text.as_deref()
+    }
+    /// get whether the event is a `Dialogue` or a `Comment`.
+    pub fn get_kind(&self) -> EventKind {
+        self.event.kind
+    }
+    /// Returns `true` if the event is a `Comment: ` line which is not shown.
+    pub fn is_comment(&self) -> bool {
+        self.event.kind == EventKind::Comment
+    }
+
+    /// get the start time of the Dialogue in milliseconds.
+    /// Returns `None` if the start time is not set or is not a valid timestamp.
+    pub fn get_start_ms(&self) -> Option<i64> {
+        self.event.start.as_deref().and_then(timestamp::parse_time)
+    }
+    /// get the end time of the Dialogue in milliseconds.
+    /// Returns `None` if the end time is not set or is not a valid timestamp.
+    pub fn get_end_ms(&self) -> Option<i64> {
+        self.event.end.as_deref().and_then(timestamp::parse_time)
     }
 }
 
@@ -1117,11 +1597,12 @@ pub struct AssFileOptions{}
 
 /// `script`, `v4` and `event` are fields in `Components`
 #[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Components {
     /// instance holding the scirpt field.
     pub script: ScriptInfo,
-    /// instance holding the V4 field of.
-    pub v4: V4Format,
+    /// instance holding the V4 styles.
+    pub v4: V4Styles,
     /// instance holding the Events field of.
     pub events: Events,
 }
@@ -1142,9 +1623,42 @@ impl Srt {
 /// # AssFile represents an instance of an existing `.ass` file.
 ///  The `AssFile::from_file function can be used to construct an `AssFile` from an existing `.ass
 ///  file`.
+///
+/// # Serde
+///
+/// With the `serde` feature enabled `AssFile` and its components implement `Serialize` and
+/// `Deserialize`. The keys are the field names used in the `.ass` file, every value is a string
+/// or `null` for unset fields, and the order of styles, dialogues and extra `Script Info` fields
+/// is kept so the JSON can be turned back into the same `.ass` file.
+///
+/// ```json
+/// {
+///   "components": {
+///     "script": {
+///       "ScriptType": "v4.00+", "PlayResX": "384", "PlayResY": "288",
+///       "ScaledBorderAndShadow": "yes", "YCbCr Matrix": "None",
+///       "Fields": [["Title", "Rap God"]]
+///     },
+///     "v4": [
+///       { "Name": "Default", "Fontname": "Arial", "Fontsize": "16", "PrimaryColour": "&Hffffff", "...": "..." }
+///     ],
+///     "events": [
+///       { "Layer": "0", "Start": "0:00:00.00", "End": "0:00:01.00", "Style": "Default", "Name": "",
+///         "MarginL": "0", "MarginR": "0", "MarginV": "0", "Effect": "", "Text": "Hello Friend", "Kind": "Dialogue" }
+///     ]
+///   }
+/// }
+/// ```
+/// The `V4+ Styles` objects have the keys `Name`, `Fontname`, `Fontsize`, `PrimaryColour`,
+/// `SecondaryColour`, `OutlineColour`, `BackColour`, `Bold`, `Italic`, `Underline`, `StrikeOut`,
+/// `ScaleX`, `ScaleY`, `Spacing`, `Angle`, `BorderStyle`, `Outline`, `Shadow`, `Alignment`,
+/// `MarginL`, `MarginR`, `MarginV` and `Encoding`. `Kind` is `Dialogue` or `Comment`, and is
+/// `Dialogue` when it is missing. The file name an `AssFile` was loaded from is not serialized.
 
 #[derive(Clone, PartialEq,Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct AssFile{
+    #[cfg_attr(feature = "serde", serde(skip))]
     _ass_file: String,
     /// Each components present in a `.ass` file. 
     /// They are `script` `v4` and `events`.
@@ -1165,7 +1679,7 @@ impl AssFile {
             _ass_file: String::new(),
             components: Components {
                 script: ScriptInfo::new(),
-                v4: V4Format::new(),
+                v4: V4Styles::new(),
                 events: Events::new(),
             }
         }
@@ -1194,6 +1708,163 @@ impl AssFile {
             srt_data: srt,
         }
     }
+
+    /// Create an `AssFile` with the default `Script Info` and `V4+ Styles` holding the given
+    /// `Events`.
+    pub(crate) fn from_events(events: Events) -> AssFile {
+        AssFile {
+            _ass_file: String::new(),
+            components: Components {
+                script: ScriptInfo::default(),
+                v4: V4Styles::default(),
+                events,
+            }
+        }
+    }
+
+    /// Read subtitles of the given format from a string.
+    /// Returns an `InvalidData` error if `.ass` contents have no `[V4+ Styles]` section.
+    ///
+    /// # Example
+    /// ```rust
+    /// use ass_parser::{AssFile, SubtitleFormat};
+    ///
+    /// let srt = "1\n00:00:01,500 --> 00:00:04,900\nLook, I was gonna go easy on you\n";
+    /// let ass_file = AssFile::from_contents(srt, SubtitleFormat::Srt).unwrap();
+    /// let vtt = ass_file.to_contents(SubtitleFormat::Vtt);
+    /// assert!(vtt.starts_with("WEBVTT"));
+    /// ```
+    pub fn from_contents(contents: &str, format: SubtitleFormat) -> std::result::Result<AssFile, std::io::Error> {
+        let ass_file = match format {
+            SubtitleFormat::Ass => AssFile {
+                _ass_file: String::new(),
+                components: Parser::new().get_each_components(contents.to_string())?,
+            },
+            SubtitleFormat::Srt => parser::parse_srt_to_ass(contents),
+            SubtitleFormat::Vtt => vtt::parse_vtt(contents),
+            SubtitleFormat::Sbv => sbv::parse_sbv(contents),
+            SubtitleFormat::Lrc => lrc::parse_lrc(contents),
+            SubtitleFormat::Sami => sami::parse_sami(contents, &[]),
+        };
+
+        Ok(ass_file)
+    }
+
+    /// Write the `AssFile` as a string in the given format.
+    pub fn to_contents(&self, format: SubtitleFormat) -> String {
+        match format {
+            SubtitleFormat::Ass => Parser::new().combine_components(&self.components),
+            SubtitleFormat::Srt => parser::stringify_srt(self),
+            SubtitleFormat::Vtt => vtt::stringify_vtt(self),
+            SubtitleFormat::Sbv => sbv::stringify_sbv(self),
+            SubtitleFormat::Lrc => lrc::stringify_lrc(self),
+            SubtitleFormat::Sami => sami::stringify_sami(self, &[]),
+        }
+    }
+
+    /// Check the subtitles for problems with the rules enabled in `options`.
+    /// See the [lint] module for the available rules.
+    pub fn lint(&self, options: &lint::LintOptions) -> Vec<lint::Diagnostic> {
+        lint::lint(self, options)
+    }
+
+    /// Load Subtitles from a WebVTT (`.vtt`) file.
+    /// `<i>`, `<b>` and `<u>` markup is converted to style override codes and the speaker of a
+    /// `<v>` voice span becomes the Name of the dialogue.
+    pub fn from_vtt(filename: &str) -> std::result::Result<AssFile, std::io::Error> {
+        let file_contents = get_contents(filename)?;
+        let mut ass_file = vtt::parse_vtt(&file_contents);
+        ass_file._ass_file = filename.to_string();
+
+        Ok(ass_file)
+    }
+
+    /// Load Subtitles from a YouTube `.sbv` caption file.
+    /// Each caption becomes a `Dialogue` with the `Default` style.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use ass_parser::AssFile;
+    ///
+    /// let ass_file = AssFile::from_sbv("captions.sbv").expect("error while reading file.");
+    /// AssFile::save_file(&ass_file, "captions.ass");
+    /// ```
+    pub fn from_sbv(filename: &str) -> std::result::Result<AssFile, std::io::Error> {
+        let file_contents = get_contents(filename)?;
+        let mut ass_file = sbv::parse_sbv(&file_contents);
+        ass_file._ass_file = filename.to_string();
+
+        Ok(ass_file)
+    }
+
+    /// Load Subtitles from an `.lrc` synced lyrics file.
+    /// Enhanced LRC word timestamps (`<mm:ss.xx>`) are converted to `\k` karaoke tags and the
+    /// `[ti:]`, `[ar:]`, `[al:]` and `[by:]` tags are stored in `Script Info` as `Title`,
+    /// `Artist`, `Album` and `Original Script`.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use ass_parser::AssFile;
+    ///
+    /// let ass_file = AssFile::from_lrc("lyrics.lrc").expect("error while reading file.");
+    /// println!("{:?}", ass_file.components.script.get_field("Title"));
+    /// ```
+    pub fn from_lrc(filename: &str) -> std::result::Result<AssFile, std::io::Error> {
+        let file_contents = get_contents(filename)?;
+        let mut ass_file = lrc::parse_lrc(&file_contents);
+        ass_file._ass_file = filename.to_string();
+
+        Ok(ass_file)
+    }
+
+    /// Load Subtitles from a SAMI (`.smi`) file.
+    ///
+    /// `languages` chooses which SAMI language classes are imported and the style each of them
+    /// gets, as `(class, style name)` pairs. Classes not listed are skipped. Pass an empty slice
+    /// to import every class with a style named after the class.
+    /// A caption ends when the next `SYNC` of the same class starts.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use ass_parser::AssFile;
+    ///
+    /// let ass_file = AssFile::from_sami("drama.smi", &[("KRCC", "Default"), ("ENCC", "English")])
+    ///     .expect("error while reading file.");
+    /// AssFile::save_file(&ass_file, "drama.ass");
+    /// ```
+    pub fn from_sami(filename: &str, languages: &[(&str, &str)]) -> std::result::Result<AssFile, std::io::Error> {
+        let file_contents = get_contents(filename)?;
+        let mut ass_file = sami::parse_sami(&file_contents, languages);
+        ass_file._ass_file = filename.to_string();
+
+        Ok(ass_file)
+    }
+
+    /// Load Subtitles from an EBU Tech 3264 (`.stl`) binary subtitle file.
+    ///
+    /// The frame rate and character code table are read from the GSI header. Subtitles in the top
+    /// half of the screen get the `Top` style and the rest the `Default` style, with the vertical
+    /// position of each subtitle stored as its MarginV. Teletext colours, double height, italics
+    /// and underline are converted to style override codes.
+    /// Returns an `InvalidData` error if the file is not an EBU STL file.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use ass_parser::AssFile;
+    ///
+    /// let ass_file = AssFile::from_stl("broadcast.stl").expect("error while reading file.");
+    /// AssFile::save_file(&ass_file, "broadcast.ass");
+    /// ```
+    pub fn from_stl(filename: &str) -> std::result::Result<AssFile, std::io::Error> {
+        if !check_path_exists(filename){
+            return Err(std::io::ErrorKind::NotFound.into());
+        }
+        let file_contents = fs::read(filename)?;
+        let mut ass_file = stl::parse_stl(&file_contents)?;
+        ass_file._ass_file = filename.to_string();
+
+        Ok(ass_file)
+    }
 }
 
 struct Parser; 
@@ -1221,7 +1892,7 @@ impl Parser {
         let script_data = &self.stringify_script(scriptinfo);
         let v4_data = &self.plug_v4(v4);
         let event_data = &self.plug_events(events);
-        let total_data = format!("{}\n\n{}\n\n{}", script_data, v4_data, event_data);
+        let total_data = format!("{}\n{}\n\n{}\n\n{}", SCRIPT_HEADER, script_data, v4_data, event_data);
 
         return total_data;
     }
@@ -1262,27 +1933,30 @@ impl Parser {
         return total_lines;
     }
 
-    fn plug_v4(&self, v4_info: V4Format) -> String {
-        let array = v4_info.get_array();
-        let mut values = Vec::new();
+    fn plug_v4(&self, v4_styles: V4Styles) -> String {
         let mut v4_lines = Vec::new();
         let mut total_v4 = String::new();
         v4_lines.push("[V4+ Styles]\n".to_string());
         v4_lines.push("Format: Name, Fontname, Fontsize, PrimaryColour, SecondaryColour, OutlineColour, BackColour, Bold, Italic, Underline, StrikeOut, ScaleX, ScaleY, Spacing, Angle, BorderStyle, Outline, Shadow, Alignment, MarginL, MarginR, MarginV, Encoding\n".to_string());
-        v4_lines.push(V4_STYLE_HEAD.to_string());
 
-        for (i, value) in array.into_iter().enumerate() {
-            let style_val = value.clone().unwrap();
-            if i < (array.len()-1) {
-                values.push(style_val + ",");
-            } else {
-                values.push(style_val);
+        for v4_info in &v4_styles.styles {
+            let array = v4_info.get_array();
+            let mut values = Vec::new();
+            v4_lines.push(V4_STYLE_HEAD.to_string());
+
+            for (i, value) in array.into_iter().enumerate() {
+                let style_val = value.clone().unwrap();
+                if i < (array.len()-1) {
+                    values.push(style_val + ",");
+                } else {
+                    values.push(style_val);
+                }
             }
-        }
 
-        values.push("\n".to_string());
+            values.push("\n".to_string());
 
-        v4_lines.append(&mut values); 
+            v4_lines.append(&mut values); 
+        }
 
         for line in v4_lines {
             total_v4.push_str(line.as_str());
@@ -1308,21 +1982,24 @@ impl Parser {
         return total_events;
     }
 
-    fn get_each_components(&self, file_contents: String) -> Components {
+    fn get_each_components(&self, file_contents: String) -> std::result::Result<Components, std::io::Error> {
         let lines:Vec<&str> = file_contents.split("\n").collect();
         let script_lines = &self.get_info(&lines, SCRIPT_HEADER);
         let v4_lines = &self.get_info(&lines, V4_HEADER);
         let events_lines = &self.get_info(&lines, EVENTS_HEADER);
 
-        let script = self.parse_script(script_lines.to_vec()).unwrap();
-        let v4 = self.parse_v4(v4_lines.to_vec()).unwrap();
-        let events = self.parse_event(events_lines.to_vec()).unwrap();
+        let script = self.parse_script(script_lines.to_vec())
+            .ok_or(std::io::Error::new(std::io::ErrorKind::InvalidData, "Unable to parse the [Script Info] section."))?;
+        let v4 = self.parse_v4(v4_lines.to_vec())
+            .ok_or(std::io::Error::new(std::io::ErrorKind::InvalidData, "Unable to parse the [V4+ Styles] section."))?;
+        let events = self.parse_event(events_lines.to_vec())
+            .ok_or(std::io::Error::new(std::io::ErrorKind::InvalidData, "Unable to parse the [Events] section."))?;
 
-        Components {
+        Ok(Components {
             script,
             v4,
             events,
-        }.clone()
+        })
     }
     fn parse_script(&self, script_lines: Vec<String>) -> Option<ScriptInfo> {
         let mut script_type: Option<String>= None;
@@ -1330,6 +2007,7 @@ impl Parser {
         let mut script_playerresy: Option<String>= None;
         let mut script_scaledborderandshadow: Option<String>= None;
         let mut script_ycbcr_matrix: Option<String>= None;
+        let mut fields: Vec<[&str; 2]> = Vec::new();
 
         for line in &script_lines {
             if line.starts_with(SCRIPT_TYPE) {
@@ -1347,21 +2025,33 @@ impl Parser {
             } else if line.starts_with(SCRIPT_YCBCR_MATRIX){
                 script_ycbcr_matrix = Some(line[SCRIPT_YCBCR_MATRIX.len()..].to_owned());
                 continue;
+            } else if line.starts_with(';') {
+                continue;
+            } else if let Some((key, value)) = line.split_once(": ") {
+                fields.push([key, value]);
             }
         }
-        println!("{:?}, {:?}, {:?}, {:?} {:?}", script_type, 
-                 script_playerresx,
-                 script_playerresy,
-                 script_scaledborderandshadow,
-                 script_ycbcr_matrix);
-
-        let mut scriptinfo = ScriptInfo::new();
-        let script_info = scriptinfo.
-            set_scripttype(&script_type.unwrap()).
-            set_playresx(&script_playerresx.unwrap()).
-            set_playresy(&script_playerresy.unwrap()).
-            set_scaledborderandshadow(&script_scaledborderandshadow.unwrap()).
-            set_ycbcr_matrix(&script_ycbcr_matrix.unwrap()).clone();
+
+        let mut script_info = ScriptInfo::new();
+        if let Some(value) = script_type {
+            script_info.set_scripttype(&value);
+        }
+        if let Some(value) = script_playerresx {
+            script_info.set_playresx(&value);
+        }
+        if let Some(value) = script_playerresy {
+            script_info.set_playresy(&value);
+        }
+        if let Some(value) = script_scaledborderandshadow {
+            script_info.set_scaledborderandshadow(&value);
+        }
+        if let Some(value) = script_ycbcr_matrix {
+            script_info.set_ycbcr_matrix(&value);
+        }
+
+        for [key, value] in fields {
+            script_info.set_field(key, value);
+        }
 
         Some(script_info)
 }
@@ -1371,12 +2061,18 @@ impl Parser {
         let mut dialogues = Vec::new();
         
         for line in event_lines {
-            if line.starts_with(EVENT_HEAD) {
-                raw_dialogues.push(line[EVENT_HEAD.len()..].to_string());
+            if let Some(dialogue) = line.strip_prefix(EVENT_HEAD) {
+                raw_dialogues.push((EventKind::Dialogue, dialogue.to_string()));
+            } else if let Some(comment) = line.strip_prefix(COMMENT_HEAD) {
+                raw_dialogues.push((EventKind::Comment, comment.to_string()));
             }
         }
-        for dialogue in &raw_dialogues {
-            let splitted_dialogue: Vec<&str> = dialogue.split(',').collect();
+        for (kind, dialogue) in &raw_dialogues {
+            // the text is everything after the 9th comma so it can include commas.
+            let splitted_dialogue: Vec<&str> = dialogue.splitn(10, ',').collect();
+            if splitted_dialogue.len() < 10 {
+                continue;
+            }
             let dialogue = Dialogue::new().
                 set_layer(splitted_dialogue[0]).
                 set_start(splitted_dialogue[1]).
@@ -1387,7 +2083,8 @@ impl Parser {
                 set_marginr(splitted_dialogue[6]).
                 set_marginv(splitted_dialogue[7]).
                 set_effect(splitted_dialogue[8]).
-                set_text(splitted_dialogue[9]);
+                set_text(splitted_dialogue[9]).
+                set_kind(*kind);
             
             dialogues.push(dialogue);
         }
@@ -1402,49 +2099,52 @@ impl Parser {
 
 
     }
-    fn parse_v4(&self, v4_lines: Vec<String>) -> Option<V4Format>{
-        let mut style_line: Option::<String> = None;
+    fn parse_v4(&self, v4_lines: Vec<String>) -> Option<V4Styles>{
+        let mut styles = Vec::new();
         for line in &v4_lines {
-            if line.starts_with(V4_STYLE_HEAD) {
-                style_line = Some(line[V4_STYLE_HEAD.len()..].to_string());
-                break;
+            // malformed style lines are skipped like malformed event lines
+            if let Some(style) = line.strip_prefix(V4_STYLE_HEAD).and_then(|style| self.parse_style(style)) {
+                styles.push(style);
             }
         }
-        if let Some(style_data) = style_line {
-            let values: Vec<&str> = style_data.split(',').collect();
-            println!("{:?}", values);
-
-            let v4format = V4Format::new().
-                set_name(values[0]).
-                set_fontname(values[1]).
-                set_fontsize(values[2]).
-                set_primarycolour(values[3]).
-                set_secondarycolour(values[4]).
-                set_outlinecolour(values[5]).
-                set_backcolour(values[6]).
-                set_bold(values[7]).
-                set_italic(values[8]).
-                set_underline(values[9]).
-                set_strikeout(values[10]).
-                set_scalex(values[11]).
-                set_scaley(values[12]).
-                set_spacing(values[13]).
-                set_angle(values[14]).
-                set_borderstyle(values[15]).
-                set_outline(values[16]).
-                set_shadow(values[17]).
-                set_alignment(values[18]).
-                set_marginl(values[19]).
-                set_marginr(values[20]).
-                set_marginv(values[22]).
-                set_encoding(values[22]).clone();
-
-            return Some(v4format);
-        } else {
-            eprintln!("Unable to parse v4!");
-            println!("{:?}", &v4_lines);
+        if styles.is_empty() {
+            return None
+        }
+        Some(V4Styles {
+            styles,
+        })
+    }
+    fn parse_style(&self, style_data: &str) -> Option<V4Format>{
+        let values: Vec<&str> = style_data.split(',').collect();
+        if values.len() < 23 {
             return None
         }
+        let v4format = V4Format::new().
+            set_name(values[0]).
+            set_fontname(values[1]).
+            set_fontsize(values[2]).
+            set_primarycolour(values[3]).
+            set_secondarycolour(values[4]).
+            set_outlinecolour(values[5]).
+            set_backcolour(values[6]).
+            set_bold(values[7]).
+            set_italic(values[8]).
+            set_underline(values[9]).
+            set_strikeout(values[10]).
+            set_scalex(values[11]).
+            set_scaley(values[12]).
+            set_spacing(values[13]).
+            set_angle(values[14]).
+            set_borderstyle(values[15]).
+            set_outline(values[16]).
+            set_shadow(values[17]).
+            set_alignment(values[18]).
+            set_marginl(values[19]).
+            set_marginr(values[20]).
+            set_marginv(values[21]).
+            set_encoding(values[22]).clone();
+
+        Some(v4format)
 //["Default", "Arial", "16", "&Hffffff", "&Hffffff", "&H0", "&H0", "0", "0", "0", "0", "100", "100", "0", "0", "1", "1", "0", "2", "10", "10", "10", "1"]
     }
     fn get_info(&self, lines: &Vec<&str>, header: &str) -> Vec<String> {
@@ -1495,7 +2195,7 @@ impl AssFile {
         let parser = Parser::new();
         match file_contents {
             Ok(contents) => {
-                let components = parser.get_each_components(contents);
+                let components = parser.get_each_components(contents)?;
                 Ok(
                     Self{
                     _ass_file: filename.to_string(),
@@ -1540,6 +2240,41 @@ impl AssFile {
         let file_data = parser.combine_components(components);
         write_contents(filename, &file_data);
     }
+
+    /// save an instance of `AssFile` as a SubRip (`.srt`) file.
+    /// Style override codes are removed and `\N` line breaks become new lines.
+    pub fn save_srt(file_components: &AssFile, filename: &str) {
+        let file_data = parser::stringify_srt(file_components);
+        write_contents(filename, &file_data);
+    }
+
+    /// save an instance of `AssFile` as a WebVTT (`.vtt`) file.
+    pub fn save_vtt(file_components: &AssFile, filename: &str) {
+        let file_data = vtt::stringify_vtt(file_components);
+        write_contents(filename, &file_data);
+    }
+
+    /// save an instance of `AssFile` as YouTube `.sbv` captions.
+    /// Style override codes are removed and `\N` line breaks become new lines.
+    pub fn save_sbv(file_components: &AssFile, filename: &str) {
+        let file_data = sbv::stringify_sbv(file_components);
+        write_contents(filename, &file_data);
+    }
+
+    /// save an instance of `AssFile` as `.lrc` lyrics.
+    /// Dialogues with `\k` karaoke tags are written with enhanced LRC word timestamps.
+    pub fn save_lrc(file_components: &AssFile, filename: &str) {
+        let file_data = lrc::stringify_lrc(file_components);
+        write_contents(filename, &file_data);
+    }
+
+    /// save an instance of `AssFile` as a SAMI (`.smi`) file with one class per style.
+    /// `languages` gives the class of each style as `(class, style name)` pairs, the same way as
+    /// in `from_sami`. Styles which are not listed use their name as the class.
+    pub fn save_sami(file_components: &AssFile, filename: &str, languages: &[(&str, &str)]) {
+        let file_data = sami::stringify_sami(file_components, languages);
+        write_contents(filename, &file_data);
+    }
 }
 
 impl AssFileOptions {
@@ -1678,6 +2413,17 @@ fn get_contents(filename: &str) -> std::result::Result<String, std::io::Error>{
 mod tests {
     use super::*;
 
+    /// A 200x100 script with a dialogue for each text, shown for the first second, and a
+    /// `Default` style with a font size of 20. Used by the tests of the other modules too.
+    pub(crate) fn ass_file_with(texts: &[&str]) -> AssFile {
+        let mut ass_file = AssFile::from_events(Events::from_dialogues(
+            texts.iter().map(|text| Dialogue::default().set_start("0:00:00.00").set_end("0:00:01.00").set_text(text)).collect(),
+        ));
+        ass_file.components.script.set_playresx("200").set_playresy("100");
+        ass_file.components.v4.set_v4(V4Format::default()).set_fontsize("20");
+        ass_file
+    }
+
     #[test]
     fn test_file_contents() {
         use parser::SrtData;
@@ -1696,6 +2442,99 @@ mod tests {
         assert_eq!(test_srt_content, srt_content[0]);
     }
 
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_round_trip() {
+        let mut ass_file = AssFile::from_events(Events::new()
+            .add_first_dialogue(Dialogue::default().set_text("Hello, Friend!")).unwrap()
+            .add_dialogue(Dialogue::default().set_start("0:00:01.00").set_style("Top"))
+            .create());
+        ass_file.components.script.set_field("Title", "Rap God");
+        ass_file.components.v4.add_style(V4Format::default().set_name("Top").set_alignment("8").clone());
+
+        let json = serde_json::to_string(&ass_file).unwrap();
+        assert!(json.contains(r#""Fields":[["Title","Rap God"]]"#));
+        assert!(json.contains(r#""Text":"Hello, Friend!""#));
+
+        let deserialized: AssFile = serde_json::from_str(&json).unwrap();
+        let parser = Parser::new();
+        assert_eq!(ass_file, deserialized);
+        assert_eq!(parser.combine_components(&ass_file.components), parser.combine_components(&deserialized.components));
+
+        // there is always at least one style
+        let json = json.replacen(&serde_json::to_string(&ass_file.components.v4).unwrap(), "[]", 1);
+        assert!(json.contains(r#""v4":[]"#));
+        assert!(serde_json::from_str::<AssFile>(&json).is_err());
+    }
+
+    #[test]
+    fn test_set_colour() {
+        let dialogue = Dialogue::default()
+            .set_text(r"{\i1}Rap God")
+            .set_colour(HexColor::YELLOW)
+            .set_colour(HexColor::rgb(1, 2, 3));
+
+        assert_eq!(Some(r"{\i1\c&H030201&}Rap God"), dialogue.get_text());
+    }
+
+    #[test]
+    fn test_ass_round_trip() {
+        let events = Events::new()
+            .add_first_dialogue(Dialogue::default().set_text("Look, I was gonna go easy on you")).unwrap()
+            .add_dialogue(Dialogue::default().set_kind(EventKind::Comment).set_text("Rap God"))
+            .create();
+        let mut ass_file = AssFile::from_events(events);
+        ass_file.components.script.set_field("Title", "Rap God");
+
+        let contents = ass_file.to_contents(SubtitleFormat::Ass);
+        assert!(contents.starts_with("[Script Info]\nScriptType: v4.00+\n"));
+        assert!(contents.contains("\nComment: 0,0:00:00.00,"));
+        assert_eq!(ass_file, AssFile::from_contents(&contents, SubtitleFormat::Ass).unwrap());
+    }
+
+    #[test]
+    fn test_style_margins() {
+        let mut ass_file = AssFile::from_events(Events::from_dialogues(vec![Dialogue::default().set_text("Look")]));
+        ass_file.components.v4.set_v4(V4Format::default())
+            .set_marginl("10")
+            .set_marginr("20")
+            .set_marginv("30")
+            .set_encoding("1");
+
+        let parsed = AssFile::from_contents(&ass_file.to_contents(SubtitleFormat::Ass), SubtitleFormat::Ass).unwrap();
+        let style = &parsed.components.v4;
+        assert_eq!(Some("10"), style.get_marginl());
+        assert_eq!(Some("20"), style.get_marginr());
+        assert_eq!(Some("30"), style.get_marginv());
+        assert_eq!(Some("1"), style.get_encoding());
+    }
+
+    #[test]
+    fn test_malformed_style() {
+        let ass_file = AssFile::from_events(Events::from_dialogues(vec![Dialogue::default().set_text("Look")]));
+        let contents = ass_file.to_contents(SubtitleFormat::Ass)
+            .replacen("\nStyle: ", "\nStyle: Top,Arial,20\nStyle: ", 1);
+        assert!(contents.contains("\nStyle: Top,Arial,20\n"));
+
+        let parsed = AssFile::from_contents(&contents, SubtitleFormat::Ass).unwrap();
+        assert_eq!(1, parsed.components.v4.len());
+        assert_eq!(None, parsed.components.v4.get_style("Top"));
+        assert_eq!(ass_file, parsed);
+    }
+
+    #[test]
+    fn test_script_comments() {
+        let mut ass_file = AssFile::from_events(Events::from_dialogues(vec![Dialogue::default().set_text("Look")]));
+        ass_file.components.script.set_field("Title", "Rap God");
+
+        let contents = ass_file.to_contents(SubtitleFormat::Ass)
+            .replacen("[Script Info]\n", "[Script Info]\n; Script generated by Aegisub: http://www.aegisub.org/\n", 1);
+        let parsed = AssFile::from_contents(&contents, SubtitleFormat::Ass).unwrap();
+
+        assert_eq!(None, parsed.components.script.get_field("; Script generated by Aegisub"));
+        assert_eq!(ass_file, parsed);
+    }
+
     #[test]
     fn test_from_file_wrong() {
         let result = AssFile::from_file("asdfasdf").map_err(|e| e.kind());
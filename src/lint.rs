@@ -0,0 +1,373 @@
+//! # Lint
+//!
+//! Quality checks for an `AssFile` before delivery. Each check is a `LintRule` which can be
+//! enabled or disabled in `LintOptions`, and every problem found is reported as a `Diagnostic`.
+//!
+//! ```rust
+//! use ass_parser::{AssFile, Dialogue, Events};
+//! use ass_parser::lint::{LintOptions, LintRule};
+//!
+//! let mut ass_file = AssFile::new();
+//! ass_file.components.events.set_events(Events::default())
+//!     .add_dialogue(Dialogue::default().set_start("0:00:02.00").set_end("0:00:01.00"));
+//!
+//! let mut options = LintOptions::default();
+//! options.disable(LintRule::MissingPlayRes).set_max_cps(17.0);
+//!
+//! for diagnostic in ass_file.lint(&options) {
+//!     println!("{}", diagnostic);
+//! }
+//! ```
+
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::AssFile;
+use crate::reading_speed::{count_characters, WhitespacePolicy};
+use crate::text::{is_known_tag, replace_line_breaks, strip_tags};
+
+/// A check that can be run over an `AssFile`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LintRule {
+    /// The end time of an event is before its start time.
+    EndBeforeStart,
+    /// The end time of an event is the same as its start time.
+    ZeroDuration,
+    /// An event uses a style which is not in the `[V4+ Styles]` section.
+    UndefinedStyle,
+    /// An override block is not closed, a `}` has no `{` or the parentheses in a block don't match.
+    UnbalancedTags,
+    /// An override block contains a tag which is not an ASS override tag.
+    UnknownTag,
+    /// The text of an event is shown for too short a time to be read.
+    CharactersPerSecond,
+    /// A line of an event has too many characters.
+    LineTooLong,
+    /// An event starts before an earlier event on the same layer and style has ended.
+    OverlappingEvents,
+    /// `PlayResX` or `PlayResY` is not set in `Script Info`.
+    MissingPlayRes,
+    /// An event has the same times, layer, style and text as an earlier event.
+    DuplicateEvent,
+}
+
+impl LintRule {
+    /// Every lint rule.
+    pub const ALL: [LintRule; 10] = [
+        LintRule::EndBeforeStart,
+        LintRule::ZeroDuration,
+        LintRule::UndefinedStyle,
+        LintRule::UnbalancedTags,
+        LintRule::UnknownTag,
+        LintRule::CharactersPerSecond,
+        LintRule::LineTooLong,
+        LintRule::OverlappingEvents,
+        LintRule::MissingPlayRes,
+        LintRule::DuplicateEvent,
+    ];
+
+    /// The id of the rule as used in reports, e.g. `end-before-start`.
+    pub fn id(&self) -> &'static str {
+        match self {
+            LintRule::EndBeforeStart => "end-before-start",
+            LintRule::ZeroDuration => "zero-duration",
+            LintRule::UndefinedStyle => "undefined-style",
+            LintRule::UnbalancedTags => "unbalanced-tags",
+            LintRule::UnknownTag => "unknown-tag",
+            LintRule::CharactersPerSecond => "characters-per-second",
+            LintRule::LineTooLong => "line-too-long",
+            LintRule::OverlappingEvents => "overlapping-events",
+            LintRule::MissingPlayRes => "missing-playres",
+            LintRule::DuplicateEvent => "duplicate-event",
+        }
+    }
+
+    /// Get a rule from its id.
+    pub fn from_id(id: &str) -> Option<LintRule> {
+        LintRule::ALL.into_iter().find(|rule| rule.id() == id)
+    }
+
+    /// How serious a problem found by the rule is.
+    pub fn severity(&self) -> Severity {
+        match self {
+            LintRule::EndBeforeStart | LintRule::UndefinedStyle | LintRule::UnbalancedTags => Severity::Error,
+            _ => Severity::Warning,
+        }
+    }
+}
+
+/// How serious a `Diagnostic` is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// Probably fine but worth a look.
+    Warning,
+    /// The subtitles will not be shown as intended.
+    Error,
+}
+
+/// A problem found by a `LintRule`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    /// The rule which found the problem.
+    pub rule: LintRule,
+    /// How serious the problem is.
+    pub severity: Severity,
+    /// Index of the event with the problem, or `None` for problems with the whole file.
+    pub event: Option<usize>,
+    /// A description of the problem.
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        match self.event {
+            Some(event) => write!(f, "{}[{}] event {}: {}", severity, self.rule.id(), event, self.message),
+            None => write!(f, "{}[{}]: {}", severity, self.rule.id(), self.message),
+        }
+    }
+}
+
+/// Which rules `lint` runs and the limits they use.
+/// By default every rule is enabled with a limit of 21 characters per second and 42 characters
+/// per line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LintOptions {
+    enabled: Vec<LintRule>,
+    max_cps: f64,
+    max_line_length: usize,
+}
+
+impl Default for LintOptions {
+    fn default() -> LintOptions {
+        LintOptions {
+            enabled: LintRule::ALL.to_vec(),
+            max_cps: 21.0,
+            max_line_length: 42,
+        }
+    }
+}
+
+impl LintOptions {
+    /// Options with no rules enabled. Use `enable` to choose the rules to run.
+    pub fn none() -> LintOptions {
+        LintOptions {
+            enabled: Vec::new(),
+            ..LintOptions::default()
+        }
+    }
+
+    /// Run the rule.
+    pub fn enable(&mut self, rule: LintRule) -> &mut Self {
+        if !self.enabled.contains(&rule) {
+            self.enabled.push(rule);
+        }
+        self
+    }
+
+    /// Don't run the rule.
+    pub fn disable(&mut self, rule: LintRule) -> &mut Self {
+        self.enabled.retain(|enabled| *enabled != rule);
+        self
+    }
+
+    /// Check if the rule is enabled.
+    pub fn is_enabled(&self, rule: LintRule) -> bool {
+        self.enabled.contains(&rule)
+    }
+
+    /// Set the most characters per second allowed by `LintRule::CharactersPerSecond`.
+    pub fn set_max_cps(&mut self, max_cps: f64) -> &mut Self {
+        self.max_cps = max_cps;
+        self
+    }
+
+    /// Set the most characters in a line allowed by `LintRule::LineTooLong`.
+    pub fn set_max_line_length(&mut self, max_line_length: usize) -> &mut Self {
+        self.max_line_length = max_line_length;
+        self
+    }
+}
+
+/// Run the enabled rules over the `AssFile` and return the problems found, ordered by event.
+/// Comments are not checked.
+pub fn lint(ass_file: &AssFile, options: &LintOptions) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let mut report = |rule: LintRule, event: Option<usize>, message: String| {
+        if options.is_enabled(rule) {
+            diagnostics.push(Diagnostic {
+                rule,
+                severity: rule.severity(),
+                event,
+                message,
+            });
+        }
+    };
+
+    let script = &ass_file.components.script;
+    if script.get_playresx().is_none() || script.get_playresy().is_none() {
+        report(LintRule::MissingPlayRes, None, "PlayResX and PlayResY should be set in Script Info".to_string());
+    }
+
+    let mut seen: HashMap<[&str; 5], usize> = HashMap::new();
+    for (i, dialogue) in ass_file.components.events.iter().enumerate().filter(|(_, dialogue)| !dialogue.is_comment()) {
+        let text = dialogue.get_text().unwrap_or("");
+        let style = dialogue.get_style().unwrap_or("");
+        let start = dialogue.get_start_ms();
+        let end = dialogue.get_end_ms();
+
+        if let (Some(start), Some(end)) = (start, end) {
+            if end < start {
+                report(LintRule::EndBeforeStart, Some(i), format!("ends at {} before it starts at {}", dialogue.get_end().unwrap_or(""), dialogue.get_start().unwrap_or("")));
+            } else if end == start {
+                report(LintRule::ZeroDuration, Some(i), "starts and ends at the same time".to_string());
+            } else {
+                let cps = count_characters(text, WhitespacePolicy::Count) as f64 * 1000.0 / (end - start) as f64;
+                if cps > options.max_cps {
+                    report(LintRule::CharactersPerSecond, Some(i), format!("{:.1} characters per second is above {}", cps, options.max_cps));
+                }
+            }
+        }
+
+        if ass_file.components.v4.get_style(style).is_none() {
+            report(LintRule::UndefinedStyle, Some(i), format!("style {:?} is not defined", style));
+        }
+
+        if let Some(problem) = unbalanced_tags(text) {
+            report(LintRule::UnbalancedTags, Some(i), problem);
+        }
+        for tag in unknown_tags(text) {
+            report(LintRule::UnknownTag, Some(i), format!("unknown override tag \\{}", tag));
+        }
+
+        for (line_number, line) in replace_line_breaks(&strip_tags(text), "\n").split('\n').enumerate() {
+            let length = line.replace("\\h", " ").chars().count();
+            if length > options.max_line_length {
+                report(LintRule::LineTooLong, Some(i), format!("line {} has {} characters, more than {}", line_number + 1, length, options.max_line_length));
+            }
+        }
+
+        let key = [
+            dialogue.get_start().unwrap_or(""),
+            dialogue.get_end().unwrap_or(""),
+            dialogue.get_layer().unwrap_or(""),
+            style,
+            text,
+        ];
+        match seen.get(&key) {
+            Some(first) => report(LintRule::DuplicateEvent, Some(i), format!("same as event {}", first)),
+            None => {
+                seen.insert(key, i);
+            },
+        }
+    }
+
+    let mut overlapping = None;
+    for overlap in ass_file.components.events.find_overlaps() {
+        if overlapping != Some(overlap.second) {
+            overlapping = Some(overlap.second);
+            report(LintRule::OverlappingEvents, Some(overlap.second), format!("starts before event {} on the same layer and style ends", overlap.first));
+        }
+    }
+
+    diagnostics.sort_by_key(|diagnostic| diagnostic.event.map(|event| event + 1).unwrap_or(0));
+    diagnostics
+}
+
+fn unbalanced_tags(text: &str) -> Option<String> {
+    let mut block: Option<String> = None;
+
+    for c in text.chars() {
+        match (c, &mut block) {
+            ('{', Some(_)) => return Some("override block opened inside another override block".to_string()),
+            ('{', None) => block = Some(String::new()),
+            ('}', None) => return Some("} without an opening {".to_string()),
+            ('}', Some(content)) => {
+                if content.matches('(').count() != content.matches(')').count() {
+                    return Some(format!("unbalanced parentheses in {{{}}}", content));
+                }
+                block = None;
+            },
+            (c, Some(content)) => content.push(c),
+            _ => {},
+        }
+    }
+
+    block.map(|_| "override block is not closed".to_string())
+}
+
+fn unknown_tags(text: &str) -> Vec<String> {
+    let mut unknown = Vec::new();
+    let mut rest = text;
+
+    while let Some(open) = rest.find('{') {
+        let Some(close) = rest[open..].find('}') else {
+            break;
+        };
+        for tag in rest[open + 1..open + close].split('\\').skip(1) {
+            let tag = tag.trim_end_matches(')');
+            if !tag.trim().is_empty() && !is_known_tag(tag) {
+                unknown.push(tag.to_string());
+            }
+        }
+        rest = &rest[open + close + 1..];
+    }
+
+    unknown
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Dialogue, Events};
+
+    fn lint_dialogues(dialogues: Vec<Dialogue>, options: &LintOptions) -> Vec<(LintRule, Option<usize>)> {
+        let ass_file = AssFile::from_events(Events::from_dialogues(dialogues));
+        lint(&ass_file, options).into_iter()
+            .map(|diagnostic| (diagnostic.rule, diagnostic.event))
+            .collect()
+    }
+
+    #[test]
+    fn test_lint() {
+        let dialogue = Dialogue::default().set_start("0:00:01.00").set_end("0:00:03.00");
+        let diagnostics = lint_dialogues(vec![
+            dialogue.clone().set_text("Hello"),
+            dialogue.clone().set_start("0:00:04.00").set_text("Back"),
+            dialogue.clone().set_start("0:00:02.00").set_style("Signs").set_text("{\\fs20\\bogus}Hi"),
+            dialogue.clone().set_start("0:00:02.50").set_text("Look, I was gonna go easy on you and not to hurt your feelings\\b1}"),
+            dialogue.clone().set_text("Hello"),
+        ], &LintOptions::default());
+
+        assert_eq!(vec![
+            (LintRule::EndBeforeStart, Some(1)),
+            (LintRule::UndefinedStyle, Some(2)),
+            (LintRule::UnknownTag, Some(2)),
+            (LintRule::CharactersPerSecond, Some(3)),
+            (LintRule::UnbalancedTags, Some(3)),
+            (LintRule::LineTooLong, Some(3)),
+            (LintRule::OverlappingEvents, Some(3)),
+            (LintRule::DuplicateEvent, Some(4)),
+            (LintRule::OverlappingEvents, Some(4)),
+        ], diagnostics);
+    }
+
+    #[test]
+    fn test_lint_options() {
+        let dialogue = Dialogue::default().set_start("0:00:01.00").set_end("0:00:01.00").set_style("Signs");
+        let mut options = LintOptions::none();
+        options.enable(LintRule::ZeroDuration);
+
+        assert_eq!(vec![(LintRule::ZeroDuration, Some(0))], lint_dialogues(vec![dialogue.clone()], &options));
+
+        let mut ass_file = AssFile::new();
+        ass_file.components.events = Events::from_dialogues(vec![dialogue]);
+        options.enable(LintRule::MissingPlayRes).disable(LintRule::ZeroDuration);
+        assert_eq!(LintRule::MissingPlayRes, lint(&ass_file, &options)[0].rule);
+        assert_eq!(None, lint(&ass_file, &options)[0].event);
+    }
+}
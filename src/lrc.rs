@@ -0,0 +1,206 @@
+use crate::{AssFile, Dialogue, Events, ScriptInfo};
+use crate::timestamp::{format_time, parse_time};
+use crate::text::{replace_line_breaks, strip_tags};
+
+/// How long the last lyric line stays on screen when nothing tells us when it ends.
+const LAST_LINE_DURATION: i64 = 5000;
+
+/// LRC ID tags and the `Script Info` fields they are stored in.
+const LRC_FIELDS: [(&str, &str); 4] = [
+    ("ti", "Title"),
+    ("ar", "Artist"),
+    ("al", "Album"),
+    ("by", "Original Script"),
+];
+
+struct LyricLine {
+    start: i64,
+    text: String,
+}
+
+/// Parse the contents of an `.lrc` synced lyrics file.
+///
+/// Every `[mm:ss.xx]` line becomes a `Dialogue` which ends when the next line starts. Enhanced
+/// `<mm:ss.xx>` word timestamps become `\k` karaoke tags, and the `[ti:]`, `[ar:]`, `[al:]` and
+/// `[by:]` tags are stored in `Script Info`.
+pub(crate) fn parse_lrc(contents: &str) -> AssFile {
+    let mut script = ScriptInfo::default();
+    let mut offset = 0;
+    let mut lines = Vec::new();
+
+    for line in contents.lines() {
+        let mut rest = line.trim();
+        let mut times = Vec::new();
+
+        while let Some(tag) = rest.strip_prefix('[') {
+            let Some(close) = tag.find(']') else {
+                break;
+            };
+            let tag_content = &tag[..close];
+            rest = &tag[close + 1..];
+
+            if let Some(time) = parse_time(tag_content) {
+                times.push(time);
+            } else if let Some((key, value)) = tag_content.split_once(':') {
+                let key = key.trim();
+                let value = value.trim();
+                if key == "offset" {
+                    offset = value.trim_start_matches('+').parse().unwrap_or(0);
+                } else if let Some((_, field)) = LRC_FIELDS.iter().find(|(tag, _)| *tag == key) {
+                    script.set_field(field, value);
+                }
+            }
+        }
+
+        for time in times {
+            lines.push(LyricLine {
+                start: time,
+                text: rest.to_string(),
+            });
+        }
+    }
+
+    lines.sort_by_key(|line| line.start);
+
+    let mut dialogues = Vec::new();
+    for (i, line) in lines.iter().enumerate() {
+        let start = line.start - offset;
+        let words = parse_words(&line.text, offset);
+        let end = match lines.get(i + 1) {
+            Some(next) => next.start - offset,
+            None => words.last()
+                .filter(|(time, word)| word.is_empty() && *time > start)
+                .map(|(time, _)| *time)
+                .unwrap_or(start + LAST_LINE_DURATION),
+        };
+
+        let dialogue = Dialogue::default()
+            .set_start(&format_time(start))
+            .set_end(&format_time(end))
+            .set_text(&karaoke_text(&line.text, &words, start, end));
+        dialogues.push(dialogue);
+    }
+
+    let mut ass_file = AssFile::from_events(Events::from_dialogues(dialogues));
+    ass_file.components.script.set_script(script);
+    ass_file
+}
+
+/// Split a lyric line on its enhanced `<mm:ss.xx>` word timestamps.
+fn parse_words(text: &str, offset: i64) -> Vec<(i64, String)> {
+    let mut words: Vec<(i64, String)> = Vec::new();
+    let mut rest = text;
+
+    while let Some(open) = rest.find('<') {
+        let Some(close) = rest[open..].find('>') else {
+            break;
+        };
+        let Some(time) = parse_time(&rest[open + 1..open + close]) else {
+            break;
+        };
+        if let Some(last) = words.last_mut() {
+            last.1.push_str(&rest[..open]);
+        }
+        words.push((time - offset, String::new()));
+        rest = &rest[open + close + 1..];
+    }
+    if let Some(last) = words.last_mut() {
+        last.1.push_str(rest);
+    }
+
+    words
+}
+
+fn karaoke_text(text: &str, words: &[(i64, String)], start: i64, end: i64) -> String {
+    let Some((first_time, _)) = words.first() else {
+        return text.to_string();
+    };
+
+    let mut karaoke = String::new();
+    let lead_in = &text[..text.find('<').unwrap_or(0)];
+    if *first_time > start || !lead_in.is_empty() {
+        karaoke.push_str(&format!("{{\\k{}}}{}", (first_time - start).max(0) / 10, lead_in));
+    }
+
+    for (i, (time, word)) in words.iter().enumerate() {
+        let word_end = words.get(i + 1).map(|(time, _)| *time).unwrap_or(end);
+        if word.is_empty() && i + 1 == words.len() {
+            break;
+        }
+        karaoke.push_str(&format!("{{\\k{}}}{}", (word_end - time).max(0) / 10, word));
+    }
+
+    karaoke
+}
+
+/// Convert the dialogues of an `AssFile` into `.lrc` lyrics.
+/// Dialogues with `\k` karaoke tags are written as enhanced LRC with `<mm:ss.xx>` word timestamps.
+pub(crate) fn stringify_lrc(ass_file: &AssFile) -> String {
+    let mut contents = String::new();
+
+    for (tag, field) in LRC_FIELDS {
+        if let Some(value) = ass_file.components.script.get_field(field) {
+            contents.push_str(&format!("[{}:{}]\n", tag, value));
+        }
+    }
+
+    for dialogue in ass_file.components.events.iter().filter(|dialogue| !dialogue.is_comment()) {
+        let Some(start) = dialogue.get_start_ms() else {
+            continue;
+        };
+        let text = dialogue.get_text().unwrap_or("");
+
+        contents.push_str(&format!("[{}]", format_lrc_time(start)));
+        match dialogue.get_karaoke() {
+            None => contents.push_str(&replace_line_breaks(&strip_tags(text), " ")),
+            Some(karaoke) => {
+                let prefix = replace_line_breaks(&strip_tags(&karaoke.prefix), " ");
+                if !prefix.is_empty() {
+                    contents.push_str(&format!("<{}>{}", format_lrc_time(start), prefix));
+                }
+                for (syllable, (time, _)) in karaoke.syllables.iter().zip(karaoke.times()) {
+                    let syllable = replace_line_breaks(&syllable.plain_text(), " ");
+                    if !syllable.is_empty() {
+                        contents.push_str(&format!("<{}>{}", format_lrc_time(time), syllable));
+                    }
+                }
+                contents.push_str(&format!("<{}>", format_lrc_time(start + karaoke.duration_ms())));
+            },
+        }
+        contents.push('\n');
+    }
+
+    contents
+}
+
+fn format_lrc_time(ms: i64) -> String {
+    let centis = (ms.max(0) + 5) / 10;
+    format!("{:02}:{:02}.{:02}", centis / 6000, (centis / 100) % 60, centis % 100)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_lrc() {
+        let contents = "[ti:Rap God]\n[ar:Eminem]\n[00:01.00]<00:01.00>Look, <00:01.50>I <00:02.00>was<00:02.40>\n[00:03.00]gonna go easy on you\n";
+        let ass_file = parse_lrc(contents);
+        let dialogues: Vec<&Dialogue> = ass_file.components.events.iter().collect();
+
+        assert_eq!(Some("Rap God"), ass_file.components.script.get_field("Title"));
+        assert_eq!(Some("Eminem"), ass_file.components.script.get_field("Artist"));
+        assert_eq!(Some(r"{\k50}Look, {\k50}I {\k40}was"), dialogues[0].get_text());
+        assert_eq!(Some("0:00:03.00"), dialogues[0].get_end());
+        assert_eq!(Some("0:00:08.00"), dialogues[1].get_end());
+    }
+
+    #[test]
+    fn test_lrc_round_trip() {
+        let contents = "[ti:Rap God]\n[ar:Eminem]\n[00:01.00]<00:01.00>Look, <00:01.50>I <00:02.00>was<00:02.40>\n[00:03.00]gonna go easy on you\n";
+        let ass_file = parse_lrc(contents);
+
+        assert_eq!(contents, stringify_lrc(&ass_file));
+    }
+}
@@ -0,0 +1,164 @@
+//! # Merge
+//!
+//! Combine several `AssFile`s into one, such as a dialogue track and a separately typeset signs
+//! track.
+//!
+//! The `Script Info` of the first file is kept. Files with a different resolution are rescaled to
+//! the resolution of the first file, keeping their aspect ratio. Styles with the same name and
+//! the same properties are merged, and styles with the same name but different properties are
+//! handled by a `StyleConflict` policy.
+//!
+//! ```rust
+//! use ass_parser::{AssFile, Dialogue, Events};
+//! use ass_parser::merge::{merge, StyleConflict};
+//!
+//! let dialogue = AssFile::new();
+//! let mut signs = AssFile::new();
+//! signs.components.events.set_events(Events::default())
+//!     .add_dialogue(Dialogue::default().set_text("Rap God"));
+//!
+//! // the signs are 1.5 seconds late
+//! let merged = merge(&[(&dialogue, 0), (&signs, -1500)], &StyleConflict::Rename(" (signs)".to_string()));
+//! ```
+
+use crate::{AssFile, V4Format};
+use crate::rescale::RescaleMode;
+
+/// What to do with styles which have the same name but different properties.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum StyleConflict {
+    /// Keep the style from the earliest file. Dialogues of later files use it too.
+    #[default]
+    KeepFirst,
+    /// Replace the style with the one from the latest file.
+    KeepLast,
+    /// Keep both styles by adding the suffix to the name of the later style, and change the
+    /// dialogues of that file to use the new name. A number is added as well if the name is
+    /// still taken.
+    Rename(String),
+}
+
+/// Merge the `AssFile`s into one. Each file is paired with an offset in milliseconds which is
+/// added to the times of its dialogues. The dialogues are kept in the order of the files.
+pub fn merge(inputs: &[(&AssFile, i64)], conflicts: &StyleConflict) -> AssFile {
+    let Some(((first, first_offset), rest)) = inputs.split_first() else {
+        return AssFile::new();
+    };
+    let mut merged = (*first).clone();
+    merged.components.events.shift(*first_offset);
+    let (width, height) = merged.components.script.play_res();
+
+    for (input, offset) in rest {
+        let mut input = (*input).clone();
+        if input.components.script.play_res() != (width, height) {
+            input.rescale(width.round() as u32, height.round() as u32, RescaleMode::Letterbox);
+        }
+        input.components.events.shift(*offset);
+
+        for [key, value] in &input.components.script.fields {
+            let key = key.trim_end_matches(": ");
+            if merged.components.script.get_field(key).is_none() {
+                merged.components.script.set_field(key, value);
+            }
+        }
+
+        let styles: Vec<V4Format> = input.components.v4.iter().cloned().collect();
+        for mut style in styles {
+            let Some(name) = style.get_name().map(str::to_string) else {
+                continue;
+            };
+            let Some(existing) = merged.components.v4.get_style(&name) else {
+                merged.components.v4.add_style(style);
+                continue;
+            };
+            if existing.same_properties(&style) {
+                continue;
+            }
+
+            match conflicts {
+                StyleConflict::KeepFirst => {},
+                StyleConflict::KeepLast => {
+                    merged.components.v4.add_style(style);
+                },
+                StyleConflict::Rename(suffix) => {
+                    // reuse a renamed style from an earlier file if it is the same
+                    let mut new_name = format!("{}{}", name, suffix);
+                    let mut number = 2;
+                    let duplicate = loop {
+                        match merged.components.v4.get_style(&new_name) {
+                            Some(renamed) if renamed.same_properties(&style) => break true,
+                            None if input.components.v4.get_style(&new_name).is_none() => break false,
+                            _ => {
+                                new_name = format!("{}{}{}", name, suffix, number);
+                                number += 1;
+                            },
+                        }
+                    };
+
+                    input.replace_style_references(&name, &new_name);
+                    if !duplicate {
+                        style.set_name(&new_name);
+                        merged.components.v4.add_style(style);
+                    }
+                },
+            }
+        }
+
+        merged.components.events.dialogues.dialogues.append(&mut input.components.events.dialogues.dialogues);
+    }
+
+    merged
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Dialogue, Events};
+
+    fn ass_file(fontsize: &str, text: &str) -> AssFile {
+        let mut ass_file = AssFile::from_events(Events::from_dialogues(vec![
+            Dialogue::default().set_start("0:00:01.00").set_end("0:00:02.00").set_text(text),
+        ]));
+        ass_file.components.v4.set_v4(V4Format::default()).set_fontsize(fontsize);
+        ass_file.components.script.set_playresx("384").set_playresy("288");
+        ass_file
+    }
+
+    fn styles(ass_file: &AssFile) -> Vec<(&str, &str)> {
+        ass_file.components.v4.iter()
+            .map(|style| (style.get_name().unwrap(), style.get_fontsize().unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn test_merge_conflicts() {
+        let dialogue = ass_file("20", "Look");
+        let signs = ass_file("30", r"{\rDefault}Rap God");
+
+        let merged = merge(&[(&dialogue, 0), (&signs, 1000)], &StyleConflict::KeepFirst);
+        assert_eq!(vec![("Default", "20")], styles(&merged));
+        assert_eq!(Some("0:00:02.00"), merged.components.events.iter().last().unwrap().get_start());
+
+        let merged = merge(&[(&dialogue, 0), (&signs, 0)], &StyleConflict::KeepLast);
+        assert_eq!(vec![("Default", "30")], styles(&merged));
+
+        let merged = merge(&[(&dialogue, 0), (&signs, 0), (&signs, 0)], &StyleConflict::Rename(" signs".to_string()));
+        assert_eq!(vec![("Default", "20"), ("Default signs", "30")], styles(&merged));
+        let last = merged.components.events.iter().last().unwrap();
+        assert_eq!(Some("Default signs"), last.get_style());
+        assert_eq!(Some(r"{\rDefault signs}Rap God"), last.get_text());
+        assert_eq!(3, merged.components.events.len());
+    }
+
+    #[test]
+    fn test_merge_rescales() {
+        let dialogue = ass_file("20", "Look");
+        let mut signs = ass_file("40", r"{\pos(1920,1080)}Rap God");
+        signs.components.script.set_playresx("1920").set_playresy("1080");
+
+        let merged = merge(&[(&dialogue, 0), (&signs, 0)], &StyleConflict::Rename("_2".to_string()));
+        assert_eq!(vec![("Default", "20"), ("Default_2", "8")], styles(&merged));
+        assert_eq!(Some(r"{\pos(384,252)}Rap God"), merged.components.events.iter().last().unwrap().get_text());
+    }
+}
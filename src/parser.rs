@@ -1,4 +1,9 @@
+use crate::{AssFile, Dialogue, Events};
+use crate::timestamp::{format_time, format_time_hms, parse_time};
+use crate::text::{replace_line_breaks, strip_tags};
+
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SrtData {
     /// The index of the srt segment.
     pub index: String,
@@ -34,13 +39,18 @@ impl SrtData {
 
         for line in splitted {
             // println!("line: {}, {}", line, line.is_empty());
-            if line.is_empty() {
+            if line.trim().is_empty() {
+                if !current_buffer.is_empty() {
                     segments.push(current_buffer);
                     current_buffer = Vec::new();
+                }
             } else {
-                current_buffer.push(line.to_owned());
+                current_buffer.push(line.trim_end_matches('\r').to_owned());
             }
         }
+        if !current_buffer.is_empty() {
+            segments.push(current_buffer);
+        }
 
         return segments;
     }
@@ -98,3 +108,41 @@ impl SrtData {
         return self.get_srt(segments);
     }
 }
+
+/// Parse the contents of a SubRip file into an `AssFile` with one `Dialogue` per segment.
+pub(crate) fn parse_srt_to_ass(contents: &str) -> AssFile {
+    let srt_content = SrtData::new().parse_srt(contents.to_string());
+    let dialogues = srt_content.iter()
+        .filter_map(|srt| {
+            let start = parse_time(&srt.start)?;
+            let end = parse_time(&srt.end)?;
+            Some(Dialogue::default()
+                .set_start(&format_time(start))
+                .set_end(&format_time(end))
+                .set_text(srt.text.trim_end()))
+        })
+        .collect();
+
+    AssFile::from_events(Events::from_dialogues(dialogues))
+}
+
+/// Convert the dialogues of an `AssFile` into SubRip segments.
+/// Style override codes are removed and `\N` line breaks become new lines.
+pub(crate) fn stringify_srt(ass_file: &AssFile) -> String {
+    let mut contents = String::new();
+    let mut index = 1;
+
+    for dialogue in ass_file.components.events.iter().filter(|dialogue| !dialogue.is_comment()) {
+        let (Some(start), Some(end)) = (dialogue.get_start_ms(), dialogue.get_end_ms()) else {
+            continue;
+        };
+        let text = strip_tags(dialogue.get_text().unwrap_or(""));
+
+        contents.push_str(&format!("{}\n{} --> {}\n", index, format_time_hms(start, ','), format_time_hms(end, ',')));
+        contents.push_str(&replace_line_breaks(&text, "\n"));
+        contents.push_str("\n\n");
+        index += 1;
+    }
+
+    contents
+}
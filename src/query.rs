@@ -0,0 +1,242 @@
+//! # Query
+//!
+//! Select dialogues by their time, style, actor, layer, kind, effect or text, and edit the
+//! selection in one go. Every condition added to a `Query` must match.
+//!
+//! ```rust
+//! use ass_parser::{Dialogue, EventKind, Events};
+//! use ass_parser::query::Query;
+//!
+//! let mut events = Events::default();
+//! events.add_dialogue(Dialogue::default().set_start("0:00:01.00").set_end("0:00:03.00").set_name("Eminem").set_text("{\\i1}Rap God"))
+//!     .add_dialogue(Dialogue::default().set_start("0:00:05.00").set_end("0:00:06.00").set_text("Look"));
+//!
+//! let query = Query::new().actor("Eminem").text_contains("Rap God");
+//! assert_eq!(vec![1], events.select(&query));
+//!
+//! events.set_style_where(&query, "Lyrics");
+//! events.shift_where(&Query::new().time_range(4000, 10_000), -500);
+//! events.remove_where(&Query::new().kind(EventKind::Comment));
+//! ```
+
+use regex::Regex;
+
+use crate::{Dialogue, EventKind, Events};
+use crate::text::strip_tags;
+use crate::timestamp::format_time;
+
+#[derive(Debug, Clone)]
+enum Condition {
+    TimeRange(i64, i64),
+    Style(String),
+    Actor(String),
+    Layer(i64),
+    Kind(EventKind),
+    Effect(String),
+    TextContains(String),
+    RawTextContains(String),
+    TextMatches(Regex),
+    RawTextMatches(Regex),
+}
+
+impl Condition {
+    fn matches(&self, dialogue: &Dialogue) -> bool {
+        let text = dialogue.get_text().unwrap_or("");
+        match self {
+            Condition::TimeRange(start_ms, end_ms) => match (dialogue.get_start_ms(), dialogue.get_end_ms()) {
+                (Some(start), Some(end)) => start < *end_ms && (end > *start_ms || start == *start_ms),
+                _ => false,
+            },
+            Condition::Style(style) => dialogue.get_style() == Some(style.as_str()),
+            Condition::Actor(actor) => dialogue.get_name() == Some(actor.as_str()),
+            Condition::Layer(layer) => dialogue.get_layer().and_then(|value| value.trim().parse::<i64>().ok()) == Some(*layer),
+            Condition::Kind(kind) => dialogue.get_kind() == *kind,
+            Condition::Effect(effect) => dialogue.get_effect() == Some(effect.as_str()),
+            Condition::TextContains(pattern) => strip_tags(text).contains(pattern.as_str()),
+            Condition::RawTextContains(pattern) => text.contains(pattern.as_str()),
+            Condition::TextMatches(regex) => regex.is_match(&strip_tags(text)),
+            Condition::RawTextMatches(regex) => regex.is_match(text),
+        }
+    }
+}
+
+/// Conditions which select dialogues from `Events`. A query without conditions selects every
+/// dialogue.
+///
+/// Text conditions check the plain text without override blocks, while the `raw_text`
+/// conditions check the text including them.
+#[derive(Debug, Clone, Default)]
+pub struct Query {
+    conditions: Vec<Condition>,
+}
+
+impl Query {
+    /// A query which selects every dialogue.
+    pub fn new() -> Query {
+        Query::default()
+    }
+
+    /// Select the dialogues shown between `start_ms` and `end_ms`.
+    pub fn time_range(mut self, start_ms: i64, end_ms: i64) -> Self {
+        self.conditions.push(Condition::TimeRange(start_ms, end_ms));
+        self
+    }
+
+    /// Select the dialogues with the style.
+    pub fn style(mut self, style: &str) -> Self {
+        self.conditions.push(Condition::Style(style.to_string()));
+        self
+    }
+
+    /// Select the dialogues with the actor in their Name field.
+    pub fn actor(mut self, actor: &str) -> Self {
+        self.conditions.push(Condition::Actor(actor.to_string()));
+        self
+    }
+
+    /// Select the dialogues on the layer.
+    pub fn layer(mut self, layer: i64) -> Self {
+        self.conditions.push(Condition::Layer(layer));
+        self
+    }
+
+    /// Select the `Dialogue` or `Comment` events.
+    pub fn kind(mut self, kind: EventKind) -> Self {
+        self.conditions.push(Condition::Kind(kind));
+        self
+    }
+
+    /// Select the dialogues with the effect.
+    pub fn effect(mut self, effect: &str) -> Self {
+        self.conditions.push(Condition::Effect(effect.to_string()));
+        self
+    }
+
+    /// Select the dialogues whose plain text contains `pattern`.
+    pub fn text_contains(mut self, pattern: &str) -> Self {
+        self.conditions.push(Condition::TextContains(pattern.to_string()));
+        self
+    }
+
+    /// Select the dialogues whose text, including override blocks, contains `pattern`.
+    pub fn raw_text_contains(mut self, pattern: &str) -> Self {
+        self.conditions.push(Condition::RawTextContains(pattern.to_string()));
+        self
+    }
+
+    /// Select the dialogues whose plain text matches the regular expression.
+    pub fn text_matches(mut self, regex: Regex) -> Self {
+        self.conditions.push(Condition::TextMatches(regex));
+        self
+    }
+
+    /// Select the dialogues whose text, including override blocks, matches the regular expression.
+    pub fn raw_text_matches(mut self, regex: Regex) -> Self {
+        self.conditions.push(Condition::RawTextMatches(regex));
+        self
+    }
+
+    /// Check if the dialogue matches every condition of the query.
+    pub fn matches(&self, dialogue: &Dialogue) -> bool {
+        self.conditions.iter().all(|condition| condition.matches(dialogue))
+    }
+}
+
+impl Events {
+    /// The indices of the dialogues selected by the query.
+    pub fn select(&self, query: &Query) -> Vec<usize> {
+        self.iter().enumerate()
+            .filter(|(_, dialogue)| query.matches(dialogue))
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// The dialogues selected by the query, to modify them.
+    pub fn select_mut(&mut self, query: &Query) -> Vec<&mut Dialogue> {
+        self.dialogues.dialogues.iter_mut()
+            .filter(|dialogue| query.matches(dialogue))
+            .collect()
+    }
+
+    /// Set the style of the selected dialogues. Returns the number of dialogues changed.
+    pub fn set_style_where(&mut self, query: &Query, style: &str) -> usize {
+        let mut selected = self.select_mut(query);
+        for dialogue in selected.iter_mut() {
+            dialogue.event.style = Some(style.to_string());
+        }
+        selected.len()
+    }
+
+    /// Move the selected dialogues by `offset_ms` milliseconds. Times which would become
+    /// negative are set to `0:00:00.00`. Returns the number of dialogues moved.
+    pub fn shift_where(&mut self, query: &Query, offset_ms: i64) -> usize {
+        self.shift_matching(|dialogue| query.matches(dialogue), offset_ms)
+    }
+
+    /// Move the dialogues for which `predicate` is true by `offset_ms` milliseconds, clamping
+    /// negative times to zero. Returns the number of dialogues moved.
+    pub(crate) fn shift_matching(&mut self, predicate: impl Fn(&Dialogue) -> bool, offset_ms: i64) -> usize {
+        let mut moved = 0;
+        for dialogue in self.dialogues.dialogues.iter_mut().filter(|dialogue| predicate(dialogue)) {
+            if let Some(start) = dialogue.get_start_ms() {
+                dialogue.event.start = Some(format_time(start + offset_ms));
+            }
+            if let Some(end) = dialogue.get_end_ms() {
+                dialogue.event.end = Some(format_time(end + offset_ms));
+            }
+            moved += 1;
+        }
+        moved
+    }
+
+    /// Remove the selected dialogues. Returns the number of dialogues removed.
+    pub fn remove_where(&mut self, query: &Query) -> usize {
+        let before = self.len();
+        self.dialogues.dialogues.retain(|dialogue| !query.matches(dialogue));
+        before - self.len()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn events() -> Events {
+        Events::from_dialogues(vec![
+            Dialogue::default().set_start("0:00:01.00").set_end("0:00:03.00").set_name("Eminem").set_text(r"{\c&H00FF00&}Look"),
+            Dialogue::default().set_start("0:00:02.00").set_end("0:00:04.00").set_layer("1").set_style("Signs").set_text("Rap God"),
+            Dialogue::default().set_start("0:00:05.00").set_end("0:00:06.00").set_effect("template line").set_kind(EventKind::Comment),
+        ])
+    }
+
+    #[test]
+    fn test_select() {
+        let events = events();
+
+        assert_eq!(vec![0, 1, 2], events.select(&Query::new()));
+        assert_eq!(vec![0, 1], events.select(&Query::new().time_range(2500, 5000)));
+        assert_eq!(vec![1], events.select(&Query::new().style("Signs").layer(1)));
+        assert_eq!(vec![0], events.select(&Query::new().actor("Eminem")));
+        assert_eq!(vec![2], events.select(&Query::new().kind(EventKind::Comment).effect("template line")));
+        assert_eq!(Vec::<usize>::new(), events.select(&Query::new().text_contains("FF00")));
+        assert_eq!(vec![0], events.select(&Query::new().raw_text_contains("FF00")));
+        assert_eq!(vec![1], events.select(&Query::new().text_matches(Regex::new(r"(?i)^rap\s+god$").unwrap())));
+        assert_eq!(vec![0], events.select(&Query::new().raw_text_matches(Regex::new(r"\\c&H").unwrap())));
+    }
+
+    #[test]
+    fn test_bulk_edits() {
+        let mut events = events();
+
+        assert_eq!(2, events.set_style_where(&Query::new().kind(EventKind::Dialogue), "Main"));
+        assert_eq!(1, events.shift_where(&Query::new().style("Main").actor("Eminem"), -1500));
+        assert_eq!(1, events.remove_where(&Query::new().kind(EventKind::Comment)));
+
+        let dialogues: Vec<&Dialogue> = events.iter().collect();
+        assert_eq!(2, dialogues.len());
+        assert_eq!(Some("Main"), dialogues[1].get_style());
+        assert_eq!(Some("0:00:00.00"), dialogues[0].get_start());
+        assert_eq!(Some("0:00:01.50"), dialogues[0].get_end());
+    }
+}
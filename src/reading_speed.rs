@@ -0,0 +1,249 @@
+//! # Reading speed
+//!
+//! Characters per second (CPS) and words per minute (WPM) of dialogues, and a fixer which gives
+//! fast dialogues more time on screen.
+//!
+//! Characters are counted on the text without style override codes and line breaks. How spaces
+//! are counted is set with `WhitespacePolicy`.
+//!
+//! ```rust
+//! use ass_parser::{Dialogue, Events};
+//! use ass_parser::reading_speed::WhitespacePolicy;
+//!
+//! let mut events = Events::default();
+//! events.add_dialogue(Dialogue::default()
+//!     .set_start("0:00:01.00")
+//!     .set_end("0:00:02.00")
+//!     .set_text("{\\i1}Look, I was gonna go easy on you"));
+//!
+//! let stats = events.reading_speed_stats(WhitespacePolicy::Count, 17.0);
+//! println!("mean {:.1} cps, slowest lines: {:?}", stats.mean_cps, stats.offending);
+//!
+//! events.fix_reading_speed(17.0, WhitespacePolicy::Count);
+//! assert!(events.reading_speed_stats(WhitespacePolicy::Count, 17.0).offending.is_empty());
+//! ```
+
+use crate::Events;
+use crate::text::{replace_line_breaks, strip_tags};
+use crate::timestamp::format_time;
+use crate::timing::TimingChange;
+
+/// How whitespace is counted in the characters per second of a dialogue.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WhitespacePolicy {
+    /// Every space counts as a character.
+    #[default]
+    Count,
+    /// A run of spaces counts as one character and spaces at the start or end are not counted.
+    Collapse,
+    /// Spaces are not counted.
+    Ignore,
+}
+
+/// The reading speed of a single dialogue.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReadingSpeed {
+    /// Index of the dialogue.
+    pub event: usize,
+    /// Number of characters shown on screen.
+    pub characters: usize,
+    /// Number of words shown on screen.
+    pub words: usize,
+    /// How long the dialogue is shown, in milliseconds.
+    pub duration_ms: i64,
+    /// Characters per second.
+    pub cps: f64,
+    /// Words per minute.
+    pub wpm: f64,
+}
+
+/// Reading speed of a whole file.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ReadingSpeedStats {
+    /// Number of dialogues measured.
+    pub count: usize,
+    /// Average characters per second.
+    pub mean_cps: f64,
+    /// Characters per second which 95% of the dialogues are at or below.
+    pub p95_cps: f64,
+    /// Highest characters per second.
+    pub max_cps: f64,
+    /// Average words per minute.
+    pub mean_wpm: f64,
+    /// Highest words per minute.
+    pub max_wpm: f64,
+    /// Indices of the dialogues above the characters per second limit, fastest first.
+    pub offending: Vec<usize>,
+}
+
+/// Number of characters shown on screen for the text of a dialogue.
+pub(crate) fn count_characters(text: &str, whitespace: WhitespacePolicy) -> usize {
+    let visible = visible_text(text);
+    match whitespace {
+        WhitespacePolicy::Count => visible.chars().count(),
+        WhitespacePolicy::Collapse => {
+            let words: Vec<&str> = visible.split_whitespace().collect();
+            words.iter().map(|word| word.chars().count()).sum::<usize>() + words.len().saturating_sub(1)
+        },
+        WhitespacePolicy::Ignore => visible.chars().filter(|c| !c.is_whitespace()).count(),
+    }
+}
+
+/// The text shown on screen, with line breaks removed and hard spaces as spaces.
+fn visible_text(text: &str) -> String {
+    replace_line_breaks(&strip_tags(text), "").replace("\\h", " ")
+}
+
+fn count_words(text: &str) -> usize {
+    replace_line_breaks(&strip_tags(text), " ").replace("\\h", " ").split_whitespace().count()
+}
+
+impl Events {
+    /// The reading speed of every dialogue which has valid times and ends after it starts.
+    /// Comments are left out.
+    pub fn reading_speeds(&self, whitespace: WhitespacePolicy) -> Vec<ReadingSpeed> {
+        self.iter().enumerate()
+            .filter(|(_, dialogue)| !dialogue.is_comment())
+            .filter_map(|(event, dialogue)| {
+                let duration_ms = dialogue.get_end_ms()? - dialogue.get_start_ms()?;
+                if duration_ms <= 0 {
+                    return None;
+                }
+                let text = dialogue.get_text().unwrap_or("");
+                let characters = count_characters(text, whitespace);
+                let words = count_words(text);
+                Some(ReadingSpeed {
+                    event,
+                    characters,
+                    words,
+                    duration_ms,
+                    cps: characters as f64 * 1000.0 / duration_ms as f64,
+                    wpm: words as f64 * 60_000.0 / duration_ms as f64,
+                })
+            })
+            .collect()
+    }
+
+    /// Summary of the reading speed of the dialogues. Dialogues faster than `max_cps`
+    /// characters per second are listed in `offending`.
+    pub fn reading_speed_stats(&self, whitespace: WhitespacePolicy, max_cps: f64) -> ReadingSpeedStats {
+        let mut speeds = self.reading_speeds(whitespace);
+        if speeds.is_empty() {
+            return ReadingSpeedStats::default();
+        }
+        speeds.sort_by(|a, b| b.cps.total_cmp(&a.cps).then(a.event.cmp(&b.event)));
+
+        let count = speeds.len();
+        // nearest rank, counted from the fastest dialogue
+        let p95_index = count - (count * 95).div_ceil(100);
+
+        ReadingSpeedStats {
+            count,
+            mean_cps: speeds.iter().map(|speed| speed.cps).sum::<f64>() / count as f64,
+            p95_cps: speeds[p95_index].cps,
+            max_cps: speeds[0].cps,
+            mean_wpm: speeds.iter().map(|speed| speed.wpm).sum::<f64>() / count as f64,
+            max_wpm: speeds.iter().map(|speed| speed.wpm).fold(0.0, f64::max),
+            offending: speeds.iter()
+                .filter(|speed| speed.cps > max_cps)
+                .map(|speed| speed.event)
+                .collect(),
+        }
+    }
+
+    /// Extend the end time of dialogues faster than `max_cps` characters per second so they can
+    /// be read in time. A dialogue is never extended past the start of the next dialogue on the
+    /// same layer and style, so it may still be too fast afterwards. Nothing is changed when
+    /// `max_cps` isn't a positive number.
+    pub fn fix_reading_speed(&mut self, max_cps: f64, whitespace: WhitespacePolicy) -> Vec<TimingChange> {
+        let mut changes = Vec::new();
+        if max_cps.is_nan() || max_cps <= 0.0 {
+            return changes;
+        }
+
+        for group in self.timing_groups() {
+            for (i, &(event, start, end)) in group.iter().enumerate() {
+                let text = self.dialogues.dialogues[event].get_text().unwrap_or("");
+                let needed_ms = (count_characters(text, whitespace) as f64 * 1000.0 / max_cps).ceil() as i64;
+                // round up to the centiseconds stored in the file
+                let mut new_end = start.saturating_add(needed_ms).saturating_add(9) / 10 * 10;
+                if let Some(&(_, next_start, _)) = group.get(i + 1) {
+                    new_end = new_end.min(next_start);
+                }
+                if new_end <= end || end < start {
+                    continue;
+                }
+
+                self.dialogues.dialogues[event].event.end = Some(format_time(new_end));
+                changes.push(TimingChange {
+                    event,
+                    old_start: start,
+                    old_end: end,
+                    new_start: start,
+                    new_end,
+                });
+            }
+        }
+
+        changes.sort_by_key(|change| change.event);
+        changes
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Dialogue;
+
+    #[test]
+    fn test_count_characters() {
+        let text = r"{\b1}Look,  I was\Ngonna go ";
+
+        assert_eq!(21, count_characters(text, WhitespacePolicy::Count));
+        assert_eq!(19, count_characters(text, WhitespacePolicy::Collapse));
+        assert_eq!(16, count_characters(text, WhitespacePolicy::Ignore));
+        assert_eq!(5, count_words(text));
+    }
+
+    #[test]
+    fn test_reading_speed_stats() {
+        let dialogue = Dialogue::default().set_start("0:00:00.00").set_end("0:00:01.00");
+        let events = Events::from_dialogues(vec![
+            dialogue.clone().set_text("0123456789"),
+            dialogue.clone().set_text("01234567890123456789012345"),
+            dialogue.clone().set_end("0:00:00.00").set_text("Skipped"),
+            dialogue.clone().set_text("012345"),
+        ]);
+        let stats = events.reading_speed_stats(WhitespacePolicy::Count, 21.0);
+
+        assert_eq!(3, stats.count);
+        assert_eq!(14.0, stats.mean_cps);
+        assert_eq!(26.0, stats.max_cps);
+        assert_eq!(26.0, stats.p95_cps);
+        assert_eq!(60.0, stats.max_wpm);
+        assert_eq!(vec![1], stats.offending);
+    }
+
+    #[test]
+    fn test_fix_reading_speed() {
+        let events = vec![
+            Dialogue::default().set_start("0:00:00.00").set_end("0:00:01.00").set_text("0123456789012345678901234567890123456789"),
+            Dialogue::default().set_start("0:00:01.50").set_end("0:00:02.00").set_text("0123456789"),
+            Dialogue::default().set_start("0:00:05.00").set_end("0:00:06.00").set_text("0123"),
+        ];
+        let mut events = Events::from_dialogues(events);
+        let changes = events.fix_reading_speed(20.0, WhitespacePolicy::Count);
+        let ends: Vec<&str> = events.iter().map(|dialogue| dialogue.get_end().unwrap()).collect();
+
+        assert_eq!(1, changes.len());
+        assert_eq!(vec!["0:00:01.50", "0:00:02.00", "0:00:06.00"], ends);
+        assert!(events.find_overlaps().is_empty());
+
+        assert!(events.fix_reading_speed(0.0, WhitespacePolicy::Count).is_empty());
+        assert!(events.fix_reading_speed(-1.0, WhitespacePolicy::Count).is_empty());
+        assert!(events.fix_reading_speed(f64::NAN, WhitespacePolicy::Count).is_empty());
+        // very slow reading extends the last two dialogues a long way without overflowing
+        assert_eq!(2, events.fix_reading_speed(0.001, WhitespacePolicy::Count).len());
+    }
+}
@@ -0,0 +1,406 @@
+//! # Render
+//!
+//! Draw what a frame of the subtitles looks like at a time, without a video or a subtitle
+//! renderer, for golden image tests and thumbnails. It needs the `render` feature.
+//!
+//! The lines are placed by the layout engine, so they follow the alignment, margins, `\pos`,
+//! wrapping and font tags, and are drawn with the primary, outline and back colours and alphas,
+//! the outline width and shadow distance, the opaque box of `BorderStyle` 3, and `\fad` or
+//! `\fade`. Rotation, `\move`, `\t`, blur, clips and drawings are not drawn. Text is only drawn
+//! with loaded fonts.
+//!
+//! ```rust
+//! use ass_parser::{AssFile, Dialogue, Events};
+//! use ass_parser::layout::Fonts;
+//! use ass_parser::render::RenderOptions;
+//!
+//! let mut ass_file = AssFile::new();
+//! ass_file.components.script.set_playresx("640").set_playresy("360");
+//! ass_file.components.events.set_events(Events::default())
+//!     .add_dialogue(Dialogue::default().set_start("0:00:01.00").set_end("0:00:03.00").set_text("{\\fad(200,0)}Rap God"));
+//!
+//! // load a directory of fonts with `Fonts::from_dir("fonts")?`
+//! let fonts = Fonts::new();
+//! let frame = ass_file.render_frame(1500, &fonts, RenderOptions::new().set_size(320, 180)).unwrap();
+//! assert_eq!((320, 180), (frame.width(), frame.height()));
+//! // frame.save_png("frame.png")?;
+//! ```
+
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+use hex_color::HexColor;
+use tiny_skia::{Color, FillRule, LineJoin, Paint, Path as SkiaPath, PathBuilder, Pixmap, Rect, Stroke, Transform};
+use ttf_parser::{Face, GlyphId, OutlineBuilder};
+
+use crate::{AssFile, Dialogue};
+use crate::contrast::Colours;
+use crate::layout::{font_scale, layout_dialogue, Fonts, Glyph, Run};
+use crate::text::{segments, split_tags, tag_arguments, tag_name, Segment};
+
+/// The largest width or height of a frame.
+pub const MAX_SIZE: u32 = 16384;
+
+/// Options for `AssFile::render_frame`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct RenderOptions {
+    size: Option<(u32, u32)>,
+}
+
+impl RenderOptions {
+    /// Render at the `PlayResX` by `PlayResY` of the script.
+    pub fn new() -> RenderOptions {
+        RenderOptions::default()
+    }
+
+    /// Render at another size, scaling the script to fit.
+    pub fn set_size(&mut self, width: u32, height: u32) -> &mut Self {
+        self.size = Some((width, height));
+        self
+    }
+}
+
+/// A rendered frame with transparent pixels where there are no subtitles.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    pixmap: Pixmap,
+}
+
+impl Frame {
+    /// Width in pixels.
+    pub fn width(&self) -> u32 {
+        self.pixmap.width()
+    }
+
+    /// Height in pixels.
+    pub fn height(&self) -> u32 {
+        self.pixmap.height()
+    }
+
+    /// The RGBA bytes of the pixels, row by row, with alpha 255 being opaque.
+    pub fn pixels(&self) -> Vec<u8> {
+        self.pixmap.pixels().iter()
+            .flat_map(|pixel| {
+                let pixel = pixel.demultiply();
+                [pixel.red(), pixel.green(), pixel.blue(), pixel.alpha()]
+            })
+            .collect()
+    }
+
+    /// The colour of a pixel, or `None` outside the frame.
+    pub fn pixel(&self, x: u32, y: u32) -> Option<HexColor> {
+        let pixel = self.pixmap.pixel(x, y)?.demultiply();
+        Some(HexColor::rgba(pixel.red(), pixel.green(), pixel.blue(), pixel.alpha()))
+    }
+
+    /// The frame as a PNG image.
+    pub fn encode_png(&self) -> Result<Vec<u8>, io::Error> {
+        self.pixmap.encode_png().map_err(io::Error::other)
+    }
+
+    /// Save the frame as a PNG image.
+    pub fn save_png<P: AsRef<Path>>(&self, path: P) -> Result<(), io::Error> {
+        std::fs::write(path, self.encode_png()?)
+    }
+}
+
+impl AssFile {
+    /// Render the dialogues shown at `time_ms`, lower layers first. Comments are skipped.
+    /// Fails with `InvalidInput` when the frame would be wider or higher than `MAX_SIZE`.
+    pub fn render_frame(&self, time_ms: i64, fonts: &Fonts, options: &RenderOptions) -> Result<Frame, io::Error> {
+        let script = &self.components.script;
+        let (play_res_x, play_res_y) = script.play_res();
+        let (width, height) = options.size.unwrap_or((play_res_x.round() as u32, play_res_y.round() as u32));
+        if width > MAX_SIZE || height > MAX_SIZE {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("Can't render a {}x{} frame, the most is {}x{}.", width, height, MAX_SIZE, MAX_SIZE)));
+        }
+        let mut pixmap = Pixmap::new(width.max(1), height.max(1))
+            .ok_or(io::Error::new(io::ErrorKind::InvalidInput, "Can't allocate the frame."))?;
+        let transform = Transform::from_scale((width as f64 / play_res_x) as f32, (height as f64 / play_res_y) as f32);
+
+        let mut dialogues: Vec<&Dialogue> = self.components.events.iter()
+            .filter(|dialogue| !dialogue.is_comment())
+            .filter(|dialogue| {
+                let start = dialogue.get_start_ms().unwrap_or(0);
+                start <= time_ms && time_ms < dialogue.get_end_ms().unwrap_or(start)
+            })
+            .collect();
+        dialogues.sort_by_key(|dialogue| dialogue.get_layer().and_then(|layer| layer.trim().parse::<i64>().ok()).unwrap_or(0));
+
+        for dialogue in dialogues {
+            let start = dialogue.get_start_ms().unwrap_or(0);
+            let duration = dialogue.get_end_ms().unwrap_or(start) - start;
+            let opacity = fade_opacity(dialogue.get_text().unwrap_or(""), time_ms - start, duration);
+            let layout = layout_dialogue(dialogue, &self.components.v4, fonts, script.wrap_style(), (play_res_x, play_res_y));
+
+            let mut glyphs: Vec<(SkiaPath, Colours)> = Vec::new();
+            let mut boxes: Vec<(Rect, Colours)> = Vec::new();
+            for line in &layout.lines {
+                // the glyphs of a run share its font, so it is parsed once for each run
+                let runs: Vec<(&[Glyph], Option<Face>)> = line.glyphs.chunk_by(|a, b| Arc::ptr_eq(&a.run, &b.run))
+                    .map(|glyphs| (glyphs, fonts.face(&glyphs[0].run)))
+                    .collect();
+
+                // the baseline is below the highest ascender of the line
+                let ascender = runs.iter()
+                    .filter_map(|(glyphs, face)| {
+                        let (face, run) = (face.as_ref()?, &glyphs[0].run);
+                        let ascender = face.tables().os2.map_or(face.ascender(), |os2| os2.windows_ascender());
+                        Some(ascender as f64 * font_scale(face, run.size) * run.scale_y / 100.0)
+                    })
+                    .reduce(f64::max);
+                let baseline = line.top + ascender.unwrap_or(line.height * 0.8);
+
+                let mut x = line.left;
+                for (run_glyphs, face) in &runs {
+                    for glyph in run_glyphs.iter() {
+                        if let Some(path) = face.as_ref().and_then(|face| glyph_path(face, glyph.c, &glyph.run, x, baseline)) {
+                            glyphs.push((path, glyph.run.colours));
+                        }
+                        x += glyph.width;
+                    }
+                }
+
+                let colours = line.glyphs.first().map(|glyph| glyph.run.colours);
+                if let Some(colours) = colours.filter(|colours| colours.opaque_box && line.width > 0.0) {
+                    let border = colours.border;
+                    let rect = Rect::from_xywh((line.left - border) as f32, (line.top - border) as f32, (line.width + border * 2.0) as f32, (line.height + border * 2.0) as f32);
+                    boxes.extend(rect.map(|rect| (rect, colours)));
+                }
+            }
+
+            for (rect, colours) in &boxes {
+                if colours.shadow > 0.0 {
+                    let offset = transform.pre_translate(colours.shadow as f32, colours.shadow as f32);
+                    pixmap.fill_rect(*rect, &paint(colours.back, opacity), offset, None);
+                }
+            }
+            for (rect, colours) in &boxes {
+                pixmap.fill_rect(*rect, &paint(colours.outline, opacity), transform, None);
+            }
+
+            let outlined = |colours: &Colours| colours.border > 0.0 && !colours.opaque_box;
+            for (path, colours) in &glyphs {
+                if colours.shadow > 0.0 && !colours.opaque_box {
+                    let offset = transform.pre_translate(colours.shadow as f32, colours.shadow as f32);
+                    let back = paint(colours.back, opacity);
+                    pixmap.fill_path(path, &back, FillRule::Winding, offset, None);
+                    if outlined(colours) {
+                        pixmap.stroke_path(path, &back, &stroke(colours.border), offset, None);
+                    }
+                }
+            }
+            for (path, colours) in glyphs.iter().filter(|(_, colours)| outlined(colours)) {
+                let outline = paint(colours.outline, opacity);
+                pixmap.stroke_path(path, &outline, &stroke(colours.border), transform, None);
+            }
+            for (path, colours) in &glyphs {
+                pixmap.fill_path(path, &paint(colours.primary, opacity), FillRule::Winding, transform, None);
+            }
+        }
+
+        Ok(Frame { pixmap })
+    }
+}
+
+fn paint(colour: HexColor, opacity: f64) -> Paint<'static> {
+    let mut paint = Paint::default();
+    paint.set_color(Color::from_rgba8(colour.r, colour.g, colour.b, (colour.a as f64 * opacity).round() as u8));
+    paint.anti_alias = true;
+    paint
+}
+
+/// An outline drawn around the glyph, `border` wide outside of it.
+fn stroke(border: f64) -> Stroke {
+    Stroke { width: (border * 2.0) as f32, line_join: LineJoin::Round, ..Stroke::default() }
+}
+
+/// How opaque the dialogue is `elapsed_ms` after its start, following its `\fad` or `\fade`.
+fn fade_opacity(text: &str, elapsed_ms: i64, duration_ms: i64) -> f64 {
+    let elapsed = elapsed_ms as f64;
+    for segment in segments(text) {
+        let Segment::Tags(block) = segment else {
+            continue;
+        };
+        for tag in split_tags(block) {
+            let Some(tag) = tag.strip_prefix('\\') else {
+                continue;
+            };
+            let name = tag_name(tag);
+            if !matches!(name, Some("fad" | "fade")) {
+                continue;
+            }
+            let arguments: Vec<f64> = tag_arguments(tag).unwrap_or_default().iter()
+                .filter_map(|argument| argument.parse::<f64>().ok())
+                .collect();
+            match arguments.as_slice() {
+                [fade_in, fade_out] => {
+                    let fade_in = match *fade_in > 0.0 && elapsed < *fade_in {
+                        true => elapsed / fade_in,
+                        false => 1.0,
+                    };
+                    let remaining = (duration_ms as f64 - elapsed).max(0.0);
+                    let fade_out = match *fade_out > 0.0 && remaining < *fade_out {
+                        true => remaining / fade_out,
+                        false => 1.0,
+                    };
+                    return fade_in.min(fade_out);
+                },
+                [a1, a2, a3, t1, t2, t3, t4] => {
+                    let between = |from: f64, to: f64, start: f64, end: f64| match end > start {
+                        true => from + (to - from) * ((elapsed - start) / (end - start)).clamp(0.0, 1.0),
+                        false => to,
+                    };
+                    let alpha = match elapsed {
+                        elapsed if elapsed < *t1 => *a1,
+                        elapsed if elapsed < *t2 => between(*a1, *a2, *t1, *t2),
+                        elapsed if elapsed < *t3 => *a2,
+                        elapsed if elapsed < *t4 => between(*a2, *a3, *t3, *t4),
+                        _ => *a3,
+                    };
+                    return 1.0 - alpha.clamp(0.0, 255.0) / 255.0;
+                },
+                _ => {},
+            }
+        }
+    }
+    1.0
+}
+
+/// The outline of a character with its left edge at `x` and its baseline at `baseline`.
+fn glyph_path(face: &Face, c: char, run: &Run, x: f64, baseline: f64) -> Option<SkiaPath> {
+    let glyph = face.glyph_index(c).unwrap_or(GlyphId(0));
+    let scale = font_scale(face, run.size);
+    let mut builder = GlyphBuilder {
+        path: PathBuilder::new(),
+        x: x as f32,
+        y: baseline as f32,
+        scale_x: (scale * run.scale_x / 100.0) as f32,
+        scale_y: (scale * run.scale_y / 100.0) as f32,
+    };
+    face.outline_glyph(glyph, &mut builder)?;
+    builder.path.finish()
+}
+
+/// Turns the outline of a glyph in font units into a path on the screen.
+struct GlyphBuilder {
+    path: PathBuilder,
+    x: f32,
+    y: f32,
+    scale_x: f32,
+    scale_y: f32,
+}
+
+impl GlyphBuilder {
+    fn point(&self, x: f32, y: f32) -> (f32, f32) {
+        // font units grow upwards from the baseline
+        (self.x + x * self.scale_x, self.y - y * self.scale_y)
+    }
+}
+
+impl OutlineBuilder for GlyphBuilder {
+    fn move_to(&mut self, x: f32, y: f32) {
+        let (x, y) = self.point(x, y);
+        self.path.move_to(x, y);
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        let (x, y) = self.point(x, y);
+        self.path.line_to(x, y);
+    }
+
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        let ((x1, y1), (x, y)) = (self.point(x1, y1), self.point(x, y));
+        self.path.quad_to(x1, y1, x, y);
+    }
+
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        let ((x1, y1), (x2, y2), (x, y)) = (self.point(x1, y1), self.point(x2, y2), self.point(x, y));
+        self.path.cubic_to(x1, y1, x2, y2, x, y);
+    }
+
+    fn close(&mut self) {
+        self.path.close();
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::ass_file_with;
+
+    /// Dialogues in a red opaque box at the top left.
+    fn boxed(texts: &[&str]) -> AssFile {
+        let mut ass_file = ass_file_with(texts);
+        ass_file.components.v4
+            .set_outlinecolour("&H000000FF")
+            .set_borderstyle("3")
+            .set_outline("0")
+            .set_alignment("7");
+        ass_file
+    }
+
+    #[test]
+    fn test_render_box() {
+        // the opaque box is drawn from the layout, even without fonts
+        let ass_file = boxed(&["Rap God"]);
+        let frame = ass_file.render_frame(500, &Fonts::new(), &RenderOptions::new()).unwrap();
+        assert_eq!((200, 100), (frame.width(), frame.height()));
+        assert_eq!(Some(HexColor::RED), frame.pixel(20, 20));
+        assert_eq!(Some(0), frame.pixel(150, 20).map(|pixel| pixel.a));
+        assert_eq!(200 * 100 * 4, frame.pixels().len());
+        assert!(frame.encode_png().unwrap().starts_with(b"\x89PNG"));
+
+        let small = ass_file.render_frame(500, &Fonts::new(), RenderOptions::new().set_size(100, 50)).unwrap();
+        assert_eq!(Some(HexColor::RED), small.pixel(10, 10));
+        let empty = ass_file.render_frame(1500, &Fonts::new(), &RenderOptions::new()).unwrap();
+        assert_eq!(Some(0), empty.pixel(20, 20).map(|pixel| pixel.a));
+    }
+
+    #[test]
+    fn test_render_glyphs() {
+        let fonts = Fonts::from_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures")).unwrap();
+        let mut ass_file = ass_file_with(&["H"]);
+        ass_file.components.v4
+            .set_fontname("Box Sans")
+            .set_outlinecolour("&H000000FF")
+            .set_backcolour("&H00FF0000")
+            .set_outline("2")
+            .set_shadow("3")
+            .set_alignment("7");
+        let frame = ass_file.render_frame(500, &fonts, &RenderOptions::new()).unwrap();
+
+        // H is a box from (11, 12) to (19, 26) under the baseline at 26, with a 2 pixel outline
+        // and the shadow 3 pixels down and right
+        assert_eq!(Some(HexColor::WHITE), frame.pixel(15, 19));
+        assert_eq!(Some(HexColor::RED), frame.pixel(10, 19));
+        assert_eq!(Some(HexColor::RED), frame.pixel(15, 11));
+        assert_eq!(Some(HexColor::BLUE), frame.pixel(22, 19));
+        assert_eq!(Some(HexColor::BLUE), frame.pixel(18, 29));
+        assert_eq!(Some(0), frame.pixel(30, 19).map(|pixel| pixel.a));
+        assert_eq!(Some(0), frame.pixel(8, 8).map(|pixel| pixel.a));
+    }
+
+    #[test]
+    fn test_render_size() {
+        let mut ass_file = boxed(&["Rap God"]);
+        ass_file.components.script.set_playresx("100000").set_playresy("100000");
+        let error = ass_file.render_frame(500, &Fonts::new(), &RenderOptions::new()).unwrap_err();
+        assert_eq!(io::ErrorKind::InvalidInput, error.kind());
+        assert!(ass_file.render_frame(500, &Fonts::new(), RenderOptions::new().set_size(320, 180)).is_ok());
+    }
+
+    #[test]
+    fn test_fades() {
+        assert_eq!(0.5, fade_opacity(r"{\fad(200,400)}Rap", 100, 1000));
+        assert_eq!(1.0, fade_opacity(r"{\fad(200,400)}Rap", 500, 1000));
+        assert_eq!(0.25, fade_opacity(r"{\fad(200,400)}Rap", 900, 1000));
+        assert_eq!(1.0, fade_opacity(r"{\fade(255,0,255,0,100,200,300)}Rap", 150, 1000));
+        assert_eq!(0.0, fade_opacity(r"{\fade(255,0,255,0,100,200,300)}Rap", 400, 1000));
+
+        let frame = boxed(&[r"{\fad(1000,0)}Rap"]).render_frame(500, &Fonts::new(), &RenderOptions::new()).unwrap();
+        assert_eq!(Some(128), frame.pixel(15, 15).map(|pixel| pixel.a));
+    }
+}
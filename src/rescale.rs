@@ -0,0 +1,336 @@
+//! # Rescale
+//!
+//! Change the resolution (`PlayResX` and `PlayResY`) of a script and scale everything which is
+//! measured in script pixels along with it: the font sizes, outlines, shadows, spacing and
+//! margins of the styles and dialogues, and the `\pos`, `\move`, `\org`, `\clip`, `\fs`,
+//! `\bord`, `\shad` and `\blur` override tags and vector drawings of the dialogues.
+//!
+//! ```rust
+//! use ass_parser::{AssFile, Dialogue, Events, ScriptInfo, V4Format};
+//! use ass_parser::rescale::RescaleMode;
+//!
+//! let mut ass_file = AssFile::new();
+//! ass_file.components.script.set_script(ScriptInfo::default());
+//! ass_file.components.v4.set_v4(V4Format::default());
+//! ass_file.components.events.set_events(Events::default())
+//!     .add_dialogue(Dialogue::default().set_text("{\\pos(192,144)}Rap God"));
+//!
+//! ass_file.rescale(1920, 1080, RescaleMode::Letterbox);
+//! assert_eq!(Some("1920"), ass_file.components.script.get_playresx());
+//! assert_eq!(Some("{\\pos(960,540)}Rap God"), ass_file.components.events.iter().last().unwrap().get_text());
+//! ```
+
+use crate::{AssFile, ScriptInfo};
+use crate::drawing::{tokenize, Token};
+use crate::text::{format_number, segments, split_tags, tag_arguments, Segment};
+
+/// How a script is fitted into a resolution with a different aspect ratio.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RescaleMode {
+    /// Scale the width and height separately so the script fills the new resolution.
+    /// Text is stretched along with the positions.
+    #[default]
+    Stretch,
+    /// Scale the width and height by the same amount to keep the aspect ratio and center the
+    /// script, leaving bars at the top and bottom (letterbox) or the sides (pillarbox).
+    Letterbox,
+}
+
+/// The scale factors and offsets from the old to the new resolution.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Scale {
+    x: f64,
+    y: f64,
+    offset_x: f64,
+    offset_y: f64,
+    /// Whether outlines and shadows are in script pixels (`ScaledBorderAndShadow: yes`).
+    borders: bool,
+}
+
+impl Scale {
+    fn point(&self, x: f64, y: f64) -> (f64, f64) {
+        (x * self.x + self.offset_x, y * self.y + self.offset_y)
+    }
+
+    fn average(&self) -> f64 {
+        (self.x + self.y) / 2.0
+    }
+
+    fn border(&self) -> f64 {
+        if self.borders { self.average() } else { 1.0 }
+    }
+}
+
+impl ScriptInfo {
+    /// The resolution of the script, using the same defaults as renderers when `PlayResX` or
+    /// `PlayResY` is missing.
+    pub(crate) fn play_res(&self) -> (f64, f64) {
+        let playres = |value: Option<&str>| value.and_then(|value| value.trim().parse::<f64>().ok()).filter(|value| *value > 0.0);
+        match (playres(self.get_playresx()), playres(self.get_playresy())) {
+            (Some(x), Some(y)) => (x, y),
+            (Some(x), None) => (x, if x == 1280.0 { 1024.0 } else { x * 3.0 / 4.0 }),
+            (None, Some(y)) => (if y == 1024.0 { 1280.0 } else { y * 4.0 / 3.0 }, y),
+            (None, None) => (384.0, 288.0),
+        }
+    }
+}
+
+impl AssFile {
+    /// Change the resolution of the script to `width` x `height` and scale the styles and
+    /// dialogues to match. A missing `PlayResX` or `PlayResY` is taken as the 384x288 default
+    /// used by renderers. Outlines and shadows are only scaled when `ScaledBorderAndShadow` is
+    /// not `no`, since they are measured in video pixels otherwise.
+    pub fn rescale(&mut self, width: u32, height: u32, mode: RescaleMode) -> &mut AssFile {
+        let script = &self.components.script;
+        let (old_width, old_height) = script.play_res();
+
+        let (width, height) = (width as f64, height as f64);
+        let borders = !script.get_scaledborderandshadow().is_some_and(|value| value.trim().eq_ignore_ascii_case("no"));
+        let scale = match mode {
+            RescaleMode::Stretch => Scale {
+                x: width / old_width,
+                y: height / old_height,
+                offset_x: 0.0,
+                offset_y: 0.0,
+                borders,
+            },
+            RescaleMode::Letterbox => {
+                let factor = (width / old_width).min(height / old_height);
+                Scale {
+                    x: factor,
+                    y: factor,
+                    offset_x: (width - old_width * factor) / 2.0,
+                    offset_y: (height - old_height * factor) / 2.0,
+                    borders,
+                }
+            },
+        };
+
+        self.components.script
+            .set_playresx(&format_number(width))
+            .set_playresy(&format_number(height));
+
+        for style in self.components.v4.styles.iter_mut() {
+            scale_field(&mut style.fontsize, scale.y, 0.0);
+            scale_field(&mut style.scalex, scale.x / scale.y, 0.0);
+            scale_field(&mut style.spacing, scale.x, 0.0);
+            scale_field(&mut style.outline, scale.border(), 0.0);
+            scale_field(&mut style.shadow, scale.border(), 0.0);
+            scale_field(&mut style.marginl, scale.x, scale.offset_x);
+            scale_field(&mut style.marginr, scale.x, scale.offset_x);
+            scale_field(&mut style.marginv, scale.y, scale.offset_y);
+        }
+
+        for dialogue in self.components.events.dialogues.dialogues.iter_mut() {
+            let event = &mut dialogue.event;
+            // a margin of 0 means the margin of the style is used
+            for (margin, factor, offset) in [(&mut event.marginl, scale.x, scale.offset_x), (&mut event.marginr, scale.x, scale.offset_x), (&mut event.marginv, scale.y, scale.offset_y)] {
+                if margin.as_deref().and_then(|margin| margin.trim().parse::<f64>().ok()).is_some_and(|margin| margin != 0.0) {
+                    scale_field(margin, factor, offset);
+                }
+            }
+            if let Some(text) = &event.text {
+                event.text = Some(rescale_text(text, &scale));
+            }
+        }
+
+        self
+    }
+}
+
+/// Scale a numeric field, rounding to two decimals. Fields which aren't numbers are left alone.
+fn scale_field(field: &mut Option<String>, factor: f64, offset: f64) {
+    if let Some(value) = field.as_deref().and_then(|value| value.trim().parse::<f64>().ok()) {
+        let scaled = value * factor + offset;
+        *field = Some(format_number((scaled * 100.0).round() / 100.0));
+    }
+}
+
+fn rescale_text(text: &str, scale: &Scale) -> String {
+    let mut rescaled = String::new();
+    let mut drawing = false;
+
+    for segment in segments(text) {
+        match segment {
+            Segment::Tags(block) => {
+                rescaled.push('{');
+                rescaled.push_str(&rescale_block(block, scale, &mut drawing));
+                rescaled.push('}');
+            },
+            Segment::Text(text) if drawing => rescaled.push_str(&scale_drawing(text, scale.x, scale.y, 0.0, 0.0)),
+            Segment::Text(text) => rescaled.push_str(text),
+        }
+    }
+
+    rescaled
+}
+
+/// Scale the tags of an override block. `drawing` is set when the block turns on `\p` drawing
+/// mode and cleared when it turns it off.
+fn rescale_block(block: &str, scale: &Scale, drawing: &mut bool) -> String {
+    split_tags(block).into_iter()
+        .map(|tag| match tag.strip_prefix('\\') {
+            Some(tag) => format!("\\{}", rescale_tag(tag, scale, drawing)),
+            None => tag.to_string(),
+        })
+        .collect()
+}
+
+fn rescale_tag(tag: &str, scale: &Scale, drawing: &mut bool) -> String {
+    let number = |value: &str| value.trim().parse::<f64>().ok();
+    let scaled = |name: &str, value: &str, factor: f64| match number(value) {
+        Some(value) => format!("{}{}", name, format_number(value * factor)),
+        None => format!("{}{}", name, value),
+    };
+
+    for name in ["pos", "org", "move", "clip", "iclip", "t"] {
+        let Some(arguments) = tag.strip_prefix(name).filter(|rest| rest.trim_start().starts_with('(')).and_then(tag_arguments) else {
+            continue;
+        };
+        return match name {
+            "t" => {
+                let open = tag.find('(').unwrap_or(0);
+                let close = tag.rfind(')').filter(|close| *close > open).unwrap_or(tag.len());
+                let inner = &tag[open + 1..close];
+                let tags_start = inner.find('\\').unwrap_or(inner.len());
+                let mut inner_drawing = *drawing;
+                format!("t({}{}){}", &inner[..tags_start], rescale_block(&inner[tags_start..], scale, &mut inner_drawing), tag.get(close + 1..).unwrap_or(""))
+            },
+            "clip" | "iclip" if arguments.len() <= 2 => {
+                let (drawing_scale, commands) = match arguments.as_slice() {
+                    [drawing_scale, commands] => (drawing_scale.parse::<i32>().unwrap_or(1), *commands),
+                    _ => (1, arguments[0]),
+                };
+                // coordinates of a clip drawing are multiplied by 2^(scale - 1)
+                let unit = 2f64.powi(drawing_scale.max(1) - 1);
+                let commands = scale_drawing(commands, scale.x, scale.y, scale.offset_x * unit, scale.offset_y * unit);
+                match arguments.len() {
+                    2 => format!("{}({},{})", name, arguments[0], commands),
+                    _ => format!("{}({})", name, commands),
+                }
+            },
+            _ => {
+                let mut values: Vec<String> = arguments.iter().map(|argument| argument.to_string()).collect();
+                // \move has two points followed by optional times, the others are all points
+                let points = if name == "move" { 2 } else { values.len() / 2 };
+                for point in 0..points.min(values.len() / 2) {
+                    if let (Some(x), Some(y)) = (number(&values[point * 2]), number(&values[point * 2 + 1])) {
+                        let (x, y) = scale.point(x, y);
+                        values[point * 2] = format_number(x);
+                        values[point * 2 + 1] = format_number(y);
+                    }
+                }
+                format!("{}({})", name, values.join(","))
+            },
+        };
+    }
+
+    let numeric = |rest: &str| rest.trim().is_empty() || number(rest).is_some();
+    for (name, factor) in [
+        ("fscx", scale.x / scale.y),
+        ("fsp", scale.x),
+        ("fs", scale.y),
+        ("xbord", if scale.borders { scale.x } else { 1.0 }),
+        ("ybord", if scale.borders { scale.y } else { 1.0 }),
+        ("xshad", if scale.borders { scale.x } else { 1.0 }),
+        ("yshad", if scale.borders { scale.y } else { 1.0 }),
+        ("bord", scale.border()),
+        ("shad", scale.border()),
+        ("blur", scale.border()),
+    ] {
+        if let Some(value) = tag.strip_prefix(name).filter(|value| numeric(value)) {
+            return scaled(name, value, factor);
+        }
+    }
+
+    if let Some(value) = tag.strip_prefix('p').filter(|value| numeric(value)) {
+        *drawing = number(value).is_some_and(|value| value > 0.0);
+    }
+    tag.to_string()
+}
+
+/// Scale the coordinates of vector drawing commands such as `m 0 0 l 100 0 100 100`. Only the
+/// numbers of coordinate pairs are changed, so unknown commands and numbers without a pair are
+/// kept as they are.
+fn scale_drawing(commands: &str, x: f64, y: f64, offset_x: f64, offset_y: f64) -> String {
+    let tokens = tokenize(commands);
+    let mut replacements = Vec::new();
+    for (i, (_, token)) in tokens.iter().enumerate() {
+        if !matches!(token, Token::Letter('m' | 'n' | 'l' | 'b' | 's' | 'p')) {
+            continue;
+        }
+        let numbers: Vec<_> = tokens[i + 1..].iter()
+            .map_while(|(range, token)| match token {
+                Token::Number(number) => Some((range.clone(), *number)),
+                Token::Letter(_) => None,
+            })
+            .collect();
+        for pair in numbers.chunks_exact(2) {
+            replacements.push((pair[0].0.clone(), pair[0].1 * x + offset_x));
+            replacements.push((pair[1].0.clone(), pair[1].1 * y + offset_y));
+        }
+    }
+
+    let mut scaled = String::new();
+    let mut copied = 0;
+    for (range, value) in replacements {
+        scaled.push_str(&commands[copied..range.start]);
+        scaled.push_str(&format_number(value));
+        copied = range.end;
+    }
+    scaled.push_str(&commands[copied..]);
+    scaled
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Dialogue, Events, V4Format};
+
+    fn ass_file(text: &str) -> AssFile {
+        let mut ass_file = AssFile::from_events(Events::from_dialogues(vec![Dialogue::default().set_text(text)]));
+        ass_file.components.script.set_script(ScriptInfo::default());
+        ass_file.components.v4.set_v4(V4Format::default());
+        ass_file
+    }
+
+    fn text(ass_file: &AssFile) -> &str {
+        ass_file.components.events.iter().next().unwrap().get_text().unwrap()
+    }
+
+    #[test]
+    fn test_rescale_stretch() {
+        let mut ass_file = ass_file(r"{\pos(192,144)\fs20\bord2\t(0,500,\fs40)\clip(m 0 0 l 384 0 384 288)}Look{\p1}m 0 0 l 10 10{\p0}");
+        ass_file.rescale(768, 864, RescaleMode::Stretch);
+        let style = ass_file.components.v4.get_style("Default").unwrap();
+
+        assert_eq!(Some("48"), style.get_fontsize());
+        assert_eq!(Some("66.67"), style.get_scalex());
+        assert_eq!(Some("2.5"), style.get_outline());
+        assert_eq!(Some("20"), style.get_marginl());
+        assert_eq!(Some("30"), style.get_marginv());
+        assert_eq!(r"{\pos(384,432)\fs60\bord5\t(0,500,\fs120)\clip(m 0 0 l 768 0 768 864)}Look{\p1}m 0 0 l 20 30{\p0}", text(&ass_file));
+    }
+
+    #[test]
+    fn test_rescale_letterbox() {
+        let mut ass_file = ass_file(r"{\move(0,0,384,288,0,500)\clip(0,0,384,288)\shad1}Look");
+        ass_file.components.script.set_scaledborderandshadow("no");
+        ass_file.rescale(1920, 1080, RescaleMode::Letterbox);
+        let style = ass_file.components.v4.get_style("Default").unwrap();
+
+        assert_eq!(Some("60"), style.get_fontsize());
+        assert_eq!(Some("1"), style.get_outline());
+        assert_eq!(Some("277.5"), style.get_marginl());
+        assert_eq!(r"{\move(240,0,1680,1080,0,500)\clip(240,0,1680,1080)\shad1}Look", text(&ass_file));
+    }
+
+    #[test]
+    fn test_rescale_drawing() {
+        // only coordinate pairs are scaled, unpaired numbers and unknown commands are kept
+        let mut ass_file = ass_file(r"{\p1}m 0 0 l 10 10 5 x 20 0 l 0 10{\p0}");
+        ass_file.rescale(768, 576, RescaleMode::Stretch);
+        assert_eq!(r"{\p1}m 0 0 l 20 20 5 x 20 0 l 0 20{\p0}", text(&ass_file));
+    }
+}
@@ -0,0 +1,260 @@
+use crate::{AssFile, Dialogue, Events, V4Format, V4Styles};
+use crate::timestamp::format_time;
+use crate::text::{decode_entities, strip_tags};
+
+/// How long the last caption of a language stays on screen when no later `SYNC` ends it.
+const LAST_SYNC_DURATION: i64 = 5000;
+
+struct Sync {
+    start: i64,
+    paragraphs: Vec<(String, String)>,
+}
+
+/// Parse the contents of a SAMI (`.smi`) subtitle file.
+///
+/// Every `<P Class=..>` inside a `<SYNC Start=..>` block becomes a `Dialogue` which ends at the
+/// next `SYNC` of the same class. `languages` maps SAMI classes to ASS style names. Classes which
+/// are not in `languages` are skipped, and if `languages` is empty every class is imported with a
+/// style named after the class.
+pub(crate) fn parse_sami(contents: &str, languages: &[(&str, &str)]) -> AssFile {
+    let syncs = get_syncs(contents);
+    let mut dialogues = Vec::new();
+    let mut style_names: Vec<String> = Vec::new();
+
+    for (i, sync) in syncs.iter().enumerate() {
+        for (class, text) in &sync.paragraphs {
+            if text.is_empty() {
+                continue;
+            }
+            let style = if languages.is_empty() {
+                class.clone()
+            } else {
+                match languages.iter().find(|(language, _)| language.eq_ignore_ascii_case(class)) {
+                    Some((_, style)) => style.to_string(),
+                    None => continue,
+                }
+            };
+
+            let later = &syncs[i + 1..];
+            let end = later.iter()
+                .find(|next| next.start > sync.start && next.paragraphs.iter().any(|(next_class, _)| next_class.eq_ignore_ascii_case(class)))
+                .or_else(|| later.iter().find(|next| next.start > sync.start))
+                .map(|next| next.start)
+                .unwrap_or(sync.start + LAST_SYNC_DURATION);
+
+            if !style_names.contains(&style) {
+                style_names.push(style.clone());
+            }
+            dialogues.push(Dialogue::default()
+                .set_start(&format_time(sync.start))
+                .set_end(&format_time(end))
+                .set_style(&style)
+                .set_text(text));
+        }
+    }
+
+    let mut ass_file = AssFile::from_events(Events::from_dialogues(dialogues));
+    if !style_names.is_empty() {
+        let styles = style_names.iter()
+            .map(|name| V4Format::default().set_name(name).clone())
+            .collect();
+        ass_file.components.v4 = V4Styles { styles };
+    }
+    ass_file
+}
+
+fn get_syncs(contents: &str) -> Vec<Sync> {
+    // ASCII lowercasing keeps byte offsets so positions in `lower` are valid in `contents`.
+    let lower = contents.to_ascii_lowercase();
+    let body_end = lower.find("</body").unwrap_or(lower.len());
+    let sync_positions: Vec<usize> = lower.match_indices("<sync")
+        .map(|(i, _)| i)
+        .filter(|i| *i < body_end)
+        .collect();
+    let mut syncs = Vec::new();
+
+    for (i, position) in sync_positions.iter().enumerate() {
+        let Some(tag_len) = lower[*position..].find('>') else {
+            continue;
+        };
+        let tag = &contents[*position..*position + tag_len];
+        let Some(start) = get_attribute(tag, "start").and_then(|start| start.parse().ok()) else {
+            continue;
+        };
+        let block_end = sync_positions.get(i + 1).copied().unwrap_or(body_end);
+        let block_start = (*position + tag_len + 1).min(block_end);
+
+        syncs.push(Sync {
+            start,
+            paragraphs: get_paragraphs(&contents[block_start..block_end]),
+        });
+    }
+
+    syncs
+}
+
+fn get_paragraphs(block: &str) -> Vec<(String, String)> {
+    let lower = block.to_ascii_lowercase();
+    let positions: Vec<usize> = lower.match_indices("<p")
+        .map(|(i, _)| i)
+        .filter(|i| matches!(lower.as_bytes().get(i + 2), Some(b'>' | b' ' | b'\t' | b'\r' | b'\n')))
+        .collect();
+    let mut paragraphs = Vec::new();
+
+    for (i, position) in positions.iter().enumerate() {
+        let end = positions.get(i + 1).copied().unwrap_or(block.len());
+        let tag_len = lower[*position..end].find('>').map(|len| len + 1).unwrap_or(end - position);
+        let class = get_attribute(&block[*position..*position + tag_len], "class").unwrap_or_default();
+
+        paragraphs.push((class, html_to_text(&block[*position + tag_len..end])));
+    }
+
+    paragraphs
+}
+
+fn get_attribute(tag: &str, name: &str) -> Option<String> {
+    let lower = tag.to_ascii_lowercase();
+    let position = lower.match_indices(name)
+        .map(|(i, _)| i)
+        .find(|i| *i > 0 && lower.as_bytes()[i - 1].is_ascii_whitespace())?;
+    let value = tag[position + name.len()..].trim_start().strip_prefix('=')?.trim_start();
+
+    let value = match value.strip_prefix(['"', '\'']) {
+        Some(quoted) => quoted.split(['"', '\'']).next().unwrap_or(""),
+        None => value.split(|c: char| c.is_whitespace() || c == '>').next().unwrap_or(""),
+    };
+    Some(value.to_string())
+}
+
+/// Convert a SAMI paragraph into dialogue text: `<br>` becomes `\N`, other markup is removed and
+/// HTML entities are decoded.
+fn html_to_text(html: &str) -> String {
+    let mut text = String::new();
+    let mut rest = html;
+
+    while let Some(open) = rest.find('<') {
+        text.push_str(&rest[..open]);
+        let close = rest[open..].find('>').map(|close| open + close + 1).unwrap_or(rest.len());
+        let tag = rest[open + 1..close].trim_end_matches('>').trim().to_ascii_lowercase();
+        if tag.starts_with("br") {
+            text.push('\u{0}');
+        }
+        rest = &rest[close..];
+    }
+    text.push_str(rest);
+
+    let text = decode_entities(&text.split_whitespace().collect::<Vec<&str>>().join(" "));
+    text.split('\u{0}')
+        .map(|line| line.trim())
+        .collect::<Vec<&str>>()
+        .join("\\N")
+        .trim_matches(|c: char| c.is_whitespace() || c == '\u{a0}')
+        .trim_end_matches("\\N")
+        .replace('\u{a0}', "\\h")
+}
+
+/// Convert the dialogues of an `AssFile` into a SAMI file with one class per style.
+/// `languages` maps SAMI classes to style names like in `parse_sami`. Styles which are not in
+/// `languages` use their own name as the class.
+pub(crate) fn stringify_sami(ass_file: &AssFile, languages: &[(&str, &str)]) -> String {
+    let class_for = |style: &str| -> String {
+        match languages.iter().find(|(_, language_style)| *language_style == style) {
+            Some((class, _)) => class.to_string(),
+            None => style.chars().filter(|c| c.is_ascii_alphanumeric()).collect(),
+        }
+    };
+
+    let mut classes: Vec<(String, String)> = Vec::new();
+    let mut timeline: Vec<(i64, String, Option<String>)> = Vec::new();
+    for dialogue in ass_file.components.events.iter().filter(|dialogue| !dialogue.is_comment()) {
+        let (Some(start), Some(end)) = (dialogue.get_start_ms(), dialogue.get_end_ms()) else {
+            continue;
+        };
+        let style = dialogue.get_style().unwrap_or("Default");
+        let class = class_for(style);
+        if !classes.iter().any(|(existing, _)| *existing == class) {
+            classes.push((class.clone(), style.to_string()));
+        }
+
+        timeline.push((start, class.clone(), Some(text_to_html(dialogue.get_text().unwrap_or("")))));
+        timeline.push((end, class, None));
+    }
+    timeline.sort_by_key(|(time, _, _)| *time);
+
+    let mut contents = String::new();
+    contents.push_str("<SAMI>\n<HEAD>\n");
+    if let Some(title) = ass_file.components.script.get_field("Title") {
+        contents.push_str(&format!("<TITLE>{}</TITLE>\n", escape_html(title)));
+    }
+    contents.push_str("<STYLE TYPE=\"text/css\">\n<!--\nP { text-align: center; font-family: Arial; color: white; }\n");
+    for (class, style) in &classes {
+        contents.push_str(&format!(".{} {{ Name: {}; }}\n", class, style));
+    }
+    contents.push_str("-->\n</STYLE>\n</HEAD>\n<BODY>\n");
+
+    let mut i = 0;
+    while i < timeline.len() {
+        let time = timeline[i].0;
+        let same_time: Vec<&(i64, String, Option<String>)> = timeline[i..].iter()
+            .take_while(|(other, _, _)| *other == time)
+            .collect();
+        i += same_time.len();
+
+        let mut paragraphs = Vec::new();
+        for (_, class, text) in &same_time {
+            let starts_again = text.is_none() && same_time.iter().any(|(_, other, text)| other == class && text.is_some());
+            if !starts_again {
+                paragraphs.push(format!("<P Class={}>{}", class, text.as_deref().unwrap_or("&nbsp;")));
+            }
+        }
+        if !paragraphs.is_empty() {
+            contents.push_str(&format!("<SYNC Start={}>{}\n", time, paragraphs.join("\n")));
+        }
+    }
+
+    contents.push_str("</BODY>\n</SAMI>\n");
+    contents
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+fn text_to_html(text: &str) -> String {
+    escape_html(&strip_tags(text)).replace("\\N", "<br>").replace("\\n", "<br>").replace("\\h", "&nbsp;")
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMI: &str = "<SAMI>\n<HEAD>\n<STYLE TYPE=\"text/css\">\n<!--\n.KRCC { Name: Korean; lang: ko-KR; }\n.ENCC { Name: English; lang: en-US; }\n-->\n</STYLE>\n</HEAD>\n<BODY>\n<SYNC Start=1000><P Class=KRCC>안녕하세요<br>친구\n<P Class=ENCC>Hello &amp; welcome<br>friend\n<SYNC Start=2500><P Class=ENCC>&nbsp;\n<SYNC Start=3000><P Class=KRCC>&nbsp;\n</BODY>\n</SAMI>\n";
+
+    #[test]
+    fn test_parse_sami() {
+        let ass_file = parse_sami(SAMI, &[("KRCC", "Default"), ("ENCC", "English")]);
+        let dialogues: Vec<&Dialogue> = ass_file.components.events.iter().collect();
+
+        assert_eq!(2, dialogues.len());
+        assert_eq!(Some("안녕하세요\\N친구"), dialogues[0].get_text());
+        assert_eq!(Some("0:00:03.00"), dialogues[0].get_end());
+        assert_eq!(Some("Hello & welcome\\Nfriend"), dialogues[1].get_text());
+        assert_eq!(Some("English"), dialogues[1].get_style());
+        assert_eq!(Some("0:00:02.50"), dialogues[1].get_end());
+        assert!(ass_file.components.v4.get_style("English").is_some());
+
+        let korean_only = parse_sami(SAMI, &[("krcc", "Korean")]);
+        assert_eq!(1, korean_only.components.events.len());
+    }
+
+    #[test]
+    fn test_sami_round_trip() {
+        let languages = [("KRCC", "Default"), ("ENCC", "English")];
+        let ass_file = parse_sami(SAMI, &languages);
+        let sami = stringify_sami(&ass_file, &languages);
+
+        assert!(sami.contains("<SYNC Start=1000><P Class=KRCC>안녕하세요<br>친구\n<P Class=ENCC>Hello &amp; welcome<br>friend\n"));
+        assert_eq!(ass_file, parse_sami(&sami, &languages));
+    }
+}
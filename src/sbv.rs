@@ -0,0 +1,77 @@
+use crate::{AssFile, Dialogue, Events};
+use crate::timestamp::{format_time, format_time_millis, parse_time};
+use crate::text::{replace_line_breaks, strip_tags};
+
+/// Parse the contents of a YouTube `.sbv` caption file.
+///
+/// Each caption is a `start,end` line followed by one or more lines of text and separated from
+/// the next caption by an empty line. Multiple text lines are joined with `\N`.
+pub(crate) fn parse_sbv(contents: &str) -> AssFile {
+    let mut dialogues = Vec::new();
+    let mut block: Vec<&str> = Vec::new();
+
+    for line in contents.lines().chain(std::iter::once("")) {
+        let line = line.trim_end_matches('\r');
+        if !line.trim().is_empty() {
+            block.push(line);
+            continue;
+        }
+        if let Some(dialogue) = parse_block(&block) {
+            dialogues.push(dialogue);
+        }
+        block.clear();
+    }
+
+    AssFile::from_events(Events::from_dialogues(dialogues))
+}
+
+fn parse_block(block: &[&str]) -> Option<Dialogue> {
+    let (timing, text) = block.split_first()?;
+    let (start, end) = timing.split_once(',')?;
+    let start = parse_time(start)?;
+    let end = parse_time(end)?;
+
+    Some(Dialogue::default()
+        .set_start(&format_time(start))
+        .set_end(&format_time(end))
+        .set_text(&text.join("\\N")))
+}
+
+/// Convert the dialogues of an `AssFile` into `.sbv` captions.
+/// Style override codes are removed since `.sbv` has no styling.
+pub(crate) fn stringify_sbv(ass_file: &AssFile) -> String {
+    let mut contents = String::new();
+
+    for dialogue in ass_file.components.events.iter().filter(|dialogue| !dialogue.is_comment()) {
+        let (Some(start), Some(end)) = (dialogue.get_start_ms(), dialogue.get_end_ms()) else {
+            continue;
+        };
+        let text = strip_tags(dialogue.get_text().unwrap_or(""));
+
+        contents.push_str(&format!("{},{}\n", format_time_millis(start), format_time_millis(end)));
+        contents.push_str(&replace_line_breaks(&text, "\n"));
+        contents.push_str("\n\n");
+    }
+
+    contents
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sbv_round_trip() {
+        let contents = "0:00:00.599,0:00:04.160\n>> ALICE: Hi, my name is Alice\nand this is John\n\n0:00:04.160,0:00:06.770\n>> JOHN: Hello!\n";
+        let ass_file = parse_sbv(contents);
+        let dialogues: Vec<&Dialogue> = ass_file.components.events.iter().collect();
+
+        assert_eq!(2, dialogues.len());
+        assert_eq!(Some("0:00:00.60"), dialogues[0].get_start());
+        assert_eq!(Some(">> ALICE: Hi, my name is Alice\\Nand this is John"), dialogues[0].get_text());
+
+        let sbv = stringify_sbv(&ass_file);
+        assert!(sbv.starts_with("0:00:00.600,0:00:04.160\n>> ALICE: Hi, my name is Alice\nand this is John\n\n"));
+    }
+}
@@ -0,0 +1,167 @@
+//! # Split
+//!
+//! Cut an `AssFile` apart: take the dialogues of a time window, or make one file for each style
+//! or actor. Each output keeps the `Script Info` of the original and only the styles it uses.
+//!
+//! ```rust
+//! use ass_parser::{AssFile, Dialogue, Events};
+//! use ass_parser::split::ExtractOptions;
+//!
+//! let mut ass_file = AssFile::new();
+//! ass_file.components.events.set_events(Events::default())
+//!     .add_dialogue(Dialogue::default().set_start("0:21:00.00").set_end("0:21:05.00").set_name("Eminem"));
+//!
+//! let mut options = ExtractOptions::default();
+//! options.set_rebase(true).set_clip(true);
+//! let episode_2 = ass_file.extract_range(20 * 60_000, 40 * 60_000, &options);
+//! assert_eq!(Some("0:01:00.00"), episode_2.components.events.iter().next().unwrap().get_start());
+//!
+//! for (actor, lines) in ass_file.split_by_actor() {
+//!     println!("{}: {} lines", actor, lines.components.events.len());
+//! }
+//! ```
+
+use std::collections::BTreeMap;
+
+use crate::{AssFile, Dialogue, Events};
+use crate::timestamp::format_time;
+
+/// How `AssFile::extract_range` treats the dialogues of the window.
+/// By default times are kept as they are and dialogues crossing the edges are not cut.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ExtractOptions {
+    rebase: bool,
+    clip: bool,
+}
+
+impl ExtractOptions {
+    /// Move the dialogues so the start of the window is at `0:00:00.00`.
+    pub fn set_rebase(&mut self, rebase: bool) -> &mut Self {
+        self.rebase = rebase;
+        self
+    }
+
+    /// Cut the dialogues which cross the start or end of the window to fit inside it.
+    pub fn set_clip(&mut self, clip: bool) -> &mut Self {
+        self.clip = clip;
+        self
+    }
+}
+
+impl AssFile {
+    /// Make a new `AssFile` with the dialogues shown between `start_ms` and `end_ms`.
+    /// Dialogues without valid times are left out.
+    pub fn extract_range(&self, start_ms: i64, end_ms: i64, options: &ExtractOptions) -> AssFile {
+        let dialogues = self.components.events.iter()
+            .filter_map(|dialogue| {
+                let (start, end) = (dialogue.get_start_ms()?, dialogue.get_end_ms()?);
+                // zero length dialogues at the start of the window are still in it
+                let inside = start < end_ms && (end > start_ms || (start == end && start == start_ms));
+                if !inside {
+                    return None;
+                }
+
+                let (mut start, mut end) = match options.clip {
+                    true => (start.max(start_ms), end.min(end_ms)),
+                    false => (start, end),
+                };
+                if options.rebase {
+                    start -= start_ms;
+                    end -= start_ms;
+                }
+
+                let mut dialogue = dialogue.clone();
+                dialogue.event.start = Some(format_time(start));
+                dialogue.event.end = Some(format_time(end));
+                Some(dialogue)
+            })
+            .collect();
+
+        self.with_dialogues(dialogues)
+    }
+
+    /// Make one `AssFile` for each style used by the dialogues, keyed by the style name.
+    pub fn split_by_style(&self) -> BTreeMap<String, AssFile> {
+        self.split_by(|dialogue| dialogue.get_style().unwrap_or(""))
+    }
+
+    /// Make one `AssFile` for each actor in the Name field of the dialogues, keyed by the name.
+    /// Dialogues without a name are under the empty string.
+    pub fn split_by_actor(&self) -> BTreeMap<String, AssFile> {
+        self.split_by(|dialogue| dialogue.get_name().unwrap_or(""))
+    }
+
+    fn split_by(&self, key: impl Fn(&Dialogue) -> &str) -> BTreeMap<String, AssFile> {
+        let mut groups: BTreeMap<String, Vec<Dialogue>> = BTreeMap::new();
+        for dialogue in self.components.events.iter() {
+            groups.entry(key(dialogue).to_string()).or_default().push(dialogue.clone());
+        }
+
+        groups.into_iter()
+            .map(|(key, dialogues)| (key, self.with_dialogues(dialogues)))
+            .collect()
+    }
+
+    /// A copy of the `AssFile` with other dialogues and only the styles they use.
+    fn with_dialogues(&self, dialogues: Vec<Dialogue>) -> AssFile {
+        let mut ass_file = AssFile {
+            _ass_file: String::new(),
+            components: self.components.clone(),
+        };
+        ass_file.components.events = Events::from_dialogues(dialogues);
+        ass_file.remove_unused_styles();
+        ass_file
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::V4Format;
+
+    fn ass_file() -> AssFile {
+        let mut ass_file = AssFile::from_events(Events::from_dialogues(vec![
+            Dialogue::default().set_start("0:00:01.00").set_end("0:00:04.00").set_name("Eminem").set_text("Look"),
+            Dialogue::default().set_start("0:00:05.00").set_end("0:00:06.00").set_style("Signs").set_text("Rap God"),
+            Dialogue::default().set_start("0:00:08.00").set_end("0:00:12.00").set_name("Eminem").set_text(r"{\rSigns}I was"),
+        ]));
+        ass_file.components.v4.add_style(V4Format::default().set_name("Signs").clone());
+        ass_file
+    }
+
+    fn times(ass_file: &AssFile) -> Vec<(&str, &str)> {
+        ass_file.components.events.iter()
+            .map(|dialogue| (dialogue.get_start().unwrap(), dialogue.get_end().unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn test_extract_range() {
+        let ass_file = ass_file();
+
+        let extracted = ass_file.extract_range(3000, 10_000, &ExtractOptions::default());
+        assert_eq!(vec![("0:00:01.00", "0:00:04.00"), ("0:00:05.00", "0:00:06.00"), ("0:00:08.00", "0:00:12.00")], times(&extracted));
+
+        let mut options = ExtractOptions::default();
+        options.set_rebase(true).set_clip(true);
+        let extracted = ass_file.extract_range(4500, 10_000, &options);
+        assert_eq!(vec![("0:00:00.50", "0:00:01.50"), ("0:00:03.50", "0:00:05.50")], times(&extracted));
+        assert_eq!(2, extracted.components.v4.len());
+    }
+
+    #[test]
+    fn test_split() {
+        let ass_file = ass_file();
+
+        let by_style = ass_file.split_by_style();
+        assert_eq!(vec!["Default", "Signs"], by_style.keys().collect::<Vec<_>>());
+        assert_eq!(1, by_style["Signs"].components.v4.len());
+        assert_eq!(Some("Signs"), by_style["Signs"].components.v4.get_name());
+
+        let by_actor = ass_file.split_by_actor();
+        assert_eq!(vec!["", "Eminem"], by_actor.keys().collect::<Vec<_>>());
+        assert_eq!(2, by_actor["Eminem"].components.events.len());
+        assert_eq!(2, by_actor["Eminem"].components.v4.len());
+    }
+}
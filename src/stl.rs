@@ -0,0 +1,457 @@
+use crate::{AssFile, Dialogue, Events, V4Format, V4Styles};
+use crate::timestamp::format_time;
+
+const GSI_SIZE: usize = 1024;
+const TTI_SIZE: usize = 128;
+const LAST_EXTENSION_BLOCK: u8 = 0xff;
+const USER_DATA_BLOCK: u8 = 0xfe;
+
+/// ASS colours (`BBGGRR`) of the eight teletext alphanumeric colour codes `0x00`-`0x07`.
+const TELETEXT_COLOURS: [&str; 8] = ["000000", "0000FF", "00FF00", "00FFFF", "FF0000", "FF00FF", "FFFF00", "FFFFFF"];
+const WHITE: u8 = 0x07;
+
+/// The information from the GSI block that is needed to decode the TTI blocks.
+struct GeneralSubtitleInformation {
+    frame_rate: i64,
+    code_table: CodeTable,
+    rows: i64,
+    programme_start: i64,
+    title: String,
+}
+
+#[derive(Clone, Copy)]
+enum CodeTable {
+    Latin,
+    Cyrillic,
+    Arabic,
+    Greek,
+    Hebrew,
+}
+
+struct Subtitle {
+    number: u16,
+    start: i64,
+    end: i64,
+    vertical_position: i64,
+    justification: u8,
+    text: Vec<u8>,
+}
+
+/// Parse an EBU Tech 3264 `.stl` binary subtitle file.
+///
+/// Subtitles in the top half of the screen use the `Top` style and the others the `Default`
+/// style. The vertical position becomes the dialogue's MarginV and left or right justification an
+/// `\an` override. Teletext colours, double height, italics and underline are kept as override
+/// codes.
+pub(crate) fn parse_stl(data: &[u8]) -> std::io::Result<AssFile> {
+    if data.len() < GSI_SIZE {
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "EBU STL file is shorter than the GSI block"));
+    }
+    let gsi = parse_gsi(&data[..GSI_SIZE])?;
+    let subtitles = get_subtitles(&data[GSI_SIZE..], &gsi);
+
+    let mut ass_file = AssFile::from_events(Events::from_dialogues(Vec::new()));
+    let play_res_y: i64 = ass_file.components.script.playresy.as_deref()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(288);
+    let row_height = play_res_y as f64 / gsi.rows as f64;
+
+    let mut dialogues = Vec::new();
+    for subtitle in subtitles {
+        let (text, rows_used) = decode_text(&subtitle.text, gsi.code_table);
+        if text.is_empty() {
+            continue;
+        }
+
+        let top = subtitle.vertical_position * 2 <= gsi.rows;
+        let (style, marginv, alignment) = if top {
+            ("Top", (subtitle.vertical_position - 1).max(0), [7, 8, 9])
+        } else {
+            ("Default", (gsi.rows - (subtitle.vertical_position + rows_used - 1)).max(0), [1, 2, 3])
+        };
+        let override_code = match subtitle.justification {
+            1 => format!("{{\\an{}}}", alignment[0]),
+            3 => format!("{{\\an{}}}", alignment[2]),
+            _ => String::new(),
+        };
+
+        dialogues.push(Dialogue::default()
+            .set_start(&format_time(subtitle.start))
+            .set_end(&format_time(subtitle.end))
+            .set_style(style)
+            .set_marginv(&((marginv as f64 * row_height).round() as i64).to_string())
+            .set_text(&(override_code + &text)));
+    }
+
+    let mut top_style = V4Format::default();
+    top_style.set_name("Top").set_alignment("8");
+    ass_file.components.v4 = V4Styles {
+        styles: vec![V4Format::default(), top_style],
+    };
+    ass_file.components.events = Events::from_dialogues(dialogues);
+    if !gsi.title.is_empty() {
+        ass_file.components.script.set_field("Title", &gsi.title);
+    }
+
+    Ok(ass_file)
+}
+
+fn parse_gsi(gsi: &[u8]) -> std::io::Result<GeneralSubtitleInformation> {
+    let field = |start: usize, end: usize| -> String {
+        gsi[start..end].iter()
+            .map(|byte| if byte.is_ascii() { *byte as char } else { ' ' })
+            .collect::<String>()
+            .trim()
+            .to_string()
+    };
+
+    let frame_rate = match field(3, 11).as_str() {
+        "STL25.01" => 25,
+        "STL30.01" => 30,
+        disk_format => return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("unknown EBU STL disk format code {:?}", disk_format),
+        )),
+    };
+    let code_table = match field(12, 14).as_str() {
+        "01" => CodeTable::Cyrillic,
+        "02" => CodeTable::Arabic,
+        "03" => CodeTable::Greek,
+        "04" => CodeTable::Hebrew,
+        _ => CodeTable::Latin,
+    };
+    let rows = field(253, 255).parse().ok().filter(|rows| *rows > 0).unwrap_or(23);
+    let programme_start = parse_timecode_digits(&field(256, 264), frame_rate).unwrap_or(0);
+
+    Ok(GeneralSubtitleInformation {
+        frame_rate,
+        code_table,
+        rows,
+        programme_start,
+        title: field(16, 48),
+    })
+}
+
+/// Parse an `HHMMSSFF` timecode from the GSI block into milliseconds.
+fn parse_timecode_digits(timecode: &str, frame_rate: i64) -> Option<i64> {
+    if timecode.len() != 8 {
+        return None;
+    }
+    let part = |i: usize| timecode.get(i..i + 2).and_then(|digits| digits.parse::<i64>().ok());
+    Some(timecode_to_ms(part(0)?, part(2)?, part(4)?, part(6)?, frame_rate))
+}
+
+fn timecode_to_ms(hours: i64, minutes: i64, seconds: i64, frames: i64, frame_rate: i64) -> i64 {
+    ((hours * 60 + minutes) * 60 + seconds) * 1000 + frames * 1000 / frame_rate
+}
+
+fn get_subtitles(tti_blocks: &[u8], gsi: &GeneralSubtitleInformation) -> Vec<Subtitle> {
+    let mut subtitles: Vec<Subtitle> = Vec::new();
+    // the subtitle number of the previous block when it has more extension blocks
+    let mut continued_number: Option<u16> = None;
+
+    for block in tti_blocks.chunks_exact(TTI_SIZE) {
+        let number = u16::from_le_bytes([block[1], block[2]]);
+        let extension_block = block[3];
+        if extension_block == USER_DATA_BLOCK {
+            continue;
+        }
+        let comment = block[15] == 1;
+        let continuation = continued_number == Some(number);
+        continued_number = (extension_block != LAST_EXTENSION_BLOCK).then_some(number);
+        if comment {
+            continue;
+        }
+
+        let text = &block[16..TTI_SIZE];
+        if continuation {
+            if let Some(subtitle) = subtitles.last_mut().filter(|subtitle| subtitle.number == number) {
+                while subtitle.text.last() == Some(&0x8f) {
+                    subtitle.text.pop();
+                }
+                subtitle.text.extend_from_slice(text);
+                continue;
+            }
+        }
+
+        let timecode = |offset: usize| {
+            let ms = timecode_to_ms(block[offset] as i64, block[offset + 1] as i64, block[offset + 2] as i64, block[offset + 3] as i64, gsi.frame_rate);
+            if ms >= gsi.programme_start { ms - gsi.programme_start } else { ms }
+        };
+
+        subtitles.push(Subtitle {
+            number,
+            start: timecode(5),
+            end: timecode(9),
+            vertical_position: block[13] as i64,
+            justification: block[14],
+            text: text.to_vec(),
+        });
+    }
+
+    subtitles
+}
+
+/// The display state set by the control codes of the text field.
+#[derive(Clone, Copy, PartialEq)]
+struct TextState {
+    colour: u8,
+    double_height: bool,
+    italic: bool,
+    underline: bool,
+}
+
+impl TextState {
+    fn override_code(&self, previous: &TextState) -> String {
+        let mut code = String::new();
+        if self.colour != previous.colour {
+            code.push_str(&format!("\\c&H{}&", TELETEXT_COLOURS[self.colour as usize]));
+        }
+        if self.double_height != previous.double_height {
+            code.push_str(if self.double_height { "\\fscy200" } else { "\\fscy100" });
+        }
+        if self.italic != previous.italic {
+            code.push_str(if self.italic { "\\i1" } else { "\\i0" });
+        }
+        if self.underline != previous.underline {
+            code.push_str(if self.underline { "\\u1" } else { "\\u0" });
+        }
+        code
+    }
+}
+
+/// Decode a TTI text field into dialogue text. Returns the text and the number of teletext rows it
+/// takes up, counting double height rows twice.
+fn decode_text(field: &[u8], code_table: CodeTable) -> (String, i64) {
+    let default_state = TextState {
+        colour: WHITE,
+        double_height: false,
+        italic: false,
+        underline: false,
+    };
+    let mut lines: Vec<String> = Vec::new();
+    let mut rows_used = 0;
+    let mut line = String::new();
+    let mut state = default_state;
+    let mut written_state = default_state;
+    let mut line_double_height = false;
+    let mut diacritic: Option<u8> = None;
+
+    let mut finish_line = |line: &mut String, double_height: bool| {
+        let trimmed = line.trim().to_string();
+        if !trimmed.is_empty() {
+            lines.push(trimmed);
+            rows_used += if double_height { 2 } else { 1 };
+        }
+        line.clear();
+    };
+
+    for byte in field.iter().copied() {
+        match byte {
+            0x00..=0x07 => state.colour = byte,
+            0x0c => state.double_height = false,
+            0x0d => {
+                state.double_height = true;
+                line_double_height = true;
+            },
+            0x80 => state.italic = true,
+            0x81 => state.italic = false,
+            0x82 => state.underline = true,
+            0x83 => state.underline = false,
+            0x8a => {
+                finish_line(&mut line, line_double_height);
+                // teletext resets the colour and height at the start of each row
+                state.colour = WHITE;
+                state.double_height = false;
+                line_double_height = false;
+            },
+            0x8f => break,
+            0xc1..=0xcf if matches!(code_table, CodeTable::Latin) => diacritic = Some(byte),
+            0x20..=0x7f | 0xa0..=0xff => {
+                if state != written_state && byte != b' ' {
+                    line.push_str(&format!("{{{}}}", state.override_code(&written_state)));
+                    written_state = state;
+                }
+                let character = decode_character(byte, code_table);
+                match diacritic.take() {
+                    Some(mark) => line.push_str(&compose(mark, character)),
+                    None => line.push(character),
+                }
+            },
+            _ => {},
+        }
+    }
+    finish_line(&mut line, line_double_height);
+
+    (lines.join("\\N"), rows_used)
+}
+
+fn decode_character(byte: u8, code_table: CodeTable) -> char {
+    if byte < 0x80 {
+        return byte as char;
+    }
+    if byte == 0xa0 {
+        return '\u{a0}';
+    }
+
+    let code = byte as u32;
+    let character = match code_table {
+        CodeTable::Latin => return ISO_6937_UPPER[(byte - 0xa0) as usize],
+        CodeTable::Cyrillic => match byte {
+            0xad => Some('\u{ad}'),
+            0xf0 => Some('№'),
+            0xfd => Some('§'),
+            _ => char::from_u32(code + 0x360),
+        },
+        CodeTable::Arabic => match byte {
+            0xa4 => Some('¤'),
+            0xac => Some('،'),
+            0xad => Some('\u{ad}'),
+            0xbb => Some('؛'),
+            0xbf => Some('؟'),
+            0xc1..=0xda | 0xe0..=0xf2 => char::from_u32(code + 0x560),
+            _ => None,
+        },
+        CodeTable::Greek => match byte {
+            0xa1 => Some('‘'),
+            0xa2 => Some('’'),
+            0xa3 => Some('£'),
+            0xa6..=0xa9 | 0xab..=0xad | 0xb0..=0xb3 | 0xb7 | 0xbb | 0xbd => char::from_u32(code),
+            0xaf => Some('―'),
+            0xb4..=0xfe => char::from_u32(code + 0x2d0),
+            _ => None,
+        },
+        CodeTable::Hebrew => match byte {
+            0xaa => Some('×'),
+            0xba => Some('÷'),
+            0xa2..=0xbe => char::from_u32(code),
+            0xdf => Some('‗'),
+            0xe0..=0xfa => char::from_u32(code + 0x4f0),
+            _ => None,
+        },
+    };
+    character.unwrap_or('\u{fffd}')
+}
+
+/// The upper half (`0xA0`-`0xFF`) of the ISO 6937 code table. The diacritical marks at
+/// `0xC1`-`0xCF` are handled by `compose` and are only here to keep the table complete.
+const ISO_6937_UPPER: [char; 96] = [
+    '\u{a0}', '¡', '¢', '£', '$', '¥', '#', '§', '¤', '‘', '“', '«', '←', '↑', '→', '↓',
+    '°', '±', '²', '³', '×', 'µ', '¶', '·', '÷', '’', '”', '»', '¼', '½', '¾', '¿',
+    '\u{fffd}', '\u{300}', '\u{301}', '\u{302}', '\u{303}', '\u{304}', '\u{306}', '\u{307}', '\u{308}', '\u{fffd}', '\u{30a}', '\u{327}', '\u{fffd}', '\u{30b}', '\u{328}', '\u{30c}',
+    '―', '¹', '®', '©', '™', '♪', '¬', '¦', '\u{fffd}', '\u{fffd}', '\u{fffd}', '\u{fffd}', '⅛', '⅜', '⅝', '⅞',
+    'Ω', 'Æ', 'Đ', 'ª', 'Ħ', '\u{fffd}', 'Ĳ', 'Ŀ', 'Ł', 'Ø', 'Œ', 'º', 'Þ', 'Ŧ', 'Ŋ', 'ŉ',
+    'ĸ', 'æ', 'đ', 'ð', 'ħ', 'ı', 'ĳ', 'ŀ', 'ł', 'ø', 'œ', 'ß', 'þ', 'ŧ', 'ŋ', '\u{ad}',
+];
+
+/// Letters which have a precomposed form for each ISO 6937 diacritical mark.
+const COMPOSITIONS: [(u8, &str, &str); 13] = [
+    (0xc1, "AEIOUaeiou", "ÀÈÌÒÙàèìòù"),
+    (0xc2, "AEIOUYaeiouyCcNnSsZzLlRr", "ÁÉÍÓÚÝáéíóúýĆćŃńŚśŹźĹĺŔŕ"),
+    (0xc3, "AEIOUaeiouCcGgHhJjSsWwYy", "ÂÊÎÔÛâêîôûĈĉĜĝĤĥĴĵŜŝŴŵŶŷ"),
+    (0xc4, "ANOanoIiUu", "ÃÑÕãñõĨĩŨũ"),
+    (0xc5, "AEIOUaeiou", "ĀĒĪŌŪāēīōū"),
+    (0xc6, "AaGgUu", "ĂăĞğŬŭ"),
+    (0xc7, "CcEeGgIZz", "ĊċĖėĠġİŻż"),
+    (0xc8, "AEIOUaeiouyY", "ÄËÏÖÜäëïöüÿŸ"),
+    (0xca, "AaUu", "ÅåŮů"),
+    (0xcb, "CcGKkLlNnRrSsTt", "ÇçĢĶķĻļŅņŖŗŞşŢţ"),
+    (0xcd, "OoUu", "ŐőŰű"),
+    (0xce, "AaEeIiUu", "ĄąĘęĮįŲų"),
+    (0xcf, "CcDdEeLlNnRrSsTtZz", "ČčĎďĚěĽľŇňŘřŠšŤťŽž"),
+];
+
+/// Combine an ISO 6937 diacritical mark with the letter that follows it.
+/// Falls back to the letter followed by the Unicode combining mark.
+fn compose(mark: u8, letter: char) -> String {
+    let composed = COMPOSITIONS.iter()
+        .find(|(code, _, _)| *code == mark)
+        .and_then(|(_, letters, composed)| {
+            letters.chars().position(|c| c == letter).and_then(|i| composed.chars().nth(i))
+        });
+
+    match composed {
+        Some(composed) => composed.to_string(),
+        None => format!("{}{}", letter, ISO_6937_UPPER[(mark - 0xa0) as usize]),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tti(number: u16, start: [u8; 4], end: [u8; 4], vertical_position: u8, justification: u8, text: &[u8]) -> Vec<u8> {
+        let mut block = vec![0u8; TTI_SIZE];
+        block[1..3].copy_from_slice(&number.to_le_bytes());
+        block[3] = LAST_EXTENSION_BLOCK;
+        block[5..9].copy_from_slice(&start);
+        block[9..13].copy_from_slice(&end);
+        block[13] = vertical_position;
+        block[14] = justification;
+        block[16..].fill(0x8f);
+        block[16..16 + text.len()].copy_from_slice(text);
+        block
+    }
+
+    fn stl_file(blocks: &[Vec<u8>]) -> Vec<u8> {
+        let mut gsi = vec![b' '; GSI_SIZE];
+        gsi[0..3].copy_from_slice(b"850");
+        gsi[3..11].copy_from_slice(b"STL25.01");
+        gsi[12..14].copy_from_slice(b"00");
+        gsi[16..23].copy_from_slice(b"Rap God");
+        gsi[253..255].copy_from_slice(b"23");
+        gsi[256..264].copy_from_slice(b"10000000");
+        blocks.iter().fold(gsi, |mut file, block| {
+            file.extend_from_slice(block);
+            file
+        })
+    }
+
+    #[test]
+    fn test_parse_stl() {
+        let data = stl_file(&[
+            tti(1, [10, 0, 1, 12], [10, 0, 4, 0], 20, 2, b"\x0dCaf\xc2e\x8a\x03Look!"),
+            tti(2, [10, 0, 5, 0], [10, 0, 6, 0], 1, 1, b"\x80Top\x81 line"),
+        ]);
+        let ass_file = parse_stl(&data).unwrap();
+        let dialogues: Vec<&Dialogue> = ass_file.components.events.iter().collect();
+
+        assert_eq!(Some("Rap God"), ass_file.components.script.get_field("Title"));
+        assert_eq!(2, dialogues.len());
+        assert_eq!(Some("0:00:01.48"), dialogues[0].get_start());
+        assert_eq!(Some("0:00:04.00"), dialogues[0].get_end());
+        assert_eq!(Some("{\\fscy200}Café\\N{\\c&H00FFFF&\\fscy100}Look!"), dialogues[0].get_text());
+        assert_eq!(Some("Default"), dialogues[0].get_style());
+        assert_eq!(Some("{\\an7}{\\i1}Top {\\i0}line"), dialogues[1].get_text());
+        assert_eq!(Some("Top"), dialogues[1].get_style());
+        assert_eq!(Some("0"), dialogues[1].get_marginv());
+    }
+
+    #[test]
+    fn test_extension_blocks() {
+        let mut first = tti(1, [10, 0, 1, 0], [10, 0, 2, 0], 20, 2, b"Look,");
+        first[3] = 0;
+        let mut user_data = tti(1, [0; 4], [0; 4], 0, 0, b"user data");
+        user_data[3] = USER_DATA_BLOCK;
+        let mut unfinished = tti(2, [10, 0, 3, 0], [10, 0, 4, 0], 20, 2, b"Rap");
+        unfinished[3] = 0;
+        let data = stl_file(&[
+            first,
+            user_data,
+            tti(1, [10, 0, 1, 0], [10, 0, 2, 0], 20, 2, b" I was"),
+            unfinished,
+            tti(3, [10, 0, 5, 0], [10, 0, 6, 0], 20, 2, b"God"),
+        ]);
+        let ass_file = parse_stl(&data).unwrap();
+        let texts: Vec<&str> = ass_file.components.events.iter().map(|dialogue| dialogue.get_text().unwrap()).collect();
+
+        assert_eq!(vec!["Look, I was", "Rap", "God"], texts);
+    }
+
+    #[test]
+    fn test_parse_stl_wrong() {
+        let result = parse_stl(b"not an stl file").map_err(|e| e.kind());
+        assert_eq!(Err(std::io::ErrorKind::InvalidData), result);
+    }
+}
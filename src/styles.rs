@@ -0,0 +1,225 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::AssFile;
+use crate::text::{replace_reset_style, reset_styles};
+
+/// Why `AssFile::rename_style` could not rename a style.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RenameStyleError {
+    /// There is no style with the old name.
+    NotFound,
+    /// The new name is empty or contains a comma, which would break the `Style:` line.
+    InvalidName,
+    /// A style with the new name already exists.
+    AlreadyExists,
+}
+
+impl fmt::Display for RenameStyleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RenameStyleError::NotFound => write!(f, "The style is not found in the V4+ Styles."),
+            RenameStyleError::InvalidName => write!(f, "Style names can't be empty or contain commas."),
+            RenameStyleError::AlreadyExists => write!(f, "A style with the new name already exists."),
+        }
+    }
+}
+
+impl std::error::Error for RenameStyleError {}
+
+impl AssFile {
+    /// Map each style name to the indices of the dialogues which use it, either as their style or
+    /// in a `\r<style>` reset tag. Unused styles are included with no dialogues, and so are styles
+    /// which are used but not defined.
+    ///
+    /// # Example
+    /// ```rust
+    /// use ass_parser::{AssFile, Dialogue, Events};
+    ///
+    /// let mut ass_file = AssFile::new();
+    /// ass_file.components.events.set_events(Events::default())
+    ///     .add_dialogue(Dialogue::default().set_text("{\\rSigns}Rap God"));
+    ///
+    /// let usage = ass_file.style_usage();
+    /// assert_eq!(Some(&vec![0, 1]), usage.get("Default"));
+    /// assert_eq!(Some(&vec![1]), usage.get("Signs"));
+    /// ```
+    pub fn style_usage(&self) -> BTreeMap<String, Vec<usize>> {
+        let mut usage: BTreeMap<String, Vec<usize>> = self.components.v4.iter()
+            .filter_map(|style| style.get_name())
+            .map(|name| (name.to_string(), Vec::new()))
+            .collect();
+
+        for (i, dialogue) in self.components.events.iter().enumerate() {
+            let mut styles = reset_styles(dialogue.get_text().unwrap_or(""));
+            styles.extend(dialogue.get_style());
+            for style in styles {
+                let events = usage.entry(style.to_string()).or_default();
+                if events.last() != Some(&i) {
+                    events.push(i);
+                }
+            }
+        }
+
+        usage
+    }
+
+    /// Map each style which is used by a dialogue but not defined in `[V4+ Styles]` to the
+    /// indices of the dialogues which use it.
+    pub fn missing_styles(&self) -> BTreeMap<String, Vec<usize>> {
+        self.style_usage().into_iter()
+            .filter(|(name, _)| self.components.v4.get_style(name).is_none())
+            .collect()
+    }
+
+    /// Remove the styles which no dialogue uses and return their names.
+    /// One style is always kept, even if no style is used.
+    pub fn remove_unused_styles(&mut self) -> Vec<String> {
+        let mut removed = Vec::new();
+
+        for (name, events) in self.style_usage() {
+            if events.is_empty() && self.components.v4.remove_style(&name).is_some() {
+                removed.push(name);
+            }
+        }
+
+        removed
+    }
+
+    /// Merge styles with identical properties into the first of them, and change the dialogues
+    /// and `\r` tags which used the others. Returns `(removed, kept)` name pairs.
+    pub fn merge_duplicate_styles(&mut self) -> Vec<(String, String)> {
+        let mut merged = Vec::new();
+        let styles: Vec<_> = self.components.v4.iter().cloned().collect();
+
+        for (i, style) in styles.iter().enumerate() {
+            let Some(name) = style.get_name() else {
+                continue;
+            };
+            let original = styles[..i].iter()
+                .find(|other| other.get_name().is_some() && other.get_name() != Some(name) && style.same_properties(other));
+            let Some(kept) = original.and_then(|original| original.get_name()) else {
+                continue;
+            };
+            if self.components.v4.remove_style(name).is_none() {
+                continue;
+            }
+
+            self.replace_style_references(name, kept);
+            merged.push((name.to_string(), kept.to_string()));
+        }
+
+        merged
+    }
+
+    /// Rename a style and change every dialogue and `\r` tag which uses it to the new name.
+    /// Nothing is changed if the rename fails.
+    ///
+    /// # Example
+    /// ```rust
+    /// use ass_parser::{AssFile, Dialogue, Events, V4Format};
+    ///
+    /// let mut ass_file = AssFile::new();
+    /// ass_file.components.v4.set_v4(V4Format::default());
+    /// ass_file.components.events.set_events(Events::default())
+    ///     .add_dialogue(Dialogue::default().set_text("{\\rDefault}Rap God"));
+    ///
+    /// ass_file.rename_style("Default", "Main").unwrap();
+    /// assert_eq!(Some("Main"), ass_file.components.events.iter().last().unwrap().get_style());
+    /// assert!(ass_file.rename_style("Main", "Main, Top").is_err());
+    /// ```
+    pub fn rename_style(&mut self, old_name: &str, new_name: &str) -> Result<&mut AssFile, RenameStyleError> {
+        let new_name = new_name.trim();
+        if new_name.is_empty() || new_name.contains(',') {
+            return Err(RenameStyleError::InvalidName);
+        }
+        if self.components.v4.get_style(old_name).is_none() {
+            return Err(RenameStyleError::NotFound);
+        }
+        if old_name == new_name {
+            return Ok(self);
+        }
+        if self.components.v4.get_style(new_name).is_some() {
+            return Err(RenameStyleError::AlreadyExists);
+        }
+
+        if let Some(style) = self.components.v4.get_style_mut(old_name) {
+            style.set_name(new_name);
+        }
+        self.replace_style_references(old_name, new_name);
+        Ok(self)
+    }
+
+    /// Change the style of the dialogues and `\r` tags using `from` to `to`.
+    pub(crate) fn replace_style_references(&mut self, from: &str, to: &str) {
+        for dialogue in self.components.events.dialogues.dialogues.iter_mut() {
+            if dialogue.event.style.as_deref() == Some(from) {
+                dialogue.event.style = Some(to.to_string());
+            }
+            if let Some(text) = &dialogue.event.text {
+                if reset_styles(text).contains(&from) {
+                    dialogue.event.text = Some(replace_reset_style(text, from, to));
+                }
+            }
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Dialogue, Events, V4Format};
+
+    fn ass_file() -> AssFile {
+        let mut ass_file = AssFile::from_events(Events::from_dialogues(vec![
+            Dialogue::default().set_style("Default 2").set_text(r"{\rSigns}Look"),
+            Dialogue::default().set_style("Missing"),
+            Dialogue::default().set_text(r"{\rDefault 2}I was"),
+        ]));
+        ass_file.components.v4
+            .add_style(V4Format::default().set_name("Signs").set_fontsize("30").clone())
+            .add_style(V4Format::default().set_name("Unused").set_fontsize("40").clone())
+            .add_style(V4Format::default().set_name("Default 2").clone());
+        ass_file
+    }
+
+    #[test]
+    fn test_style_usage() {
+        let ass_file = ass_file();
+        let usage = ass_file.style_usage();
+
+        assert_eq!(Some(&vec![0, 2]), usage.get("Default 2"));
+        assert_eq!(Some(&vec![0]), usage.get("Signs"));
+        assert_eq!(Some(&vec![]), usage.get("Unused"));
+        assert_eq!(vec!["Missing"], ass_file.missing_styles().keys().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_rename_style() {
+        let mut ass_file = ass_file();
+
+        assert_eq!(Err(RenameStyleError::InvalidName), ass_file.rename_style("Signs", "Signs, Top").map(|_| ()));
+        assert_eq!(Err(RenameStyleError::NotFound), ass_file.rename_style("Missing", "Found").map(|_| ()));
+        assert_eq!(Err(RenameStyleError::AlreadyExists), ass_file.rename_style("Signs", "Unused").map(|_| ()));
+
+        ass_file.rename_style("Default 2", "Main").unwrap();
+        let events: Vec<_> = ass_file.components.events.iter().collect();
+        assert!(ass_file.components.v4.get_style("Main").is_some());
+        assert_eq!(Some("Main"), events[0].get_style());
+        assert_eq!(Some(r"{\rMain}I was"), events[2].get_text());
+    }
+
+    #[test]
+    fn test_clean_up_styles() {
+        let mut ass_file = ass_file();
+
+        assert_eq!(vec!["Unused"], ass_file.remove_unused_styles());
+        assert_eq!(vec![("Default 2".to_string(), "Default".to_string())], ass_file.merge_duplicate_styles());
+        assert_eq!(2, ass_file.components.v4.len());
+
+        let events: Vec<_> = ass_file.components.events.iter().collect();
+        assert_eq!(Some("Default"), events[0].get_style());
+        assert_eq!(Some(r"{\rDefault}I was"), events[2].get_text());
+    }
+}
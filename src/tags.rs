@@ -0,0 +1,322 @@
+//! # Tags
+//!
+//! Typed builders for the common override tags. Each one goes into the leading override block
+//! of the dialogue text and replaces the same tag if it is already there, so calling them again
+//! doesn't pile up blocks. `\pos` and `\move` replace each other, and so do `\fad` and `\fade`.
+//!
+//! ```rust
+//! use ass_parser::Dialogue;
+//! use ass_parser::tags::{Alignment, Tags};
+//!
+//! let dialogue = Dialogue::default()
+//!     .set_text("{\\i1}Rap God")
+//!     .set_alignment(Alignment::Top)
+//!     .set_fade(200, 200)
+//!     .set_pos(640.0, 100.0)
+//!     .set_move((640.0, 100.0), (640.0, 50.0), Some((0, 500)))
+//!     .add_transform(Some((0, 500)), None, &Tags::new().scale(120.0, 120.0).blur(2.0));
+//!
+//! assert_eq!(Some("{\\i1\\an8\\fad(200,200)\\move(640,100,640,50,0,500)\\t(0,500,\\fscx120\\fscy120\\blur2)}Rap God"), dialogue.get_text());
+//! ```
+
+use std::fmt;
+
+use hex_color::HexColor;
+
+use crate::Dialogue;
+use crate::colour::ass_colour;
+use crate::drawing::Drawing;
+use crate::text::{format_number, set_leading_tag};
+
+/// Where the text is anchored, in the layout of a numeric keypad.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Alignment {
+    /// `\an1`
+    BottomLeft,
+    /// `\an2`
+    #[default]
+    Bottom,
+    /// `\an3`
+    BottomRight,
+    /// `\an4`
+    Left,
+    /// `\an5`
+    Center,
+    /// `\an6`
+    Right,
+    /// `\an7`
+    TopLeft,
+    /// `\an8`
+    Top,
+    /// `\an9`
+    TopRight,
+}
+
+impl Alignment {
+    /// The number of the alignment for `\an`.
+    pub fn number(&self) -> u8 {
+        *self as u8 + 1
+    }
+}
+
+/// Override tags which can be animated with `Dialogue::add_transform`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Tags {
+    tags: String,
+}
+
+impl Tags {
+    /// No tags.
+    pub fn new() -> Tags {
+        Tags::default()
+    }
+
+    /// `\c`: the colour of the text.
+    pub fn colour(mut self, colour: HexColor) -> Self {
+        self.tags.push_str(&format!(r"\c{}", ass_colour(colour)));
+        self
+    }
+
+    /// `\3c`: the colour of the outline.
+    pub fn outline_colour(mut self, colour: HexColor) -> Self {
+        self.tags.push_str(&format!(r"\3c{}", ass_colour(colour)));
+        self
+    }
+
+    /// `\fscx` and `\fscy`: the scale of the text in percent.
+    pub fn scale(mut self, x: f64, y: f64) -> Self {
+        self.tags.push_str(&format!(r"\fscx{}\fscy{}", format_number(x), format_number(y)));
+        self
+    }
+
+    /// `\frz`: the rotation in degrees, counterclockwise.
+    pub fn rotation(mut self, degrees: f64) -> Self {
+        self.tags.push_str(&format!(r"\frz{}", format_number(degrees)));
+        self
+    }
+
+    /// `\blur`: how much the edges are blurred.
+    pub fn blur(mut self, strength: f64) -> Self {
+        self.tags.push_str(&format!(r"\blur{}", format_number(strength)));
+        self
+    }
+
+    /// `\bord`: the width of the outline.
+    pub fn border(mut self, width: f64) -> Self {
+        self.tags.push_str(&format!(r"\bord{}", format_number(width)));
+        self
+    }
+
+    /// `\shad`: the distance of the shadow.
+    pub fn shadow(mut self, depth: f64) -> Self {
+        self.tags.push_str(&format!(r"\shad{}", format_number(depth)));
+        self
+    }
+
+    /// `\fs`: the font size.
+    pub fn font_size(mut self, size: f64) -> Self {
+        self.tags.push_str(&format!(r"\fs{}", format_number(size)));
+        self
+    }
+
+    /// `\clip`: only show the text inside the rectangle.
+    pub fn clip(mut self, x1: f64, y1: f64, x2: f64, y2: f64) -> Self {
+        self.tags.push_str(&clip_tag("clip", x1, y1, x2, y2));
+        self
+    }
+}
+
+impl fmt::Display for Tags {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.tags)
+    }
+}
+
+impl Dialogue {
+    /// `\pos`: place the text at a point. Replaces `\move`.
+    pub fn set_pos(self, x: f64, y: f64) -> Self {
+        self.set_tag(&format!(r"\pos({},{})", format_number(x), format_number(y)), &["pos", "move"])
+    }
+
+    /// `\move`: move the text between two points, during the times in milliseconds from the
+    /// start of the dialogue or during the whole dialogue. Replaces `\pos`.
+    pub fn set_move(self, from: (f64, f64), to: (f64, f64), times: Option<(i64, i64)>) -> Self {
+        let mut tag = format!(r"\move({},{},{},{}", format_number(from.0), format_number(from.1), format_number(to.0), format_number(to.1));
+        if let Some((start, end)) = times {
+            tag.push_str(&format!(",{},{}", start, end));
+        }
+        tag.push(')');
+        self.set_tag(&tag, &["pos", "move"])
+    }
+
+    /// `\fad`: fade in and out over the durations in milliseconds. Replaces `\fade`.
+    pub fn set_fade(self, fade_in_ms: i64, fade_out_ms: i64) -> Self {
+        self.set_tag(&format!(r"\fad({},{})", fade_in_ms, fade_out_ms), &["fad", "fade"])
+    }
+
+    /// `\fade`: change the alpha from `alphas.0` to `alphas.1` between the first two times, and
+    /// to `alphas.2` between the last two. Alpha 0 is opaque and 255 is transparent. Replaces
+    /// `\fad`.
+    pub fn set_complex_fade(self, alphas: (u8, u8, u8), times: (i64, i64, i64, i64)) -> Self {
+        let tag = format!(r"\fade({},{},{},{},{},{},{})", alphas.0, alphas.1, alphas.2, times.0, times.1, times.2, times.3);
+        self.set_tag(&tag, &["fad", "fade"])
+    }
+
+    /// `\an`: where the text is anchored.
+    pub fn set_alignment(self, alignment: Alignment) -> Self {
+        self.set_tag(&format!(r"\an{}", alignment.number()), &["an", "a"])
+    }
+
+    /// `\org`: the point the text rotates around.
+    pub fn set_origin(self, x: f64, y: f64) -> Self {
+        self.set_tag(&format!(r"\org({},{})", format_number(x), format_number(y)), &["org"])
+    }
+
+    /// `\frz`: the rotation in degrees, counterclockwise.
+    pub fn set_rotation(self, degrees: f64) -> Self {
+        self.set_tag(&format!(r"\frz{}", format_number(degrees)), &["frz", "fr"])
+    }
+
+    /// `\frx`: the rotation around the horizontal axis in degrees.
+    pub fn set_rotation_x(self, degrees: f64) -> Self {
+        self.set_tag(&format!(r"\frx{}", format_number(degrees)), &["frx"])
+    }
+
+    /// `\fry`: the rotation around the vertical axis in degrees.
+    pub fn set_rotation_y(self, degrees: f64) -> Self {
+        self.set_tag(&format!(r"\fry{}", format_number(degrees)), &["fry"])
+    }
+
+    /// `\fscx` and `\fscy`: the scale of the text in percent.
+    pub fn set_scale(self, x: f64, y: f64) -> Self {
+        self.set_tag(&format!(r"\fscx{}", format_number(x)), &["fscx"])
+            .set_tag(&format!(r"\fscy{}", format_number(y)), &["fscy"])
+    }
+
+    /// `\blur`: how much the edges are blurred.
+    pub fn set_blur(self, strength: f64) -> Self {
+        self.set_tag(&format!(r"\blur{}", format_number(strength)), &["blur"])
+    }
+
+    /// `\bord`: the width of the outline.
+    pub fn set_border(self, width: f64) -> Self {
+        self.set_tag(&format!(r"\bord{}", format_number(width)), &["bord"])
+    }
+
+    /// `\shad`: the distance of the shadow.
+    pub fn set_shadow(self, depth: f64) -> Self {
+        self.set_tag(&format!(r"\shad{}", format_number(depth)), &["shad"])
+    }
+
+    /// `\fn`: the font name.
+    pub fn set_font_name(self, name: &str) -> Self {
+        self.set_tag(&format!(r"\fn{}", name), &["fn"])
+    }
+
+    /// `\fs`: the font size.
+    pub fn set_font_size(self, size: f64) -> Self {
+        self.set_tag(&format!(r"\fs{}", format_number(size)), &["fs"])
+    }
+
+    /// `\b`: bold text.
+    pub fn set_bold(self, bold: bool) -> Self {
+        self.set_tag(&format!(r"\b{}", bold as u8), &["b"])
+    }
+
+    /// `\i`: italic text.
+    pub fn set_italic(self, italic: bool) -> Self {
+        self.set_tag(&format!(r"\i{}", italic as u8), &["i"])
+    }
+
+    /// `\clip`: only show the text inside the rectangle. Replaces `\iclip`.
+    pub fn set_clip(self, x1: f64, y1: f64, x2: f64, y2: f64) -> Self {
+        self.set_tag(&clip_tag("clip", x1, y1, x2, y2), &["clip", "iclip"])
+    }
+
+    /// `\iclip`: only show the text outside the rectangle. Replaces `\clip`.
+    pub fn set_inverse_clip(self, x1: f64, y1: f64, x2: f64, y2: f64) -> Self {
+        self.set_tag(&clip_tag("iclip", x1, y1, x2, y2), &["clip", "iclip"])
+    }
+
+    /// `\clip`: only show the text inside the drawing. Replaces `\iclip`.
+    pub fn set_vector_clip(self, drawing: &Drawing) -> Self {
+        self.set_tag(&format!(r"\clip({})", drawing), &["clip", "iclip"])
+    }
+
+    /// `\iclip`: only show the text outside the drawing. Replaces `\clip`.
+    pub fn set_vector_inverse_clip(self, drawing: &Drawing) -> Self {
+        self.set_tag(&format!(r"\iclip({})", drawing), &["clip", "iclip"])
+    }
+
+    /// `\t`: animate the tags, during the times in milliseconds from the start of the dialogue or
+    /// during the whole dialogue. `acceleration` above 1 starts slow and ends fast. Transforms are
+    /// added after the ones already in the leading block.
+    pub fn add_transform(self, times: Option<(i64, i64)>, acceleration: Option<f64>, tags: &Tags) -> Self {
+        let mut arguments: Vec<String> = Vec::new();
+        if let Some((start, end)) = times {
+            arguments.extend([start.to_string(), end.to_string()]);
+        }
+        if let Some(acceleration) = acceleration {
+            arguments.push(format_number(acceleration));
+        }
+        arguments.push(tags.to_string());
+        self.set_tag(&format!(r"\t({})", arguments.join(",")), &[])
+    }
+
+    fn set_tag(self, tag: &str, replaces: &[&str]) -> Self {
+        let text = set_leading_tag(self.get_text().unwrap_or(""), tag, replaces);
+        self.set_text(&text)
+    }
+}
+
+fn clip_tag(name: &str, x1: f64, y1: f64, x2: f64, y2: f64) -> String {
+    format!(r"\{}({},{},{},{})", name, format_number(x1), format_number(y1), format_number(x2), format_number(y2))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_tags() {
+        let dialogue = Dialogue::default()
+            .set_text(r"{\an8\move(0,0,10,10)}{\b1}Look{\pos(1,1)}")
+            .set_pos(5.5, 10.0)
+            .set_alignment(Alignment::BottomLeft)
+            .set_complex_fade((255, 0, 255), (0, 100, 900, 1000))
+            .set_fade(100, 100)
+            .set_bold(false)
+            .set_scale(110.0, 90.0)
+            .set_scale(120.0, 80.0)
+            .set_font_name("Arial Black")
+            .set_inverse_clip(0.0, 0.0, 100.0, 50.0)
+            .set_clip(0.0, 0.0, 200.0, 50.0);
+
+        assert_eq!(Some(r"{\an1\pos(5.5,10)}{\b0\fad(100,100)\fscx120\fscy80\fnArial Black\clip(0,0,200,50)}Look{\pos(1,1)}"), dialogue.get_text());
+    }
+
+    #[test]
+    fn test_transforms() {
+        let dialogue = Dialogue::default()
+            .set_text("Rap God")
+            .set_origin(320.0, 240.0)
+            .set_rotation(15.0)
+            .add_transform(None, Some(0.5), &Tags::new().rotation(0.0).colour(HexColor::RED))
+            .add_transform(Some((500, 1000)), None, &Tags::new().border(3.0).clip(0.0, 0.0, 1.0, 1.0));
+
+        assert_eq!(Some(r"{\org(320,240)\frz15\t(0.5,\frz0\c&H0000FF&)\t(500,1000,\bord3\clip(0,0,1,1))}Rap God"), dialogue.get_text());
+        assert_eq!(5, Alignment::Center.number());
+    }
+
+    #[test]
+    fn test_vector_clip() {
+        let drawing = Drawing::parse("m 0 0 l 100 0 100 100");
+        let dialogue = Dialogue::default()
+            .set_text(r"{\clip(0,0,10,10)}Rap God")
+            .set_vector_inverse_clip(&drawing)
+            .set_vector_clip(&drawing);
+
+        assert_eq!(Some(r"{\clip(m 0 0 l 100 0 100 100)}Rap God"), dialogue.get_text());
+    }
+}
@@ -0,0 +1,584 @@
+//! # Template
+//!
+//! A karaoke templater which generates effect lines from template lines, in the same way as the
+//! kara-templater of Aegisub.
+//!
+//! Template lines are `Comment` events whose Effect is `template syl`, `template char` or
+//! `template line`. They apply to the karaoke lines with the same style, which are the
+//! `Dialogue` events with an empty Effect. Each karaoke line is turned into a `Comment` with the
+//! `karaoke` effect, and the generated lines are `Dialogue` events with the `fx` effect, so running
+//! the templates again removes the old generated lines first.
+//!
+//! - `template syl` makes a line for each syllable, with the expanded template before the
+//!   syllable text.
+//! - `template char` makes a line for each character.
+//! - `template line` makes one line, with the template expanded before each syllable.
+//!
+//! Blank syllables and characters are skipped by `syl` and `char` templates. The generated lines
+//! keep the times of the karaoke line and use the layer of the template.
+//!
+//! ## Variables
+//!
+//! `$name` in a template is replaced by a number, and `!expression!` is replaced by the result of
+//! the arithmetic inside it, such as `!$start - 150!`. Times are in milliseconds and positions in
+//! script pixels.
+//!
+//! | Variable | Value |
+//! |---|---|
+//! | `$lstart`, `$lend`, `$ldur`, `$lmid` | Start, end, duration and middle of the line. |
+//! | `$lleft`, `$lcenter`, `$lright`, `$lwidth` | Horizontal position and width of the line. |
+//! | `$ltop`, `$lmiddle`, `$lbottom`, `$lheight` | Vertical position and height of the line. |
+//! | `$lx`, `$ly` | Position of the line for `\pos`, following its alignment. |
+//! | `$sstart`, `$send`, `$sdur`, `$smid` | Start, end, duration and middle of the syllable, from the start of the line. |
+//! | `$kdur` | Duration of the syllable in centiseconds. |
+//! | `$si`, `$syln` | Index of the syllable from 1, and the number of syllables. |
+//! | `$sleft`, `$scenter`, `$sright`, `$swidth`, `$sx`, `$sy` | Position and width of the syllable. |
+//! | `$ci`, `$cleft`, `$ccenter`, `$cright`, `$cwidth`, `$cx` | Index from 1, position and width of the character in `char` templates. |
+//! | `$start`, `$end`, `$dur`, `$mid`, `$i`, `$left`, `$center`, `$right`, `$width`, `$x`, `$y` | The same for the syllable, or the character in `char` templates. |
+//!
+//! ```rust
+//! use ass_parser::{AssFile, Dialogue, EventKind, Events};
+//! use ass_parser::query::Query;
+//!
+//! let mut ass_file = AssFile::new();
+//! ass_file.components.events.set_events(Events::default())
+//!     .add_dialogue(Dialogue::default()
+//!         .set_kind(EventKind::Comment)
+//!         .set_effect("template syl")
+//!         .set_text("{\\an5\\pos($x,$y)\\t($sstart,$send,\\fscx120)\\fad(!$sstart - 100!,0)}"))
+//!     .add_dialogue(Dialogue::default()
+//!         .set_start("0:00:01.00")
+//!         .set_end("0:00:03.00")
+//!         .set_text("{\\k50}Rap {\\k50}God"));
+//!
+//! assert_eq!(2, ass_file.apply_templates().unwrap());
+//! let fx = ass_file.components.events.iter().last().unwrap();
+//! assert_eq!(Some("fx"), fx.get_effect());
+//!
+//! // running the templates again replaces the generated lines
+//! assert_eq!(2, ass_file.apply_templates().unwrap());
+//! assert_eq!(2, ass_file.components.events.select(&Query::new().effect("fx")).len());
+//! ```
+
+use std::fmt;
+
+use crate::{AssFile, Dialogue, EventKind, V4Format};
+use crate::karaoke::Karaoke;
+use crate::text::{format_number, parse_number, strip_tags};
+
+const TEMPLATE_EFFECT: &str = "template";
+const KARAOKE_EFFECT: &str = "karaoke";
+const FX_EFFECT: &str = "fx";
+
+/// An expression in a template which can't be evaluated.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TemplateError {
+    /// Index of the template line.
+    pub event: usize,
+    /// The expression between the `!` marks, after the variables were replaced.
+    pub expression: String,
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Can't evaluate !{}! in the template of event {}.", self.expression, self.event)
+    }
+}
+
+impl std::error::Error for TemplateError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TemplateKind {
+    Line,
+    Syl,
+    Char,
+}
+
+struct Template {
+    event: usize,
+    kind: TemplateKind,
+    style: String,
+    layer: Option<String>,
+    text: String,
+}
+
+type Variables = Vec<(&'static str, f64)>;
+
+impl AssFile {
+    /// Generate the effect lines of the template lines, measuring the text with
+    /// `estimate_width`. Returns the number of generated lines.
+    pub fn apply_templates(&mut self) -> Result<usize, TemplateError> {
+        self.apply_templates_with(estimate_width)
+    }
+
+    /// Generate the effect lines of the template lines, with `measure` giving the width of a text
+    /// in a style in script pixels. Returns the number of generated lines.
+    /// The events are only changed when every template could be expanded.
+    pub fn apply_templates_with(&mut self, measure: impl Fn(&V4Format, &str) -> f64) -> Result<usize, TemplateError> {
+        let mut dialogues = self.components.events.dialogues.dialogues.clone();
+        remove_generated_lines(&mut dialogues);
+
+        let templates: Vec<Template> = dialogues.iter().enumerate()
+            .filter(|(_, dialogue)| dialogue.is_comment())
+            .filter_map(|(event, dialogue)| {
+                let mut words = dialogue.get_effect()?.split_whitespace();
+                if words.next() != Some(TEMPLATE_EFFECT) {
+                    return None;
+                }
+                let kind = match words.next() {
+                    Some("line") => TemplateKind::Line,
+                    Some("char") => TemplateKind::Char,
+                    _ => TemplateKind::Syl,
+                };
+                Some(Template {
+                    event,
+                    kind,
+                    style: dialogue.get_style().unwrap_or("").to_string(),
+                    layer: dialogue.event.layer.clone(),
+                    text: dialogue.get_text().unwrap_or("").to_string(),
+                })
+            })
+            .collect();
+
+        let mut generated = Vec::new();
+        for dialogue in dialogues.iter_mut() {
+            let style = dialogue.get_style().unwrap_or("");
+            let is_karaoke_line = !dialogue.is_comment() && dialogue.get_effect().unwrap_or("").is_empty();
+            let templates: Vec<&Template> = templates.iter().filter(|template| template.style == style).collect();
+            if !is_karaoke_line || templates.is_empty() {
+                continue;
+            }
+
+            let style = self.components.v4.get_style(style).cloned().unwrap_or_default();
+            let line = Line::new(dialogue, &style, self.components.script.play_res(), &measure);
+            for template in templates {
+                for text in line.expand(template, &style, &measure)? {
+                    let mut fx = dialogue.clone().set_effect(FX_EFFECT).set_text(&text);
+                    fx.event.layer = template.layer.clone();
+                    generated.push(fx);
+                }
+            }
+
+            dialogue.event.kind = EventKind::Comment;
+            dialogue.event.effect = Some(KARAOKE_EFFECT.to_string());
+        }
+
+        let count = generated.len();
+        dialogues.append(&mut generated);
+        self.components.events.dialogues.dialogues = dialogues;
+        Ok(count)
+    }
+
+    /// Remove the lines generated by the templates and turn the karaoke lines back into
+    /// dialogues. Returns the number of removed lines.
+    pub fn remove_generated_lines(&mut self) -> usize {
+        remove_generated_lines(&mut self.components.events.dialogues.dialogues)
+    }
+}
+
+fn remove_generated_lines(dialogues: &mut Vec<Dialogue>) -> usize {
+    let before = dialogues.len();
+    dialogues.retain(|dialogue| dialogue.is_comment() || dialogue.get_effect() != Some(FX_EFFECT));
+
+    for dialogue in dialogues.iter_mut() {
+        if dialogue.is_comment() && dialogue.get_effect() == Some(KARAOKE_EFFECT) {
+            dialogue.event.kind = EventKind::Dialogue;
+            dialogue.event.effect = Some(String::new());
+        }
+    }
+    before - dialogues.len()
+}
+
+/// A rough width of a text in a style, for when the font isn't available: half the font size
+/// for each character, or the whole font size for wide characters such as CJK.
+pub fn estimate_width(style: &V4Format, text: &str) -> f64 {
+    let fontsize = parse_number(style.get_fontsize(), 20.0);
+    let scale_x = parse_number(style.get_scalex(), 100.0);
+    let spacing = parse_number(style.get_spacing(), 0.0);
+
+    text.chars().map(|c| estimate_char_width(c, fontsize, scale_x, spacing)).sum()
+}
+
+/// The estimated width of a character at a font size, with `scale_x` in percent.
+pub(crate) fn estimate_char_width(c: char, size: f64, scale_x: f64, spacing: f64) -> f64 {
+    match c >= '\u{2E80}' {
+        true => size * scale_x / 100.0 + spacing,
+        false => size * scale_x / 100.0 * 0.5 + spacing,
+    }
+}
+
+impl Dialogue {
+    /// The left, right and vertical margins, taken from the style where the dialogue's are 0.
+    pub(crate) fn margins(&self, style: &V4Format) -> (f64, f64, f64) {
+        let margin = |dialogue_margin: Option<&str>, style_margin: Option<&str>| {
+            let margin = parse_number(dialogue_margin, 0.0);
+            match margin != 0.0 {
+                true => margin,
+                false => parse_number(style_margin, 0.0),
+            }
+        };
+        (
+            margin(self.get_marginl(), style.get_marginl()),
+            margin(self.get_marginr(), style.get_marginr()),
+            margin(self.get_marginv(), style.get_marginv()),
+        )
+    }
+}
+
+/// The karaoke line with the layout of its syllables.
+struct Line {
+    karaoke: Karaoke,
+    variables: Variables,
+    /// `(left, width)` of each syllable, without the spaces around it.
+    syllables: Vec<(f64, f64)>,
+    alignment: i64,
+    top: f64,
+    height: f64,
+}
+
+impl Line {
+    fn new(dialogue: &Dialogue, style: &V4Format, (play_res_x, play_res_y): (f64, f64), measure: &impl Fn(&V4Format, &str) -> f64) -> Line {
+        let start = dialogue.get_start_ms().unwrap_or(0);
+        let end = dialogue.get_end_ms().unwrap_or(start);
+        let karaoke = dialogue.get_karaoke()
+            .unwrap_or_else(|| Karaoke::from_words(start, &[(dialogue.get_text().unwrap_or(""), end - start)]));
+
+        let (margin_l, margin_r, margin_v) = dialogue.margins(style);
+        let alignment = parse_number(style.get_alignment(), 2.0) as i64;
+
+        let mut left = measure(style, &strip_tags(&karaoke.prefix));
+        let mut syllables = Vec::new();
+        for syllable in &karaoke.syllables {
+            let text = syllable.plain_text();
+            let trimmed = text.trim_start();
+            let pre_space = measure(style, &text[..text.len() - trimmed.len()]);
+            let width = measure(style, trimmed.trim_end());
+            syllables.push((left + pre_space, width));
+            left += measure(style, &text);
+        }
+        let width = left;
+        let height = parse_number(style.get_fontsize(), 20.0) * parse_number(style.get_scaley(), 100.0) / 100.0;
+
+        let line_left = match alignment % 3 {
+            1 => margin_l,
+            0 => play_res_x - margin_r - width,
+            _ => margin_l + (play_res_x - margin_l - margin_r - width) / 2.0,
+        };
+        let line_top = match alignment {
+            7..=9 => margin_v,
+            4..=6 => (play_res_y - height) / 2.0,
+            _ => play_res_y - margin_v - height,
+        };
+        for syllable in syllables.iter_mut() {
+            syllable.0 += line_left;
+        }
+
+        let (line_x, line_y) = anchor(alignment, line_left, line_top, width, height);
+        let variables = vec![
+            ("lstart", start as f64),
+            ("lend", end as f64),
+            ("ldur", (end - start) as f64),
+            ("lmid", (start + end) as f64 / 2.0),
+            ("lleft", line_left),
+            ("lcenter", line_left + width / 2.0),
+            ("lright", line_left + width),
+            ("lwidth", width),
+            ("ltop", line_top),
+            ("lmiddle", line_top + height / 2.0),
+            ("lbottom", line_top + height),
+            ("lheight", height),
+            ("lx", line_x),
+            ("ly", line_y),
+            ("syln", karaoke.syllables.len() as f64),
+        ];
+
+        Line { karaoke, variables, syllables, alignment, top: line_top, height }
+    }
+
+    /// The texts of the lines generated by a template.
+    fn expand(&self, template: &Template, style: &V4Format, measure: &impl Fn(&V4Format, &str) -> f64) -> Result<Vec<String>, TemplateError> {
+        let times = self.karaoke.times();
+        let mut texts = Vec::new();
+        let mut line_text = self.karaoke.prefix.clone();
+
+        for (i, syllable) in self.karaoke.syllables.iter().enumerate() {
+            let (left, width) = self.syllables[i];
+            let (start, end) = (times[i].0 - self.karaoke.start_ms, times[i].1 - self.karaoke.start_ms);
+            let mut variables = self.variables.clone();
+            variables.extend([
+                ("sstart", start as f64),
+                ("send", end as f64),
+                ("sdur", (end - start) as f64),
+                ("smid", (start + end) as f64 / 2.0),
+                ("kdur", syllable.duration as f64),
+                ("si", (i + 1) as f64),
+            ]);
+            variables.extend(self.position("s", left, width));
+            let tags = match syllable.tags.is_empty() {
+                true => String::new(),
+                false => format!("{{{}}}", syllable.tags),
+            };
+
+            match template.kind {
+                TemplateKind::Line => {
+                    let variables = with_aliases(variables, "s");
+                    line_text.push_str(&expand_text(&template.text, &variables, template.event)?);
+                    line_text.push_str(&tags);
+                    line_text.push_str(&syllable.text);
+                },
+                TemplateKind::Syl => {
+                    if syllable.plain_text().trim().is_empty() {
+                        continue;
+                    }
+                    let variables = with_aliases(variables, "s");
+                    texts.push(format!("{}{}{}", expand_text(&template.text, &variables, template.event)?, tags, syllable.text.trim()));
+                },
+                TemplateKind::Char => {
+                    let text = syllable.plain_text();
+                    let text = text.trim();
+                    let mut char_left = left;
+                    for (ci, (offset, c)) in text.char_indices().enumerate() {
+                        let char_width = measure(style, &c.to_string());
+                        let char_left_here = char_left;
+                        char_left = left + measure(style, &text[..offset + c.len_utf8()]);
+                        if c.is_whitespace() {
+                            continue;
+                        }
+                        let mut variables = variables.clone();
+                        variables.push(("ci", (ci + 1) as f64));
+                        variables.extend(self.position("c", char_left_here, char_width));
+                        let variables = with_aliases(variables, "c");
+                        texts.push(format!("{}{}{}", expand_text(&template.text, &variables, template.event)?, tags, c));
+                    }
+                },
+            }
+        }
+
+        if template.kind == TemplateKind::Line {
+            texts.push(line_text);
+        }
+        Ok(texts)
+    }
+
+    /// The position variables of a syllable or character, named with the prefix.
+    fn position(&self, prefix: &str, left: f64, width: f64) -> Variables {
+        let (x, y) = anchor(self.alignment, left, self.top, width, self.height);
+        let names: [(&'static str, &'static str); 6] = match prefix {
+            "s" => [("sleft", "left"), ("scenter", "center"), ("sright", "right"), ("swidth", "width"), ("sx", "x"), ("sy", "y")],
+            _ => [("cleft", "left"), ("ccenter", "center"), ("cright", "right"), ("cwidth", "width"), ("cx", "x"), ("cy", "y")],
+        };
+        let values = [left, left + width / 2.0, left + width, width, x, y];
+        names.iter().zip(values).map(|((name, _), value)| (*name, value)).collect()
+    }
+}
+
+/// The point of a box which `\pos` moves for the alignment.
+fn anchor(alignment: i64, left: f64, top: f64, width: f64, height: f64) -> (f64, f64) {
+    let x = match alignment % 3 {
+        1 => left,
+        0 => left + width,
+        _ => left + width / 2.0,
+    };
+    let y = match alignment {
+        7..=9 => top,
+        4..=6 => top + height / 2.0,
+        _ => top + height,
+    };
+    (x, y)
+}
+
+/// Add the short names such as `$start` for the syllable or character variables.
+fn with_aliases(mut variables: Variables, prefix: &str) -> Variables {
+    let aliases: [(&'static str, &'static str); 6] = [("start", "sstart"), ("end", "send"), ("dur", "sdur"), ("mid", "smid"), ("i", "si"), ("y", "sy")];
+    let positions: [(&'static str, &'static str); 5] = match prefix {
+        "s" => [("left", "sleft"), ("center", "scenter"), ("right", "sright"), ("width", "swidth"), ("x", "sx")],
+        _ => [("left", "cleft"), ("center", "ccenter"), ("right", "cright"), ("width", "cwidth"), ("x", "cx")],
+    };
+    let aliases: Vec<(&'static str, f64)> = aliases.iter().chain(positions.iter())
+        .filter_map(|(alias, name)| {
+            let name = match (prefix, *alias) {
+                ("c", "i") => "ci",
+                _ => name,
+            };
+            variables.iter().find(|(variable, _)| *variable == name).map(|(_, value)| (*alias, *value))
+        })
+        .collect();
+    variables.extend(aliases);
+    variables
+}
+
+/// Replace the `$variables` and evaluate the `!expressions!` of a template text.
+fn expand_text(text: &str, variables: &Variables, event: usize) -> Result<String, TemplateError> {
+    let mut replaced = String::new();
+    let mut rest = text;
+    while let Some(dollar) = rest.find('$') {
+        replaced.push_str(&rest[..dollar]);
+        let name_len = rest[dollar + 1..].find(|c: char| !c.is_ascii_alphanumeric()).unwrap_or(rest.len() - dollar - 1);
+        let name = &rest[dollar + 1..dollar + 1 + name_len];
+        match variables.iter().find(|(variable, _)| *variable == name) {
+            Some((_, value)) => replaced.push_str(&format_number(*value)),
+            None => replaced.push_str(&rest[dollar..dollar + 1 + name_len]),
+        }
+        rest = &rest[dollar + 1 + name_len..];
+    }
+    replaced.push_str(rest);
+
+    let mut expanded = String::new();
+    let mut parts = replaced.split('!');
+    expanded.push_str(parts.next().unwrap_or(""));
+    while let Some(expression) = parts.next() {
+        match parts.next() {
+            Some(after) => {
+                let value = evaluate(expression).ok_or_else(|| TemplateError { event, expression: expression.to_string() })?;
+                expanded.push_str(&format_number(value));
+                expanded.push_str(after);
+            },
+            // a lone `!` is kept as it is
+            None => {
+                expanded.push('!');
+                expanded.push_str(expression);
+            },
+        }
+    }
+    Ok(expanded)
+}
+
+/// Evaluate an arithmetic expression with `+`, `-`, `*`, `/`, `%` and parentheses.
+fn evaluate(expression: &str) -> Option<f64> {
+    let tokens: Vec<char> = expression.chars().filter(|c| !c.is_whitespace()).collect();
+    let mut position = 0;
+    let value = evaluate_sum(&tokens, &mut position)?;
+    (position == tokens.len() && value.is_finite()).then_some(value)
+}
+
+fn evaluate_sum(tokens: &[char], position: &mut usize) -> Option<f64> {
+    let mut value = evaluate_product(tokens, position)?;
+    while let Some(operator @ ('+' | '-')) = tokens.get(*position) {
+        *position += 1;
+        let right = evaluate_product(tokens, position)?;
+        value = match operator {
+            '+' => value + right,
+            _ => value - right,
+        };
+    }
+    Some(value)
+}
+
+fn evaluate_product(tokens: &[char], position: &mut usize) -> Option<f64> {
+    let mut value = evaluate_factor(tokens, position)?;
+    while let Some(operator @ ('*' | '/' | '%')) = tokens.get(*position) {
+        *position += 1;
+        let right = evaluate_factor(tokens, position)?;
+        value = match operator {
+            '*' => value * right,
+            '/' => value / right,
+            _ => value % right,
+        };
+    }
+    Some(value)
+}
+
+fn evaluate_factor(tokens: &[char], position: &mut usize) -> Option<f64> {
+    match tokens.get(*position)? {
+        '-' => {
+            *position += 1;
+            Some(-evaluate_factor(tokens, position)?)
+        },
+        '(' => {
+            *position += 1;
+            let value = evaluate_sum(tokens, position)?;
+            (tokens.get(*position) == Some(&')')).then(|| *position += 1)?;
+            Some(value)
+        },
+        _ => {
+            let start = *position;
+            while tokens.get(*position).is_some_and(|c| c.is_ascii_digit() || *c == '.') {
+                *position += 1;
+            }
+            tokens[start..*position].iter().collect::<String>().parse::<f64>().ok()
+        },
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Events;
+
+    fn ass_file(effect: &str, template: &str) -> AssFile {
+        let mut ass_file = AssFile::from_events(Events::from_dialogues(vec![
+            Dialogue::default().set_kind(EventKind::Comment).set_layer("1").set_effect(effect).set_text(template),
+            Dialogue::default().set_start("0:00:01.00").set_end("0:00:03.00").set_text(r"{\k50}Rap {\k100}God"),
+            Dialogue::default().set_start("0:00:04.00").set_end("0:00:05.00").set_style("Signs").set_text(r"{\k50}Look"),
+        ]));
+        ass_file.components.script.set_playresx("640").set_playresy("480");
+        ass_file
+    }
+
+    fn fx_texts(ass_file: &AssFile) -> Vec<&str> {
+        ass_file.components.events.iter()
+            .filter(|dialogue| dialogue.get_effect() == Some("fx"))
+            .map(|dialogue| dialogue.get_text().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_template_syl() {
+        // "Rap " is 32 pixels wide and "God" 24 at the default font size of 16
+        let mut ass_file = ass_file("template syl", r"{\pos($x,$y)\t($start,!$end - 10!,\fscx120)\k$kdur}");
+
+        assert_eq!(2, ass_file.apply_templates().unwrap());
+        assert_eq!(vec![r"{\pos(304,470)\t(0,490,\fscx120)\k50}Rap", r"{\pos(336,470)\t(500,1490,\fscx120)\k100}God"], fx_texts(&ass_file));
+
+        let dialogues: Vec<&Dialogue> = ass_file.components.events.iter().collect();
+        assert_eq!((EventKind::Comment, Some("karaoke")), (dialogues[1].get_kind(), dialogues[1].get_effect()));
+        assert_eq!(EventKind::Dialogue, dialogues[2].get_kind());
+        assert_eq!((Some("1"), Some("0:00:01.00")), (dialogues[3].get_layer(), dialogues[3].get_start()));
+
+        assert_eq!(2, ass_file.apply_templates().unwrap());
+        assert_eq!(5, ass_file.components.events.len());
+        assert_eq!(2, ass_file.remove_generated_lines());
+        assert_eq!(EventKind::Dialogue, ass_file.components.events.iter().nth(1).unwrap().get_kind());
+    }
+
+    #[test]
+    fn test_template_line_and_char() {
+        let mut line = ass_file("template line", r"{\t($sstart,$send,\1c&HFF&)}");
+        assert_eq!(1, line.apply_templates().unwrap());
+        assert_eq!(vec![r"{\t(0,500,\1c&HFF&)}Rap {\t(500,1500,\1c&HFF&)}God"], fx_texts(&line));
+
+        let mut char = ass_file("template char", r"{\pos($x,$ly)\fad($si,$ci)}");
+        assert_eq!(6, char.apply_templates().unwrap());
+        assert_eq!(r"{\pos(296,470)\fad(1,1)}R", fx_texts(&char)[0]);
+        assert_eq!(r"{\pos(344,470)\fad(2,3)}d", fx_texts(&char)[5]);
+    }
+
+    #[test]
+    fn test_template_error() {
+        let mut ass_file = ass_file("template syl", r"{\pos($x,$y)}");
+        assert_eq!(2, ass_file.apply_templates().unwrap());
+        // the Default line expands fine and the Signs line after it fails
+        ass_file.components.events.add_dialogue(Dialogue::default()
+            .set_kind(EventKind::Comment)
+            .set_style("Signs")
+            .set_effect("template syl")
+            .set_text(r"{\fad(!1 +!,0)}"));
+        let before = ass_file.clone();
+
+        let error = ass_file.apply_templates().unwrap_err();
+        assert_eq!("1 +", error.expression);
+        assert_eq!(before, ass_file);
+    }
+
+    #[test]
+    fn test_expressions() {
+        let variables = vec![("start", 500.0), ("x", 20.5)];
+        assert_eq!(Ok(r"\pos(41,-10)\fad(450,0) Look!".to_string()), expand_text(r"\pos(!$x * 2!,!-(5 + 5)!)\fad(!$start - 50!,0) Look!", &variables, 0));
+        assert_eq!("$unknown", expand_text("$unknown", &variables, 0).unwrap());
+        assert_eq!(Err(TemplateError { event: 3, expression: "1 +".to_string() }), expand_text("!1 +!", &variables, 3));
+        assert_eq!(Some(7.0), evaluate("1 + 2 * 3"));
+        assert_eq!(Some(1.0), evaluate("(7 % 4) - 2"));
+        assert_eq!(None, evaluate("1 / 0"));
+    }
+}
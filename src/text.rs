@@ -0,0 +1,390 @@
+/// Remove every `{...}` override block from a dialogue text, leaving only the visible text.
+pub(crate) fn strip_tags(text: &str) -> String {
+    let mut plain = String::new();
+    let mut in_block = false;
+
+    for c in text.chars() {
+        match c {
+            '{' if !in_block => in_block = true,
+            '}' if in_block => in_block = false,
+            _ if !in_block => plain.push(c),
+            _ => {},
+        }
+    }
+
+    plain
+}
+
+/// Replace the `\N` and `\n` line breaks of a dialogue text with `separator`.
+pub(crate) fn replace_line_breaks(text: &str, separator: &str) -> String {
+    text.replace("\\N", separator).replace("\\n", separator)
+}
+
+/// Split a dialogue text after each sentence and on each `\N` or `\n` line break outside of
+/// override blocks. The pieces are trimmed and the line breaks between them are removed.
+pub(crate) fn split_sentences(text: &str) -> Vec<String> {
+    let mut pieces = Vec::new();
+    let mut current = String::new();
+    let mut in_block = false;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        let next = chars.peek().copied();
+        match c {
+            '{' => in_block = true,
+            '}' => in_block = false,
+            '\\' if !in_block && matches!(next, Some('N') | Some('n')) => {
+                chars.next();
+                push_piece(&mut pieces, &mut current);
+                continue;
+            },
+            '.' | '!' | '?' | '…' if !in_block && next.is_some_and(char::is_whitespace) => {
+                current.push(c);
+                push_piece(&mut pieces, &mut current);
+                continue;
+            },
+            _ => {},
+        }
+        current.push(c);
+    }
+    push_piece(&mut pieces, &mut current);
+
+    pieces
+}
+
+fn push_piece(pieces: &mut Vec<String>, current: &mut String) {
+    let piece = current.trim();
+    if !piece.is_empty() {
+        pieces.push(piece.to_string());
+    }
+    current.clear();
+}
+
+/// The override blocks at the start of a dialogue text, e.g. `{\an8}{\i1}` of `{\an8}{\i1}Hello`.
+pub(crate) fn leading_tags(text: &str) -> &str {
+    let mut end = 0;
+    while text[end..].starts_with('{') {
+        match text[end..].find('}') {
+            Some(close) => end += close + 1,
+            None => break,
+        }
+    }
+    &text[..end]
+}
+
+/// Add override tags to the leading override block of a dialogue text, or in a new block at
+/// the start if there is none. Tags in the block are applied in order, so the added tags
+/// override earlier ones.
+pub(crate) fn insert_leading_tags(text: &str, tags: &str) -> String {
+    let leading = leading_tags(text);
+    match leading.is_empty() {
+        true => format!("{{{}}}{}", tags, text),
+        false => format!("{}{}}}{}", &leading[..leading.len() - 1], tags, &text[leading.len()..]),
+    }
+}
+
+/// Whether the text after an override block is a `\p` drawing, given whether it was before.
+pub(crate) fn drawing_mode(block: &str, drawing: bool) -> bool {
+    split_tags(block).iter()
+        .filter_map(|tag| tag.strip_prefix("\\p")?.trim().parse::<f64>().ok())
+        .next_back()
+        .map_or(drawing, |scale| scale > 0.0)
+}
+
+
+/// The style names of the `\r<style>` reset tags in a dialogue text. `\r` without a style is
+/// not included.
+pub(crate) fn reset_styles(text: &str) -> Vec<&str> {
+    let mut styles = Vec::new();
+    let mut rest = text;
+
+    while let Some(open) = rest.find('{') {
+        let close = rest[open..].find('}').map(|close| open + close).unwrap_or(rest.len());
+        for tag in rest[open + 1..close].split('\\') {
+            if let Some(style) = tag.strip_prefix('r').filter(|style| !style.trim().is_empty()) {
+                styles.push(style.trim());
+            }
+        }
+        rest = rest.get(close + 1..).unwrap_or("");
+    }
+
+    styles
+}
+
+/// Replace the style of every `\r<from>` reset tag in a dialogue text with `to`.
+pub(crate) fn replace_reset_style(text: &str, from: &str, to: &str) -> String {
+    let mut replaced = String::new();
+    let mut rest = text;
+
+    while let Some(open) = rest.find('{') {
+        let close = rest[open..].find('}').map(|close| open + close).unwrap_or(rest.len());
+        replaced.push_str(&rest[..=open]);
+        let tags: Vec<String> = rest[open + 1..close].split('\\')
+            .map(|tag| match tag.strip_prefix('r') {
+                Some(style) if style.trim() == from => format!("r{}", to),
+                _ => tag.to_string(),
+            })
+            .collect();
+        replaced.push_str(&tags.join("\\"));
+        rest = &rest[close..];
+    }
+    replaced.push_str(rest);
+
+    replaced
+}
+
+/// A part of a dialogue text: the contents of an override block without the braces, or text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Segment<'a> {
+    Tags(&'a str),
+    Text(&'a str),
+}
+
+/// Split a dialogue text into its override blocks and text. An override block which is not
+/// closed is kept as text.
+pub(crate) fn segments(text: &str) -> Vec<Segment<'_>> {
+    let mut segments = Vec::new();
+    let mut rest = text;
+
+    while !rest.is_empty() {
+        match rest.find('{').and_then(|open| rest[open..].find('}').map(|close| (open, open + close))) {
+            Some((open, close)) => {
+                if open > 0 {
+                    segments.push(Segment::Text(&rest[..open]));
+                }
+                segments.push(Segment::Tags(&rest[open + 1..close]));
+                rest = &rest[close + 1..];
+            },
+            None => {
+                segments.push(Segment::Text(rest));
+                rest = "";
+            },
+        }
+    }
+
+    segments
+}
+
+/// Split the contents of an override block into its tags, each starting with `\`.
+/// Backslashes inside parentheses such as those of `\t(\fs20)` don't start a new tag.
+/// Anything before the first tag is returned first.
+pub(crate) fn split_tags(block: &str) -> Vec<&str> {
+    let mut tags = Vec::new();
+    let mut start = 0;
+    let mut depth = 0;
+
+    for (i, c) in block.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth = (depth - 1).max(0),
+            '\\' if depth == 0 && i > start => {
+                tags.push(&block[start..i]);
+                start = i;
+            },
+            _ => {},
+        }
+    }
+    if start < block.len() {
+        tags.push(&block[start..]);
+    }
+
+    tags
+}
+
+/// The arguments between the parentheses of a tag such as `pos(10,20)`, split on commas.
+pub(crate) fn tag_arguments(tag: &str) -> Option<Vec<&str>> {
+    let open = tag.find('(')?;
+    let arguments = &tag[open + 1..];
+    let arguments = arguments.strip_suffix(')').unwrap_or(arguments);
+    Some(arguments.split(',').map(str::trim).collect())
+}
+
+/// Read a numeric field, or `default` when it is missing or not a number.
+pub(crate) fn parse_number(value: Option<&str>, default: f64) -> f64 {
+    value.and_then(|value| value.trim().parse::<f64>().ok()).unwrap_or(default)
+}
+
+/// Write a number with at most three decimals and without trailing zeros.
+pub(crate) fn format_number(number: f64) -> String {
+    let rounded = (number * 1000.0).round() / 1000.0;
+    let formatted = format!("{:.3}", rounded);
+    let formatted = formatted.trim_end_matches('0').trim_end_matches('.');
+    match formatted {
+        "-0" => "0".to_string(),
+        formatted => formatted.to_string(),
+    }
+}
+
+
+/// Decode the HTML entities used by SAMI and WebVTT files such as `&amp;` and `&#233;`.
+/// `&nbsp;` becomes a no-break space.
+pub(crate) fn decode_entities(text: &str) -> String {
+    let mut decoded = String::new();
+    let mut rest = text;
+
+    while let Some(amp) = rest.find('&') {
+        decoded.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let entity = rest.find(';').filter(|end| *end <= 10).map(|end| &rest[1..end]);
+        let character = entity.and_then(|entity| match entity.to_ascii_lowercase().as_str() {
+            "nbsp" => Some('\u{a0}'),
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            numeric => numeric.strip_prefix("#x")
+                .map(|hex| u32::from_str_radix(hex, 16).ok())
+                .unwrap_or_else(|| numeric.strip_prefix('#').and_then(|decimal| decimal.parse().ok()))
+                .and_then(char::from_u32),
+        });
+
+        match (entity, character) {
+            (Some(entity), Some(character)) => {
+                decoded.push(character);
+                rest = &rest[entity.len() + 2..];
+            },
+            _ => {
+                decoded.push('&');
+                rest = &rest[1..];
+            },
+        }
+    }
+    decoded.push_str(rest);
+
+    decoded
+}
+
+/// Names of the ASS style override tags.
+const OVERRIDE_TAGS: &[&str] = &[
+    "iclip", "xshad", "yshad", "xbord", "ybord", "alpha", "clip", "move", "fscx", "fscy", "fade",
+    "bord", "shad", "blur", "pbo", "fsp", "frx", "fry", "frz", "fax", "fay", "org", "pos", "fad",
+    "kf", "ko", "fn", "fs", "fr", "fe", "an", "be", "1c", "2c", "3c", "4c", "1a", "2a", "3a", "4a",
+    "b", "i", "u", "s", "c", "a", "k", "K", "q", "r", "t", "p",
+];
+
+/// Check if a tag from an override block (the text after its `\`) is a known ASS override tag
+/// with a valid looking parameter, e.g. `fs20`, `1c&H00FFFF&` or `pos(10,20)`.
+pub(crate) fn is_known_tag(tag: &str) -> bool {
+    tag_name(tag).is_some()
+}
+
+/// The name of a known override tag (the text after its `\`), e.g. `fscx` for `fscx120`.
+pub(crate) fn tag_name(tag: &str) -> Option<&'static str> {
+    let tag = tag.trim();
+    OVERRIDE_TAGS.iter()
+        .find(|name| match tag.strip_prefix(**name) {
+            Some(parameter) => {
+                matches!(**name, "fn" | "r")
+                    || parameter.trim().is_empty()
+                    || parameter.trim_start().starts_with(|c: char| c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | '(' | '&'))
+            },
+            None => false,
+        })
+        .copied()
+}
+
+/// Put an override tag such as `\pos(10,20)` in the leading override blocks of a dialogue text.
+/// Tags named in `replaces` are removed from those blocks, and the new tag takes the place of the
+/// first of them. Otherwise it is added at the end of the leading blocks.
+pub(crate) fn set_leading_tag(text: &str, tag: &str, replaces: &[&str]) -> String {
+    let leading = leading_tags(text);
+    let mut placed = false;
+    let mut blocks = Vec::new();
+
+    for segment in segments(leading) {
+        let Segment::Tags(block) = segment else {
+            continue;
+        };
+        let mut rebuilt = String::new();
+        for existing in split_tags(block) {
+            let name = existing.strip_prefix('\\').and_then(tag_name);
+            if name.is_some_and(|name| replaces.contains(&name)) {
+                if !placed {
+                    rebuilt.push_str(tag);
+                    placed = true;
+                }
+            } else {
+                rebuilt.push_str(existing);
+            }
+        }
+        if !rebuilt.is_empty() || block.is_empty() {
+            blocks.push(rebuilt);
+        }
+    }
+
+    let mut result: String = blocks.iter().map(|block| format!("{{{}}}", block)).collect();
+    result.push_str(&text[leading.len()..]);
+    match placed {
+        true => result,
+        false => insert_leading_tags(&result, tag),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_tags() {
+        assert_eq!("Hello Friend!", strip_tags(r"{\c&H00ffff&}Hello {\b1}Friend!"));
+        assert_eq!("Hello\nFriend", replace_line_breaks(r"Hello\NFriend", "\n"));
+    }
+
+    #[test]
+    fn test_split_sentences() {
+        assert_eq!(vec![r"{\i1}Look.", "I was gonna go easy on you", "not to hurt your feelings"],
+            split_sentences(r"{\i1}Look. I was gonna go easy on you\Nnot to hurt your feelings"));
+        assert_eq!(vec!["Mr.Smith"], split_sentences("Mr.Smith"));
+        assert_eq!(r"{\an8}{\i1}", leading_tags(r"{\an8}{\i1}Hello"));
+    }
+
+    #[test]
+    fn test_set_leading_tag() {
+        assert_eq!(Some("fscx"), tag_name("fscx120"));
+        assert_eq!(Some("clip"), tag_name("clip(0,0,10,10)"));
+        assert_eq!(None, tag_name("Note"));
+
+        assert_eq!(r"{\an8\pos(1,2)}{\i1}Hi{\pos(5,5)}", set_leading_tag(r"{\an8\move(0,0,9,9)}{\i1\pos(3,4)}Hi{\pos(5,5)}", r"\pos(1,2)", &["pos", "move"]));
+        assert_eq!(r"{\fs20\i1}{Note}Hi", set_leading_tag(r"{\fs10\i1}{Note}Hi", r"\fs20", &["fs"]));
+        assert_eq!(r"{\fs20}Hi", set_leading_tag("Hi", r"\fs20", &["fs"]));
+    }
+
+    #[test]
+    fn test_leading_tag_helpers() {
+        assert_eq!(r"{\an8}{\i1\b1}Hello", insert_leading_tags(r"{\an8}{\i1}Hello", r"\b1"));
+        assert_eq!(r"{\b1}Hello{\i1}", insert_leading_tags(r"Hello{\i1}", r"\b1"));
+        assert!(drawing_mode(r"\pos(1,2)\p1", false));
+        assert!(!drawing_mode(r"\p1\p0", true));
+        assert!(drawing_mode(r"\pos(1,2)", true));
+    }
+
+    #[test]
+    fn test_reset_styles() {
+        let text = r"{\rSigns\fs20}Look, {\r}I was {\b1\r Signs }gonna";
+
+        assert_eq!(vec!["Signs", "Signs"], reset_styles(text));
+        assert_eq!(r"{\rTitle\fs20}Look, {\r}I was {\b1\rTitle}gonna", replace_reset_style(text, "Signs", "Title"));
+    }
+
+    #[test]
+    fn test_segments() {
+        let text = r"{\pos(10,20)\t(0,500,\fs20\bord2)}Look{\i1}, I";
+
+        assert_eq!(vec![Segment::Tags(r"\pos(10,20)\t(0,500,\fs20\bord2)"), Segment::Text("Look"), Segment::Tags(r"\i1"), Segment::Text(", I")], segments(text));
+        assert_eq!(vec![r"\pos(10,20)", r"\t(0,500,\fs20\bord2)"], split_tags(r"\pos(10,20)\t(0,500,\fs20\bord2)"));
+        assert_eq!(Some(vec!["10", "20"]), tag_arguments(r"\pos(10, 20)"));
+        assert_eq!("1.5", format_number(1.5000001));
+        assert_eq!("-3", format_number(-3.0));
+    }
+
+    #[test]
+    fn test_is_known_tag() {
+        assert!(is_known_tag("fs20"));
+        assert!(is_known_tag("1c&H00FFFF&"));
+        assert!(is_known_tag("pos(10,20)"));
+        assert!(is_known_tag("fnArial"));
+        assert!(!is_known_tag("bogus"));
+    }
+}
@@ -0,0 +1,114 @@
+/// Parse a timestamp into milliseconds.
+///
+/// Accepts the `H:MM:SS.cc` form used by `.ass` files as well as the shorter `MM:SS.cc` form and
+/// the `H:MM:SS:cc` form (hundredths after a colon). The fractional part can have any number of
+/// digits so `0:00:01.5`, `0:00:01.50` and `0:00:01.500` are all one and a half seconds.
+pub(crate) fn parse_time(value: &str) -> Option<i64> {
+    let value = value.trim();
+    let (negative, value) = match value.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, value),
+    };
+    let parts: Vec<&str> = value.split(':').collect();
+
+    let (clock, fraction): (Vec<&str>, Option<&str>) = match parts.len() {
+        4 => (parts[..3].to_vec(), Some(parts[3])),
+        2 | 3 => {
+            let last = parts[parts.len() - 1];
+            match last.split_once(['.', ',']) {
+                Some((seconds, fraction)) => {
+                    let mut clock = parts[..parts.len() - 1].to_vec();
+                    clock.push(seconds);
+                    (clock, Some(fraction))
+                },
+                None => (parts.clone(), None),
+            }
+        },
+        _ => return None,
+    };
+
+    parse_clock(&clock, fraction).map(|ms| if negative { -ms } else { ms })
+}
+
+fn parse_clock(clock: &[&str], fraction: Option<&str>) -> Option<i64> {
+    let mut ms: i64 = 0;
+    for part in clock {
+        let value: i64 = part.trim().parse().ok()?;
+        ms = ms * 60 + value;
+    }
+    ms *= 1000;
+
+    if let Some(fraction) = fraction {
+        let fraction = fraction.trim();
+        if fraction.is_empty() || !fraction.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        let mut digits: String = fraction.chars().take(3).collect();
+        while digits.len() < 3 {
+            digits.push('0');
+        }
+        ms += digits.parse::<i64>().ok()?;
+    }
+
+    Some(ms)
+}
+
+/// Format milliseconds as an `.ass` timestamp (`H:MM:SS.cc`), rounding to the nearest hundredth.
+pub(crate) fn format_time(ms: i64) -> String {
+    let ms = ms.max(0);
+    let centis = (ms + 5) / 10;
+    let hours = centis / 360_000;
+    let minutes = (centis / 6000) % 60;
+    let seconds = (centis / 100) % 60;
+    let centis = centis % 100;
+
+    format!("{}:{:02}:{:02}.{:02}", hours, minutes, seconds, centis)
+}
+
+/// Format milliseconds as `H:MM:SS.mmm`, which is what YouTube `.sbv` captions use.
+pub(crate) fn format_time_millis(ms: i64) -> String {
+    let ms = ms.max(0);
+    let hours = ms / 3_600_000;
+    let minutes = (ms / 60_000) % 60;
+    let seconds = (ms / 1000) % 60;
+    let millis = ms % 1000;
+
+    format!("{}:{:02}:{:02}.{:03}", hours, minutes, seconds, millis)
+}
+
+/// Format milliseconds as `HH:MM:SS.mmm` using `separator` before the milliseconds, which is `,`
+/// for SubRip and `.` for WebVTT.
+pub(crate) fn format_time_hms(ms: i64, separator: char) -> String {
+    let ms = ms.max(0);
+    let hours = ms / 3_600_000;
+    let minutes = (ms / 60_000) % 60;
+    let seconds = (ms / 1000) % 60;
+    let millis = ms % 1000;
+
+    format!("{:02}:{:02}:{:02}{}{:03}", hours, minutes, seconds, separator, millis)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_time() {
+        assert_eq!(Some(1500), parse_time("0:00:01.50"));
+        assert_eq!(Some(500), parse_time("00:00.50"));
+        assert_eq!(Some(3_723_450), parse_time("1:02:03.45"));
+        assert_eq!(Some(2000), parse_time("0:00:02:00"));
+        assert_eq!(Some(734), parse_time("00:00:00,734"));
+        assert_eq!(None, parse_time("hello"));
+    }
+
+    #[test]
+    fn test_format_time() {
+        assert_eq!("0:00:01.50", format_time(1500));
+        assert_eq!("1:02:03.45", format_time(3_723_450));
+        assert_eq!("0:00:00.73", format_time(734));
+        assert_eq!("0:00:00.734", format_time_millis(734));
+        assert_eq!("00:00:00,734", format_time_hms(734, ','));
+    }
+}
@@ -0,0 +1,398 @@
+//! # Timing
+//!
+//! Find dialogues which overlap on screen, fix the timing between them and keep dialogues on
+//! screen for a sensible time.
+//!
+//! Only dialogues on the same layer and with the same style are compared, as dialogues with
+//! different styles are usually placed apart on screen, and comments are never changed. Every
+//! fixer returns a `TimingChange` for each dialogue it changed.
+//!
+//! ```rust
+//! use ass_parser::{Dialogue, Events};
+//!
+//! let mut events = Events::default();
+//! events.add_dialogue(Dialogue::default().set_start("0:00:01.00").set_end("0:00:03.00"))
+//!     .add_dialogue(Dialogue::default().set_start("0:00:02.50").set_end("0:00:04.00"));
+//!
+//! assert_eq!(1, events.find_overlaps().len());
+//! for change in events.fix_overlaps() {
+//!     println!("{}", change);
+//! }
+//! assert!(events.find_overlaps().is_empty());
+//! ```
+
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::Events;
+use crate::reading_speed::{count_characters, WhitespacePolicy};
+use crate::text::{leading_tags, split_sentences};
+use crate::timestamp::format_time;
+
+/// Two dialogues on the same layer and style which are on screen at the same time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Overlap {
+    /// Index of the dialogue which starts first.
+    pub first: usize,
+    /// Index of the dialogue which starts while `first` is still on screen.
+    pub second: usize,
+    /// How long both dialogues are on screen, in milliseconds.
+    pub duration_ms: i64,
+}
+
+/// The times of a dialogue before and after it was changed by a fixer, in milliseconds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimingChange {
+    /// Index of the dialogue which was changed.
+    pub event: usize,
+    /// Start time before the change.
+    pub old_start: i64,
+    /// End time before the change.
+    pub old_end: i64,
+    /// Start time after the change.
+    pub new_start: i64,
+    /// End time after the change.
+    pub new_end: i64,
+}
+
+impl fmt::Display for TimingChange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "event {}: {} --> {} changed to {} --> {}", self.event,
+            format_time(self.old_start), format_time(self.old_end),
+            format_time(self.new_start), format_time(self.new_end))
+    }
+}
+
+/// A change made by `Events::normalize_durations`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DurationEdit {
+    /// A long dialogue was split into `parts` dialogues, starting at index `event`.
+    Split {
+        /// Index of the first part.
+        event: usize,
+        /// Number of dialogues the dialogue was split into.
+        parts: usize,
+    },
+    /// A short dialogue was shown for longer.
+    Extended(TimingChange),
+}
+
+impl fmt::Display for DurationEdit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DurationEdit::Split { event, parts } => write!(f, "event {}: split into {} dialogues", event, parts),
+            DurationEdit::Extended(change) => write!(f, "{}", change),
+        }
+    }
+}
+
+/// Index, start and end time in milliseconds of a dialogue.
+pub(crate) type TimedEvent = (usize, i64, i64);
+
+impl Events {
+    /// Find every pair of dialogues on the same layer and style which overlap in time.
+    /// Dialogues without valid times are ignored.
+    pub fn find_overlaps(&self) -> Vec<Overlap> {
+        let mut overlaps = Vec::new();
+
+        for group in self.timing_groups() {
+            for (i, &(first, _, first_end)) in group.iter().enumerate() {
+                for &(second, second_start, second_end) in &group[i + 1..] {
+                    if second_start >= first_end {
+                        break;
+                    }
+                    overlaps.push(Overlap {
+                        first,
+                        second,
+                        duration_ms: first_end.min(second_end) - second_start,
+                    });
+                }
+            }
+        }
+
+        overlaps.sort_by_key(|overlap| (overlap.second, overlap.first));
+        overlaps
+    }
+
+    /// End each dialogue when the first dialogue on the same layer and style which starts after it
+    /// starts. Dialogues which start at the same time can't be fixed this way, so they still
+    /// overlap each other.
+    pub fn fix_overlaps(&mut self) -> Vec<TimingChange> {
+        self.adjust_ends(|start, end, next_start| {
+            (end > next_start && next_start > start).then_some(next_start)
+        })
+    }
+
+    /// Close the gaps shorter than `threshold_ms` between dialogues on the same layer and style
+    /// by extending the earlier dialogue until the next one starts.
+    pub fn close_gaps(&mut self, threshold_ms: i64) -> Vec<TimingChange> {
+        self.adjust_ends(|_, end, next_start| {
+            let gap = next_start - end;
+            (gap > 0 && gap < threshold_ms).then_some(next_start)
+        })
+    }
+
+    /// Make sure there are at least `min_gap_ms` milliseconds between dialogues on the same layer
+    /// and style by ending the earlier dialogue sooner. Overlapping dialogues are fixed as well.
+    /// A dialogue is never shortened to end before it starts.
+    pub fn enforce_min_gap(&mut self, min_gap_ms: i64) -> Vec<TimingChange> {
+        self.adjust_ends(|start, end, next_start| {
+            let new_end = next_start - min_gap_ms;
+            (end > new_end && new_end > start).then_some(new_end)
+        })
+    }
+
+    /// Keep every dialogue on screen for between `min_ms` and `max_ms` milliseconds.
+    ///
+    /// Dialogues longer than `max_ms` are split after sentences and at `\N` line breaks, with
+    /// the time shared out by the length of each part. Override blocks at the start of the text
+    /// are repeated on every part. Dialogues which can't be split are left alone.
+    /// Dialogues shorter than `min_ms` are extended, but never past the start of the next
+    /// dialogue on the same layer and style.
+    ///
+    /// The indices in the returned edits refer to the dialogues after all of the edits.
+    pub fn normalize_durations(&mut self, min_ms: i64, max_ms: i64) -> Vec<DurationEdit> {
+        let mut edits = Vec::new();
+        let mut dialogues = Vec::new();
+
+        for dialogue in self.dialogues.dialogues.drain(..) {
+            let (Some(start), Some(end), false) = (dialogue.get_start_ms(), dialogue.get_end_ms(), dialogue.is_comment()) else {
+                dialogues.push(dialogue);
+                continue;
+            };
+            let text = dialogue.get_text().unwrap_or("");
+            let pieces = split_sentences(text);
+            let parts = (((end - start) as f64 / max_ms as f64).ceil() as usize).min(pieces.len());
+            if end - start <= max_ms || parts < 2 {
+                dialogues.push(dialogue);
+                continue;
+            }
+
+            let tags = leading_tags(text);
+            let weights: Vec<usize> = pieces.iter()
+                .map(|piece| count_characters(piece, WhitespacePolicy::Count).max(1))
+                .collect();
+            let total: usize = weights.iter().sum();
+
+            // put each piece in the part where its middle falls, so the parts have similar lengths
+            let mut groups: Vec<(usize, Vec<&str>, usize)> = Vec::new();
+            let mut before = 0;
+            for (piece, weight) in pieces.iter().zip(weights) {
+                let part = (before * 2 + weight) * parts / (total * 2);
+                match groups.last_mut() {
+                    Some((last_part, group, group_weight)) if *last_part == part => {
+                        group.push(piece);
+                        *group_weight += weight;
+                    },
+                    _ => groups.push((part, vec![piece], weight)),
+                }
+                before += weight;
+            }
+            if groups.len() < 2 {
+                dialogues.push(dialogue);
+                continue;
+            }
+
+            edits.push(DurationEdit::Split { event: dialogues.len(), parts: groups.len() });
+            let mut part_start = start;
+            let mut characters = 0;
+            for (i, (_, group, weight)) in groups.iter().enumerate() {
+                let mut part_text = group.join(" ");
+                characters += weight;
+                let part_end = match i + 1 == groups.len() {
+                    true => end,
+                    false => (start + (end - start) * characters as i64 / total as i64 + 5) / 10 * 10,
+                };
+                if i > 0 && !part_text.starts_with(tags) {
+                    part_text.insert_str(0, tags);
+                }
+
+                let mut part = dialogue.clone();
+                part.event.start = Some(format_time(part_start));
+                part.event.end = Some(format_time(part_end));
+                part.event.text = Some(part_text);
+                dialogues.push(part);
+                part_start = part_end;
+            }
+        }
+        self.dialogues.dialogues = dialogues;
+
+        for group in self.timing_groups() {
+            for (i, &(event, start, end)) in group.iter().enumerate() {
+                let mut new_end = start + min_ms;
+                if let Some(&(_, next_start, _)) = group.get(i + 1) {
+                    new_end = new_end.min(next_start);
+                }
+                if end < start || new_end <= end {
+                    continue;
+                }
+
+                self.dialogues.dialogues[event].event.end = Some(format_time(new_end));
+                edits.push(DurationEdit::Extended(TimingChange {
+                    event,
+                    old_start: start,
+                    old_end: end,
+                    new_start: start,
+                    new_end,
+                }));
+            }
+        }
+
+        edits.sort_by_key(|edit| match edit {
+            DurationEdit::Split { event, .. } => *event,
+            DurationEdit::Extended(change) => change.event,
+        });
+        edits
+    }
+
+    /// Indices, start and end times of the dialogues with valid times, grouped by layer and style
+    /// and sorted by start time. Comments are left out.
+    pub(crate) fn timing_groups(&self) -> Vec<Vec<TimedEvent>> {
+        let mut groups: HashMap<(&str, &str), Vec<TimedEvent>> = HashMap::new();
+
+        for (i, dialogue) in self.iter().enumerate().filter(|(_, dialogue)| !dialogue.is_comment()) {
+            if let (Some(start), Some(end)) = (dialogue.get_start_ms(), dialogue.get_end_ms()) {
+                let key = (dialogue.get_layer().unwrap_or("0"), dialogue.get_style().unwrap_or(""));
+                groups.entry(key).or_default().push((i, start, end));
+            }
+        }
+
+        let mut groups: Vec<_> = groups.into_values().collect();
+        for group in groups.iter_mut() {
+            group.sort_by_key(|&(i, start, _)| (start, i));
+        }
+        groups.sort_by_key(|group| group[0].0);
+        groups
+    }
+
+    /// Call `new_end(start, end, next_start)` for every dialogue followed by another on the same
+    /// layer and style, and set the end time it returns. `next_start` is the first start after the
+    /// start of the dialogue, so dialogues starting at the same time don't hide the ones after them.
+    fn adjust_ends(&mut self, new_end: impl Fn(i64, i64, i64) -> Option<i64>) -> Vec<TimingChange> {
+        let mut changes = Vec::new();
+
+        for group in self.timing_groups() {
+            for (i, &(event, start, end)) in group.iter().enumerate() {
+                let Some(&(_, next_start, _)) = group[i + 1..].iter().find(|(_, next_start, _)| *next_start > start) else {
+                    continue;
+                };
+                let Some(end_ms) = new_end(start, end, next_start).filter(|end_ms| *end_ms != end) else {
+                    continue;
+                };
+                self.dialogues.dialogues[event].event.end = Some(format_time(end_ms));
+                changes.push(TimingChange {
+                    event,
+                    old_start: start,
+                    old_end: end,
+                    new_start: start,
+                    new_end: end_ms,
+                });
+            }
+        }
+
+        changes.sort_by_key(|change| change.event);
+        changes
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Dialogue;
+
+    fn events(times: &[(&str, &str, &str)]) -> Events {
+        Events::from_dialogues(times.iter()
+            .map(|(start, end, style)| Dialogue::default().set_start(start).set_end(end).set_style(style))
+            .collect())
+    }
+
+    fn ends(events: &Events) -> Vec<&str> {
+        events.iter().map(|dialogue| dialogue.get_end().unwrap()).collect()
+    }
+
+    #[test]
+    fn test_find_overlaps() {
+        let events = events(&[
+            ("0:00:01.00", "0:00:05.00", "Default"),
+            ("0:00:02.00", "0:00:03.00", "Default"),
+            ("0:00:02.00", "0:00:03.00", "Signs"),
+            ("0:00:04.50", "0:00:06.00", "Default"),
+        ]);
+
+        assert_eq!(vec![
+            Overlap { first: 0, second: 1, duration_ms: 1000 },
+            Overlap { first: 0, second: 3, duration_ms: 500 },
+        ], events.find_overlaps());
+    }
+
+    #[test]
+    fn test_fix_overlaps() {
+        let mut events = events(&[
+            ("0:00:01.00", "0:00:05.00", "Default"),
+            ("0:00:02.00", "0:00:03.00", "Default"),
+            ("0:00:04.50", "0:00:06.00", "Default"),
+        ]);
+        let changes = events.fix_overlaps();
+
+        assert_eq!(vec![TimingChange { event: 0, old_start: 1000, old_end: 5000, new_start: 1000, new_end: 2000 }], changes);
+        assert!(events.find_overlaps().is_empty());
+    }
+
+    #[test]
+    fn test_fix_overlaps_same_start() {
+        // the first two start together, and the long one still has to end before the third
+        let mut events = events(&[
+            ("0:00:01.00", "0:00:05.00", "Default"),
+            ("0:00:01.00", "0:00:02.00", "Default"),
+            ("0:00:03.00", "0:00:04.00", "Default"),
+        ]);
+        let changes = events.fix_overlaps();
+
+        assert_eq!(vec![TimingChange { event: 0, old_start: 1000, old_end: 5000, new_start: 1000, new_end: 3000 }], changes);
+        let overlaps: Vec<(usize, usize)> = events.find_overlaps().iter().map(|overlap| (overlap.first, overlap.second)).collect();
+        assert_eq!(vec![(0, 1)], overlaps);
+    }
+
+    #[test]
+    fn test_normalize_durations() {
+        let mut events = Events::from_dialogues(vec![
+            Dialogue::default().set_start("0:00:00.00").set_end("0:00:20.00")
+                .set_text(r"{\an8}Look, I was gonna go easy on you. Not to hurt your feelings\NBut I'm only going to get this one chance"),
+            Dialogue::default().set_start("0:00:20.00").set_end("0:00:20.30").set_text("Hey"),
+            Dialogue::default().set_start("0:00:20.50").set_end("0:00:21.00").set_text("Hey"),
+            Dialogue::default().set_start("0:00:30.00").set_end("0:00:50.00").set_text("No sentences to split here"),
+        ]);
+        let edits = events.normalize_durations(1000, 8000);
+        let times: Vec<(&str, &str, &str)> = events.iter()
+            .map(|dialogue| (dialogue.get_start().unwrap(), dialogue.get_end().unwrap(), dialogue.get_text().unwrap()))
+            .collect();
+
+        assert_eq!(vec![
+            ("0:00:00.00", "0:00:06.67", r"{\an8}Look, I was gonna go easy on you."),
+            ("0:00:06.67", "0:00:11.72", r"{\an8}Not to hurt your feelings"),
+            ("0:00:11.72", "0:00:20.00", r"{\an8}But I'm only going to get this one chance"),
+            ("0:00:20.00", "0:00:20.50", "Hey"),
+            ("0:00:20.50", "0:00:21.50", "Hey"),
+            ("0:00:30.00", "0:00:50.00", "No sentences to split here"),
+        ], times);
+        assert_eq!(DurationEdit::Split { event: 0, parts: 3 }, edits[0]);
+        assert_eq!(3, edits.len());
+    }
+
+    #[test]
+    fn test_close_gaps_and_min_gap() {
+        let mut events = events(&[
+            ("0:00:01.00", "0:00:01.90", "Default"),
+            ("0:00:02.00", "0:00:03.00", "Default"),
+            ("0:00:04.00", "0:00:05.00", "Default"),
+            ("0:00:05.05", "0:00:06.00", "Default"),
+        ]);
+
+        assert_eq!(2, events.close_gaps(200).len());
+        assert_eq!(vec!["0:00:02.00", "0:00:03.00", "0:00:05.05", "0:00:06.00"], ends(&events));
+
+        assert_eq!(2, events.enforce_min_gap(100).len());
+        assert_eq!(vec!["0:00:01.90", "0:00:03.00", "0:00:04.95", "0:00:06.00"], ends(&events));
+    }
+}
@@ -0,0 +1,171 @@
+use crate::{AssFile, Dialogue, Events};
+use crate::timestamp::{format_time, format_time_hms, parse_time};
+use crate::text::{decode_entities, replace_line_breaks, strip_tags};
+
+/// WebVTT markup which has an override code equivalent.
+const VTT_TAGS: [(&str, &str); 6] = [
+    ("<i>", "{\\i1}"),
+    ("</i>", "{\\i0}"),
+    ("<b>", "{\\b1}"),
+    ("</b>", "{\\b0}"),
+    ("<u>", "{\\u1}"),
+    ("</u>", "{\\u0}"),
+];
+
+/// Parse the contents of a WebVTT (`.vtt`) file.
+///
+/// `<i>`, `<b>` and `<u>` become override codes, the speaker of a `<v Speaker>` voice span becomes
+/// the dialogue's Name and the `align` and `line` cue settings become an `\an` override. `NOTE`,
+/// `STYLE` and `REGION` blocks are skipped.
+pub(crate) fn parse_vtt(contents: &str) -> AssFile {
+    let mut dialogues = Vec::new();
+    let mut block: Vec<&str> = Vec::new();
+
+    let contents = contents.trim_start_matches('\u{feff}');
+    for line in contents.lines().chain(std::iter::once("")) {
+        let line = line.trim_end_matches('\r');
+        if !line.trim().is_empty() {
+            block.push(line);
+            continue;
+        }
+        if let Some(dialogue) = parse_cue(&block) {
+            dialogues.push(dialogue);
+        }
+        block.clear();
+    }
+
+    AssFile::from_events(Events::from_dialogues(dialogues))
+}
+
+fn parse_cue(block: &[&str]) -> Option<Dialogue> {
+    let timing_line = block.iter().position(|line| line.contains("-->"))?;
+    if block[0].starts_with("WEBVTT") || block[0].starts_with("NOTE") {
+        return None;
+    }
+
+    let (start, rest) = block[timing_line].split_once("-->")?;
+    let mut rest = rest.split_whitespace();
+    let start = parse_time(start)?;
+    let end = parse_time(rest.next()?)?;
+    let alignment = cue_alignment(rest.collect());
+
+    let mut name = String::new();
+    let mut lines = Vec::new();
+    for line in &block[timing_line + 1..] {
+        let (voice, text) = convert_markup(line);
+        if name.is_empty() {
+            name = voice;
+        }
+        lines.push(text);
+    }
+
+    let text = match alignment {
+        Some(alignment) => format!("{{\\an{}}}{}", alignment, lines.join("\\N")),
+        None => lines.join("\\N"),
+    };
+    Some(Dialogue::default()
+        .set_start(&format_time(start))
+        .set_end(&format_time(end))
+        .set_name(&name)
+        .set_text(&text))
+}
+
+/// Turn the `align` and `line` cue settings into a numpad alignment.
+fn cue_alignment(settings: Vec<&str>) -> Option<u8> {
+    let mut column = 2;
+    let mut row = 0;
+
+    for setting in settings {
+        match setting.split_once(':') {
+            Some(("align", "start" | "left")) => column = 1,
+            Some(("align", "end" | "right")) => column = 3,
+            Some(("line", line)) => {
+                let line = line.split(',').next().unwrap_or("");
+                let top = match line.strip_suffix('%') {
+                    Some(percent) => percent.parse::<f64>().map(|percent| percent < 50.0).unwrap_or(false),
+                    None => line.parse::<i64>().map(|line| (0..6).contains(&line)).unwrap_or(false),
+                };
+                if top {
+                    row = 6;
+                }
+            },
+            _ => {},
+        }
+    }
+
+    if column == 2 && row == 0 {
+        return None;
+    }
+    Some(column + row)
+}
+
+/// Convert a line of cue text into dialogue text and return it with the speaker of its voice span.
+fn convert_markup(line: &str) -> (String, String) {
+    let mut voice = String::new();
+    let mut text = String::new();
+    let mut rest = line;
+
+    while let Some(open) = rest.find('<') {
+        text.push_str(&rest[..open]);
+        let close = rest[open..].find('>').map(|close| open + close + 1).unwrap_or(rest.len());
+        let tag = &rest[open..close];
+
+        if let Some((_, code)) = VTT_TAGS.iter().find(|(vtt, _)| vtt.eq_ignore_ascii_case(tag)) {
+            text.push_str(code);
+        } else if let Some(speaker) = tag.strip_prefix("<v").filter(|speaker| speaker.starts_with([' ', '.'])) {
+            let speaker = speaker.trim_end_matches('>');
+            voice = speaker.split_once(' ').map(|(_, name)| name.trim()).unwrap_or("").to_string();
+        }
+        rest = &rest[close..];
+    }
+    text.push_str(rest);
+
+    (voice, decode_entities(&text).replace('\u{a0}', "\\h"))
+}
+
+/// Convert the dialogues of an `AssFile` into a WebVTT file.
+/// Style override codes are removed and the Name of a dialogue becomes a `<v>` voice span.
+pub(crate) fn stringify_vtt(ass_file: &AssFile) -> String {
+    let mut contents = String::from("WEBVTT\n\n");
+
+    for dialogue in ass_file.components.events.iter().filter(|dialogue| !dialogue.is_comment()) {
+        let (Some(start), Some(end)) = (dialogue.get_start_ms(), dialogue.get_end_ms()) else {
+            continue;
+        };
+        let text = strip_tags(dialogue.get_text().unwrap_or(""))
+            .replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+            .replace("\\h", "&nbsp;");
+
+        contents.push_str(&format!("{} --> {}\n", format_time_hms(start, '.'), format_time_hms(end, '.')));
+        if let Some(name) = dialogue.get_name().filter(|name| !name.is_empty()) {
+            contents.push_str(&format!("<v {}>", name));
+        }
+        contents.push_str(&replace_line_breaks(&text, "\n"));
+        contents.push_str("\n\n");
+    }
+
+    contents
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vtt_round_trip() {
+        let contents = "WEBVTT\n\nNOTE this is a comment\n\n1\n00:01.000 --> 00:04.500 align:start\n<v Eminem>Look, I was <i>gonna</i> go easy\non you &amp; not\n\n00:00:05.000 --> 00:00:06.000\nHello\n";
+        let ass_file = parse_vtt(contents);
+        let dialogues: Vec<&Dialogue> = ass_file.components.events.iter().collect();
+
+        assert_eq!(2, dialogues.len());
+        assert_eq!(Some("Eminem"), dialogues[0].get_name());
+        assert_eq!(Some("{\\an1}Look, I was {\\i1}gonna{\\i0} go easy\\Non you & not"), dialogues[0].get_text());
+        assert_eq!(Some("0:00:04.50"), dialogues[0].get_end());
+
+        let vtt = stringify_vtt(&ass_file);
+        assert_eq!("WEBVTT\n\n00:00:01.000 --> 00:00:04.500\n<v Eminem>Look, I was gonna go easy\non you &amp; not\n\n00:00:05.000 --> 00:00:06.000\nHello\n\n", vtt);
+    }
+}
@@ -0,0 +1,146 @@
+#!/usr/bin/env python3
+"""Generate the Box Sans test fonts.
+
+Every glyph is a filled box so widths and pixels are easy to work out in tests. There are 1000
+units per em and the Windows ascender and descender are 800 and 200, so one unit is a thousandth
+of the font size. Advances of the regular and italic fonts:
+
+    space i l , . ' !          250
+    m, w, M, W                 750
+    everything else            500
+
+The bold font adds 100 to every advance except the space. Boxes are drawn from 50 units after
+the start of the glyph to 50 units before its end, and from the baseline to 700 units up.
+
+Run it from this directory: python3 make_fonts.py
+"""
+
+import struct
+
+UNITS_PER_EM = 1000
+ASCENDER = 800
+DESCENDER = 200
+BOX_TOP = 700
+BOX_INSET = 50
+
+CHARS = " " + "".join(map(chr, range(ord("A"), ord("Z") + 1))) + "".join(map(chr, range(ord("a"), ord("z") + 1))) \
+    + "0123456789" + ",.!?'-"
+
+
+def advance(c, bold):
+    if c == " ":
+        return 250
+    if c in "il',.!":
+        width = 250
+    elif c in "mwMW":
+        width = 750
+    else:
+        width = 500
+    return width + (100 if bold else 0)
+
+
+def checksum(data):
+    data += b"\0" * (-len(data) % 4)
+    return sum(struct.unpack(">%dI" % (len(data) // 4), data)) & 0xFFFFFFFF
+
+
+def box_glyph(width):
+    x0, x1, y0, y1 = BOX_INSET, width - BOX_INSET, 0, BOX_TOP
+    points = [(x0, y0), (x0, y1), (x1, y1), (x1, y0)]
+    data = struct.pack(">hhhhh", 1, x0, y0, x1, y1)
+    data += struct.pack(">HH", 3, 0)
+    data += bytes([0x01] * 4)
+    x, y = 0, 0
+    for px, _ in points:
+        data += struct.pack(">h", px - x)
+        x = px
+    for _, py in points:
+        data += struct.pack(">h", py - y)
+        y = py
+    return data + b"\0" * (-len(data) % 4)
+
+
+def cmap_table():
+    codes = [ord(c) for c in CHARS]
+    segments = sorted((code, code, index + 1) for index, code in enumerate(codes)) + [(0xFFFF, 0xFFFF, 0)]
+    count = len(segments)
+    search_range = 2 * 2 ** (count.bit_length() - 1)
+    subtable = struct.pack(">HHHH", count * 2, search_range, count.bit_length() - 1, count * 2 - search_range)
+    subtable += b"".join(struct.pack(">H", end) for _, end, _ in segments)
+    subtable += struct.pack(">H", 0)
+    subtable += b"".join(struct.pack(">H", start) for start, _, _ in segments)
+    subtable += b"".join(struct.pack(">H", (glyph - start) & 0xFFFF if glyph else 1) for start, _, glyph in segments)
+    subtable += b"".join(struct.pack(">H", 0) for _ in segments)
+    subtable = struct.pack(">HHH", 4, len(subtable) + 6, 0) + subtable
+    return struct.pack(">HHHHI", 0, 1, 3, 1, 12) + subtable
+
+
+def name_table(names):
+    records, strings = b"", b""
+    for name_id, text in names:
+        encoded = text.encode("utf-16-be")
+        records += struct.pack(">HHHHHH", 3, 1, 0x409, name_id, len(encoded), len(strings))
+        strings += encoded
+    return struct.pack(">HHH", 0, len(names), 6 + len(records)) + records + strings
+
+
+def font(style):
+    bold, italic = style in ("Bold",), style in ("Italic",)
+    widths = [500] + [advance(c, bold) for c in CHARS]
+    glyphs = [box_glyph(500)] + [b"" if c == " " else box_glyph(advance(c, bold)) for c in CHARS]
+    count = len(glyphs)
+
+    offsets, glyf = [], b""
+    for glyph in glyphs:
+        offsets.append(len(glyf))
+        glyf += glyph
+    offsets.append(len(glyf))
+    loca = b"".join(struct.pack(">I", offset) for offset in offsets)
+    hmtx = b"".join(struct.pack(">Hh", width, BOX_INSET) for width in widths)
+
+    mac_style = (1 if bold else 0) | (2 if italic else 0)
+    head = struct.pack(">IIIIHHqqhhhhHHhhh", 0x00010000, 0x00010000, 0, 0x5F0F3CF5, 0x000B, UNITS_PER_EM, 0, 0,
+                       0, 0, max(widths), BOX_TOP, mac_style, 8, 2, 1, 0)
+    hhea = struct.pack(">IhhhHhhhhhhhhhhhH", 0x00010000, ASCENDER, -DESCENDER, 0, max(widths), BOX_INSET, BOX_INSET,
+                       max(widths) - BOX_INSET, 1, 0, 0, 0, 0, 0, 0, 0, count)
+    maxp = struct.pack(">IHHHHHHHHHHHHHH", 0x00010000, count, 4, 1, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0)
+    fs_selection = (0x20 if bold else 0) | (0x01 if italic else 0) or 0x40
+    os2 = struct.pack(">HhHHH", 4, 500, 700 if bold else 400, 5, 0)
+    os2 += struct.pack(">10h", 650, 600, 0, 75, 650, 600, 0, 350, 50, 250)
+    os2 += struct.pack(">h", 0) + bytes(10) + bytes(16) + b"BOXS"
+    os2 += struct.pack(">HHHhhhHH", fs_selection, 0x20, 0x7A, ASCENDER, -DESCENDER, 0, ASCENDER, DESCENDER)
+    os2 += struct.pack(">II", 1, 0) + struct.pack(">hhHHH", 500, BOX_TOP, 0, 0x20, 1)
+    post = struct.pack(">IihhIIIII", 0x00030000, -12 << 16 if italic else 0, -100, 50, 0, 0, 0, 0, 0)
+    full_name = "Box Sans" if style == "Regular" else "Box Sans " + style
+    name = name_table([
+        (0, "Copyright 2026 The ass_parser Authors"),
+        (1, "Box Sans"),
+        (2, style),
+        (4, full_name),
+        (6, full_name.replace(" ", "")),
+        (13, "This Font Software is licensed under the SIL Open Font License, Version 1.1."),
+        (14, "https://openfontlicense.org"),
+    ])
+
+    tables = sorted({
+        b"OS/2": os2, b"cmap": cmap_table(), b"glyf": glyf, b"head": head, b"hhea": hhea,
+        b"hmtx": hmtx, b"loca": loca, b"maxp": maxp, b"name": name, b"post": post,
+    }.items())
+    entry_selector = len(tables).bit_length() - 1
+    search_range = 16 * 2 ** entry_selector
+    data = struct.pack(">IHHHH", 0x00010000, len(tables), search_range, entry_selector, len(tables) * 16 - search_range)
+    offset = len(data) + 16 * len(tables)
+    body = b""
+    for tag, table in tables:
+        data += struct.pack(">4sIII", tag, checksum(table), offset + len(body), len(table))
+        body += table + b"\0" * (-len(table) % 4)
+    data += body
+
+    adjustment = (0xB1B0AFBA - checksum(data)) & 0xFFFFFFFF
+    head_offset = data.index(head)
+    return data[:head_offset + 8] + struct.pack(">I", adjustment) + data[head_offset + 12:]
+
+
+for style in ("Regular", "Bold", "Italic"):
+    with open("BoxSans-%s.ttf" % style, "wb") as file:
+        file.write(font(style))